@@ -0,0 +1,23 @@
+-- Add migration script here
+BEGIN;
+
+CREATE TABLE IF NOT EXISTS public.twitch_live_settings (
+    guild_id bigint NOT NULL,
+    delete_offline_message boolean DEFAULT false NOT NULL,
+    CONSTRAINT twitch_live_settings_pk PRIMARY KEY (guild_id),
+    CONSTRAINT twitch_live_settings_guilds_guild_id_fk FOREIGN KEY (guild_id) REFERENCES public.guilds(guild_id)
+);
+
+CREATE TABLE IF NOT EXISTS public.twitch_live_messages (
+    stream_id text NOT NULL,
+    broadcaster_id text NOT NULL,
+    guild_id bigint NOT NULL,
+    channel_id bigint NOT NULL,
+    message_id bigint NOT NULL,
+    started_at timestamp with time zone NOT NULL,
+    CONSTRAINT twitch_live_messages_pk PRIMARY KEY (stream_id, channel_id),
+    CONSTRAINT twitch_live_messages_guilds_guild_id_fk FOREIGN KEY (guild_id) REFERENCES public.guilds(guild_id)
+);
+CREATE INDEX IF NOT EXISTS twitch_live_messages_broadcaster_id_index ON public.twitch_live_messages (broadcaster_id);
+
+COMMIT;
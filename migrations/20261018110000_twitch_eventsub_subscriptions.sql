@@ -0,0 +1,15 @@
+-- Add migration script here
+BEGIN;
+
+CREATE TABLE IF NOT EXISTS public.twitch_eventsub_subscriptions (
+    subscription_id text NOT NULL,
+    broadcaster_id text NOT NULL,
+    event_type text NOT NULL,
+    status text NOT NULL,
+    created_at timestamp with time zone DEFAULT now() NOT NULL,
+    revoked_at timestamp with time zone,
+    CONSTRAINT twitch_eventsub_subscriptions_pk PRIMARY KEY (subscription_id)
+);
+CREATE INDEX IF NOT EXISTS twitch_eventsub_subscriptions_broadcaster_id_index ON public.twitch_eventsub_subscriptions (broadcaster_id);
+
+COMMIT;
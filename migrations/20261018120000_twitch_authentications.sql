@@ -0,0 +1,37 @@
+-- Add migration script here
+BEGIN;
+
+CREATE TABLE IF NOT EXISTS public.twitch_authentications (
+    user_id text NOT NULL,
+    login text NOT NULL,
+    client_id text NOT NULL,
+    access_token text NOT NULL,
+    refresh_token text,
+    -- NULL means, that the token never expires
+    expiry timestamp with time zone,
+    CONSTRAINT twitch_authentications_pk PRIMARY KEY (user_id)
+);
+
+CREATE TABLE IF NOT EXISTS public.twitch_enabled_channels (
+    user_id text NOT NULL,
+    channel_id bigint NOT NULL,
+    enabled boolean DEFAULT false NOT NULL,
+    last_changed timestamp with time zone DEFAULT now() NOT NULL,
+    CONSTRAINT twitch_enabled_channels_pk PRIMARY KEY (user_id, channel_id)
+);
+
+CREATE TABLE IF NOT EXISTS public.twitch_live_message (
+    user_id text NOT NULL,
+    live_message text NOT NULL,
+    last_changed timestamp with time zone DEFAULT now() NOT NULL,
+    CONSTRAINT twitch_live_message_pk PRIMARY KEY (user_id)
+);
+
+CREATE TABLE IF NOT EXISTS public.twitch_conduit (
+    conduit_id text NOT NULL,
+    conduit jsonb NOT NULL,
+    created_at timestamp with time zone DEFAULT now() NOT NULL,
+    CONSTRAINT twitch_conduit_pk PRIMARY KEY (conduit_id)
+);
+
+COMMIT;
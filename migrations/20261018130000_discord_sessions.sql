@@ -0,0 +1,19 @@
+-- Add migration script here
+BEGIN;
+
+CREATE TABLE IF NOT EXISTS public.discord_sessions (
+    session_id bytea NOT NULL,
+    access_token text NOT NULL,
+    refresh_token text NOT NULL,
+    scope text NOT NULL,
+    -- When the access token was issued and how many seconds it is valid for
+    token_created timestamp with time zone NOT NULL,
+    expires_in bigint NOT NULL,
+    created_at timestamp with time zone DEFAULT now() NOT NULL,
+    last_used timestamp with time zone DEFAULT now() NOT NULL,
+    CONSTRAINT discord_sessions_pk PRIMARY KEY (session_id)
+);
+
+CREATE INDEX IF NOT EXISTS discord_sessions_last_used_index ON public.discord_sessions (last_used);
+
+COMMIT;
@@ -0,0 +1,31 @@
+-- Add migration script here
+BEGIN;
+
+ALTER TABLE public.xp
+    -- One of 'linear', 'quadratic', 'mee6' or 'custom'
+    ADD COLUMN IF NOT EXISTS level_formula text DEFAULT 'mee6' NOT NULL,
+    -- XP per level for 'linear', multiplier of level² for 'quadratic'
+    ADD COLUMN IF NOT EXISTS level_factor bigint DEFAULT 100 NOT NULL,
+    ADD COLUMN IF NOT EXISTS txt_weight double precision DEFAULT 1 NOT NULL,
+    -- XP per second in a voice channel
+    ADD COLUMN IF NOT EXISTS vc_weight double precision DEFAULT 1 NOT NULL,
+    -- NULL and level_up_dm = false means no announcements
+    ADD COLUMN IF NOT EXISTS level_up_channel bigint,
+    ADD COLUMN IF NOT EXISTS level_up_dm boolean DEFAULT false NOT NULL,
+    ADD COLUMN IF NOT EXISTS level_up_message text;
+
+-- Total XP needed to reach a level, for the 'custom' level formula
+CREATE TABLE IF NOT EXISTS public.xp_level_table (
+    guild_id bigint NOT NULL,
+    level integer NOT NULL,
+    xp bigint NOT NULL,
+    CONSTRAINT xp_level_table_pk PRIMARY KEY (guild_id, level),
+    CONSTRAINT xp_level_table_guilds_guild_id_fk FOREIGN KEY (guild_id) REFERENCES public.guilds(guild_id)
+);
+
+-- Last level a member was seen at. NULL means unknown, so the next level gets stored without an announcement.
+-- Existing members stay NULL, so they don't all get announced at once.
+ALTER TABLE public.xp_user ADD COLUMN IF NOT EXISTS level bigint;
+ALTER TABLE public.xp_user ALTER COLUMN level SET DEFAULT 0;
+
+COMMIT;
@@ -0,0 +1,14 @@
+-- Add migration script here
+BEGIN;
+
+CREATE TABLE IF NOT EXISTS public.xp_role_rewards (
+    guild_id bigint NOT NULL,
+    role_id bigint NOT NULL,
+    level bigint NOT NULL,
+    -- Whether this reward removes the rewards of lower levels, instead of stacking with them
+    replace_previous boolean DEFAULT false NOT NULL,
+    CONSTRAINT xp_role_rewards_pk PRIMARY KEY (guild_id, role_id),
+    CONSTRAINT xp_role_rewards_guilds_guild_id_fk FOREIGN KEY (guild_id) REFERENCES public.guilds(guild_id)
+);
+
+COMMIT;
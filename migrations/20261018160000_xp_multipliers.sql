@@ -0,0 +1,26 @@
+-- Add migration script here
+BEGIN;
+
+-- How multipliers of roles and the channel get combined: 'max' or 'product'
+ALTER TABLE public.xp ADD COLUMN IF NOT EXISTS multiplier_rule text DEFAULT 'max' NOT NULL;
+
+CREATE TABLE IF NOT EXISTS public.xp_multipliers_role (
+    guild_id bigint NOT NULL,
+    role_id bigint NOT NULL,
+    multiplier double precision NOT NULL,
+    CONSTRAINT xp_multipliers_role_pk PRIMARY KEY (guild_id, role_id),
+    CONSTRAINT xp_multipliers_role_guilds_guild_id_fk FOREIGN KEY (guild_id) REFERENCES public.guilds(guild_id)
+);
+
+CREATE TABLE IF NOT EXISTS public.xp_multipliers_channel (
+    guild_id bigint NOT NULL,
+    channel_id bigint NOT NULL,
+    multiplier double precision NOT NULL,
+    CONSTRAINT xp_multipliers_channel_pk PRIMARY KEY (guild_id, channel_id),
+    CONSTRAINT xp_multipliers_channel_guilds_guild_id_fk FOREIGN KEY (guild_id) REFERENCES public.guilds(guild_id)
+);
+
+-- Multiplier of the voice time, that accrued since xp_vc_tmp.time
+ALTER TABLE public.xp_vc_tmp ADD COLUMN IF NOT EXISTS multiplier double precision DEFAULT 1 NOT NULL;
+
+COMMIT;
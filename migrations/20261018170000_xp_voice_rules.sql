@@ -0,0 +1,27 @@
+-- Add migration script here
+BEGIN;
+
+-- Pause voice xp for members, that aren't actively participating
+ALTER TABLE public.xp ADD COLUMN IF NOT EXISTS vc_pause_deafened boolean DEFAULT true NOT NULL;
+ALTER TABLE public.xp ADD COLUMN IF NOT EXISTS vc_pause_muted boolean DEFAULT true NOT NULL;
+ALTER TABLE public.xp ADD COLUMN IF NOT EXISTS vc_pause_alone boolean DEFAULT true NOT NULL;
+ALTER TABLE public.xp ADD COLUMN IF NOT EXISTS vc_pause_afk boolean DEFAULT true NOT NULL;
+
+ALTER TABLE public.guilds ADD COLUMN IF NOT EXISTS afk_channel_id bigint;
+
+-- The voice channel every member is currently in, to re-evaluate everyone in a channel
+CREATE TABLE IF NOT EXISTS public.xp_vc_state (
+    guild_id bigint NOT NULL,
+    user_id bigint NOT NULL,
+    channel_id bigint NOT NULL,
+    -- self-deafened or deafened by the server
+    deafened boolean NOT NULL,
+    -- muted by the server
+    muted boolean NOT NULL,
+    bot boolean NOT NULL,
+    CONSTRAINT xp_vc_state_pk PRIMARY KEY (guild_id, user_id),
+    CONSTRAINT xp_vc_state_guilds_guild_id_fk FOREIGN KEY (guild_id) REFERENCES public.guilds(guild_id)
+);
+CREATE INDEX IF NOT EXISTS xp_vc_state_channel_idx ON public.xp_vc_state (guild_id, channel_id);
+
+COMMIT;
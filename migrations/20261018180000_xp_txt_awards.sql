@@ -0,0 +1,21 @@
+-- Add migration script here
+BEGIN;
+
+-- Text xp awarded per message, so it can be revoked when the message or reaction is removed.
+-- Rows are only kept for a limited time.
+CREATE TABLE IF NOT EXISTS public.xp_txt_awards (
+    guild_id bigint NOT NULL,
+    message_id bigint NOT NULL,
+    -- The member who got the xp
+    user_id bigint NOT NULL,
+    -- The reacting member and emoji, if the xp was awarded for a reaction
+    reaction_user_id bigint,
+    reaction_emoji text,
+    xp bigint NOT NULL,
+    "time" timestamp with time zone DEFAULT now() NOT NULL,
+    CONSTRAINT xp_txt_awards_guilds_guild_id_fk FOREIGN KEY (guild_id) REFERENCES public.guilds(guild_id)
+);
+CREATE INDEX IF NOT EXISTS xp_txt_awards_message_idx ON public.xp_txt_awards (guild_id, message_id);
+CREATE INDEX IF NOT EXISTS xp_txt_awards_time_idx ON public.xp_txt_awards ("time");
+
+COMMIT;
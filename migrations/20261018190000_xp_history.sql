@@ -0,0 +1,16 @@
+-- Add migration script here
+BEGIN;
+
+-- XP applied per member and UTC day, including spam penalties. Used for time-windowed leaderboards and trends.
+CREATE TABLE IF NOT EXISTS public.xp_history (
+    guild_id bigint NOT NULL,
+    user_id bigint NOT NULL,
+    day date NOT NULL,
+    txt bigint DEFAULT 0 NOT NULL,
+    vc interval DEFAULT '00:00:00'::interval NOT NULL,
+    CONSTRAINT xp_history_pk PRIMARY KEY (guild_id, user_id, day),
+    CONSTRAINT xp_history_guilds_guild_id_fk FOREIGN KEY (guild_id) REFERENCES public.guilds(guild_id)
+);
+CREATE INDEX IF NOT EXISTS xp_history_day_idx ON public.xp_history (guild_id, day);
+
+COMMIT;
@@ -0,0 +1,25 @@
+-- Add migration script here
+BEGIN;
+
+-- Manual XP changes by moderators
+CREATE TABLE IF NOT EXISTS public.xp_audit (
+    id bigserial NOT NULL,
+    guild_id bigint NOT NULL,
+    -- The moderator who changed the xp
+    actor_id bigint NOT NULL,
+    -- 'give', 'take', 'set', 'reset_user', 'reset_guild' or 'transfer'
+    action text NOT NULL,
+    -- The affected member. NULL for guild-wide resets.
+    user_id bigint,
+    -- The member receiving the xp of a transfer
+    target_user_id bigint,
+    txt bigint,
+    vc interval,
+    reason text,
+    "time" timestamp with time zone DEFAULT now() NOT NULL,
+    CONSTRAINT xp_audit_pk PRIMARY KEY (id),
+    CONSTRAINT xp_audit_guilds_guild_id_fk FOREIGN KEY (guild_id) REFERENCES public.guilds(guild_id)
+);
+CREATE INDEX IF NOT EXISTS xp_audit_guild_idx ON public.xp_audit (guild_id, "time");
+
+COMMIT;
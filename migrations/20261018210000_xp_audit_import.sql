@@ -0,0 +1,7 @@
+-- Add migration script here
+BEGIN;
+
+-- Imports ('import' action) from the command line have no moderator
+ALTER TABLE public.xp_audit ALTER COLUMN actor_id DROP NOT NULL;
+
+COMMIT;
@@ -0,0 +1,14 @@
+-- Add migration script here
+BEGIN;
+
+-- A guild can have multiple creator channels, each with their own settings
+ALTER TABLE public.temp_channels DROP CONSTRAINT IF EXISTS temp_channels_pkey;
+ALTER TABLE public.temp_channels ADD CONSTRAINT temp_channels_pkey PRIMARY KEY (guild_id, creator_channel);
+
+-- The creator channel, whose settings apply to a created channel
+ALTER TABLE public.temp_channels_created ADD COLUMN IF NOT EXISTS creator_channel bigint;
+UPDATE public.temp_channels_created SET creator_channel = (
+    SELECT temp_channels.creator_channel FROM public.temp_channels WHERE temp_channels.guild_id = temp_channels_created.guild_id LIMIT 1
+) WHERE creator_channel IS NULL;
+
+COMMIT;
@@ -0,0 +1,16 @@
+-- Add migration script here
+BEGIN;
+
+-- Name template and defaults for the channels a creator channel creates. NULL means Discord's default.
+ALTER TABLE public.temp_channels ADD COLUMN IF NOT EXISTS name_template text;
+ALTER TABLE public.temp_channels ADD COLUMN IF NOT EXISTS user_limit integer;
+-- in bits per second
+ALTER TABLE public.temp_channels ADD COLUMN IF NOT EXISTS bitrate integer;
+ALTER TABLE public.temp_channels ADD COLUMN IF NOT EXISTS rtc_region text;
+-- 1 = auto, 2 = full (720p)
+ALTER TABLE public.temp_channels ADD COLUMN IF NOT EXISTS video_quality smallint;
+
+-- Number of a created channel for the {count} placeholder of the name template
+ALTER TABLE public.temp_channels_created ADD COLUMN IF NOT EXISTS number integer;
+
+COMMIT;
@@ -0,0 +1,9 @@
+-- Add migration script here
+BEGIN;
+
+-- The member, that owns a created channel. NULL for channels the bot did not create itself.
+ALTER TABLE public.temp_channels_created ADD COLUMN IF NOT EXISTS owner_id bigint;
+-- Ownership passes to the member, who has been in the channel the longest
+ALTER TABLE public.temp_channels_created_users ADD COLUMN IF NOT EXISTS joined_at timestamp with time zone DEFAULT now() NOT NULL;
+
+COMMIT;
@@ -0,0 +1,20 @@
+-- Add migration script here
+BEGIN;
+
+-- Settings members made to their created channel with /vc, applied to the next channel they create
+CREATE TABLE IF NOT EXISTS public.temp_channels_preferences (
+    guild_id bigint NOT NULL,
+    user_id bigint NOT NULL,
+    -- false, if the member opted out of having their settings remembered
+    remember boolean DEFAULT true NOT NULL,
+    name text,
+    user_limit integer,
+    locked boolean DEFAULT false NOT NULL,
+    hidden boolean DEFAULT false NOT NULL,
+    permitted bigint[] DEFAULT '{}' NOT NULL,
+    banned bigint[] DEFAULT '{}' NOT NULL,
+    CONSTRAINT temp_channels_preferences_pk PRIMARY KEY (guild_id, user_id),
+    CONSTRAINT temp_channels_preferences_guilds_guild_id_fk FOREIGN KEY (guild_id) REFERENCES public.guilds(guild_id)
+);
+
+COMMIT;
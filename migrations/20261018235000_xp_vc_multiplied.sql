@@ -0,0 +1,10 @@
+-- Add migration script here
+BEGIN;
+
+-- Voice time with XP multipliers applied. vc stays the real time spent in voice channels.
+ALTER TABLE public.xp_user ADD COLUMN IF NOT EXISTS vc_xp interval DEFAULT '00:00:00'::interval NOT NULL;
+UPDATE public.xp_user SET vc_xp = vc;
+ALTER TABLE public.xp_history ADD COLUMN IF NOT EXISTS vc_xp interval DEFAULT '00:00:00'::interval NOT NULL;
+UPDATE public.xp_history SET vc_xp = vc;
+
+COMMIT;
@@ -0,0 +1,77 @@
+//! Subcommands of the binary, that run instead of the bot.
+use crate::client::portability::{Conversion, ExportFormat, ImportOptions, ImportSource};
+
+const USAGE: &str = "Usage:
+    import-xp <guild id> <mee6|arcane|tatsu> <file> [--conversion text|voice|level] [--factor <number>] [--replace]
+    export-xp <guild id> <csv|json> [output file]";
+
+/// Runs the subcommand in `args` (without the binary name). Returns `None`, if there is none and the bot should start.
+pub(crate) async fn run(args: &[String]) -> Option<anyhow::Result<()>> {
+    let (command, args) = args.split_first()?;
+    match command.as_str() {
+        "import-xp" => Some(import_xp(args).await),
+        "export-xp" => Some(export_xp(args).await),
+        "help" | "--help" | "-h" => {
+            println!("{USAGE}");
+            Some(Ok(()))
+        },
+        _ => None,
+    }
+}
+
+fn parse_guild(guild: Option<&String>) -> anyhow::Result<i64> {
+    let guild = guild.ok_or_else(|| anyhow::format_err!("Missing guild id.\n{USAGE}"))?;
+    let guild = guild.parse::<u64>().map_err(|err| anyhow::format_err!("Invalid guild id {guild}: {err}"))?;
+    Ok(crate::converti(guild))
+}
+
+async fn import_xp(args: &[String]) -> anyhow::Result<()> {
+    let guild = parse_guild(args.first())?;
+    let source = args.get(1).ok_or_else(|| anyhow::format_err!("Missing source.\n{USAGE}"))?
+        .parse::<ImportSource>().map_err(anyhow::Error::msg)?;
+    let file = args.get(2).ok_or_else(|| anyhow::format_err!("Missing file.\n{USAGE}"))?;
+    let mut options = ImportOptions {
+        source,
+        conversion: Conversion::default(),
+        factor: 1.,
+        replace: false,
+    };
+    let mut flags = args[3..].iter();
+    while let Some(flag) = flags.next() {
+        match flag.as_str() {
+            "--conversion" => {
+                options.conversion = flags.next().ok_or_else(|| anyhow::format_err!("Missing value for --conversion"))?
+                    .parse().map_err(anyhow::Error::msg)?;
+            },
+            "--factor" => {
+                options.factor = flags.next().ok_or_else(|| anyhow::format_err!("Missing value for --factor"))?.parse()?;
+                if !options.factor.is_finite() || options.factor < 0. {
+                    anyhow::bail!("The factor must be a positive number.");
+                }
+            },
+            "--replace" => options.replace = true,
+            other => anyhow::bail!("Unknown option {other}.\n{USAGE}"),
+        }
+    }
+    let data = tokio::fs::read(file).await?;
+    let db = crate::get_db().await;
+    let summary = crate::client::portability::import(&db, guild, None, &data, &options).await.map_err(anyhow::Error::msg)?;
+    println!("Imported {} text XP and {}s of voice time for {} members.", summary.txt, summary.vc_seconds, summary.members);
+    Ok(())
+}
+
+async fn export_xp(args: &[String]) -> anyhow::Result<()> {
+    let guild = parse_guild(args.first())?;
+    let format = args.get(1).ok_or_else(|| anyhow::format_err!("Missing format.\n{USAGE}"))?
+        .parse::<ExportFormat>().map_err(anyhow::Error::msg)?;
+    let db = crate::get_db().await;
+    let data = crate::client::portability::export(&db, guild, format).await.map_err(anyhow::Error::msg)?;
+    match args.get(2) {
+        Some(file) => tokio::fs::write(file, data).await?,
+        None => {
+            use std::io::Write;
+            std::io::stdout().write_all(&data)?;
+        },
+    }
+    Ok(())
+}
@@ -1,15 +1,18 @@
 mod temp_channels;
 mod xp;
 mod commands;
-mod role_limiter;
+pub(crate) mod role_limiter;
 mod role_reaction;
 
+pub(crate) use xp::portability;
+
 use poise::serenity_prelude as serenity;
 use serenity::utils::validate_token;
 use std::ops::{Deref, DerefMut};
 use std::sync::Arc;
 use serenity::all::{Context, Event, GatewayIntents};
 use tokio::io::AsyncReadExt;
+use serenity::CacheHttp;
 
 /// User data, which is stored and accessible in all command invocations
 struct Data;
@@ -26,7 +29,7 @@ impl serenity::client::RawEventHandler for Handler {
             Event::UserUpdate(_) => {}
 
             Event::ChannelCreate(channel) => {
-                self.channel_create(&channel.channel, false).await;
+                self.channel_create(&channel.channel, None).await;
             }
             Event::ChannelUpdate(channel) => {
                 let channel_name = channel.channel.name;
@@ -55,14 +58,30 @@ impl serenity::client::RawEventHandler for Handler {
             }
 
             Event::GuildCreate(create) => {
-                self.guild_info(create.guild.into()).await
+                let guild_id = create.guild.id;
+                let voice_members = create.guild.voice_states.values()
+                    .filter_map(|state| {
+                        let bot = create.guild.members.get(&state.user_id).is_some_and(|v| v.user.bot);
+                        xp::VoiceMember::new(state, bot)
+                    })
+                    .collect::<Vec<_>>();
+                self.guild_info(create.guild.into()).await;
+                //apply_previous_message_xp joins the xp settings, so every guild needs a row
+                if let Err(err) = sqlx::query!("INSERT INTO xp (guild_id) VALUES ($1) ON CONFLICT DO NOTHING", crate::converti(guild_id.get()))
+                    .execute(&self.pool).await
+                {
+                    tracing::error!("Error creating default xp settings: {err}");
+                }
+                self.resync_voice_xp(&ctx, guild_id, &voice_members).await;
             }
             Event::GuildUpdate(update) => {
                 self.guild_info(update.guild).await
             }
             Event::GuildDelete(_) => {}
 
-            Event::GuildMemberAdd(_) => {}
+            Event::GuildMemberAdd(event) => {
+                self.apply_level_rewards(&ctx.http, event.member.guild_id, event.member.user.id).await
+            }
             Event::GuildMemberRemove(_) => {}
             Event::GuildMemberUpdate(event) => {
                 let member = match event.guild_id.member(&ctx, event.user.id).await {
@@ -86,41 +105,9 @@ impl serenity::client::RawEventHandler for Handler {
                     Some(v) => v,
                     None => return,
                 };
-                let db = crate::get_db().await;
-                let apply_vc = async {
-                    match new_state.channel_id {
-                        None => {
-                            match sqlx::query!("WITH vc_xp_apply AS (
-    DELETE FROM xp_vc_tmp WHERE guild_id = $1 AND user_id = $2 RETURNING *
-) MERGE INTO xp_user USING vc_xp_apply ON xp_user.guild_id = vc_xp_apply.guild_id AND xp_user.user_id = vc_xp_apply.user_id
-WHEN MATCHED THEN UPDATE SET vc = xp_user.vc + (now() - vc_xp_apply.time)
-WHEN NOT MATCHED THEN INSERT (guild_id, user_id, vc) VALUES (vc_xp_apply.guild_id, vc_xp_apply.user_id, (now() - vc_xp_apply.time))
-", crate::converti(guild_id.get()), crate::converti(new_state.user_id.get())).execute(&db).await {
-                                Ok(v) => match v.rows_affected(){
-                                    0 => {
-                                        tracing::info!("Error applying voice xp for user {} in guild {:?}: No rows affected", new_state.user_id, new_state.guild_id);
-                                    },
-                                    _ => {},
-                                },
-                                Err(err) => {
-                                    tracing::error!("Error applying voice xp: {err}");
-                                }
-                            }
-                        },
-                        Some(_) => {
-                            match sqlx::query!(r#"MERGE INTO xp_vc_tmp
-USING (SELECT $1::bigint as guild_id, $2::bigint as user_id) as input ON xp_vc_tmp.guild_id = input.guild_id AND xp_vc_tmp.user_id = input.user_id
-WHEN MATCHED THEN DO NOTHING
-WHEN NOT MATCHED THEN INSERT (guild_id, user_id, time) VALUES (input.guild_id, input.user_id, now())
-"#, crate::converti(guild_id.get()), crate::converti(new_state.user_id.get())).execute(&db).await {
-                                Ok(_) => {},
-                                Err(err) => {
-                                    tracing::error!("Error adding user to voice xp tmp: {err}");
-                                }
-                            }
-                        }
-                    }
-                };
+                //The temp channel handling takes ownership of the context.
+                let xp_ctx = ctx.clone();
+                let apply_vc = self.voice_state_xp(&xp_ctx, guild_id, &new_state);
                 let temp_channel = async {
                     match new_state.channel_id {
                         Some(channel) => {
@@ -139,6 +126,7 @@ crate::converti(guild_id.get()), crate::converti(new_state.user_id.get())
                                     return;
                                 }
                             };
+                            self.transfer_left_channels(&ctx, guild_id).await;
                             self.check_delete_channels(ctx).await
                         }
                     }
@@ -151,19 +139,26 @@ crate::converti(guild_id.get()), crate::converti(new_state.user_id.get())
             Event::VoiceChannelStatusUpdate(_) => {}
 
             Event::MessageCreate(create) => {
-                self.message_xp(create.message).await;
+                self.message_xp(&ctx.http, create.message).await;
             }
             Event::MessageUpdate(_) => {}
-            Event::MessageDelete(_) => {}
-            Event::MessageDeleteBulk(_) => {}
+            Event::MessageDelete(delete) => {
+                self.revoke_message_xp(&ctx.http, delete.guild_id, &[delete.message_id]).await;
+            }
+            Event::MessageDeleteBulk(delete) => {
+                self.revoke_message_xp(&ctx.http, delete.guild_id, &delete.ids).await;
+            }
             Event::ReactionAdd(add) => {
                 tokio::join!(
                     role_reaction::add_reaction(&ctx, &add),
-                    self.message_xp_react(&add.reaction),
+                    self.message_xp_react(&ctx, &add.reaction),
                 );
             }
             Event::ReactionRemove(remove) => {
-                role_reaction::remove_reaction(&ctx, &remove).await;
+                tokio::join!(
+                    role_reaction::remove_reaction(&ctx, &remove),
+                    self.revoke_reaction_xp(&ctx.http, &remove.reaction),
+                );
             }
             Event::ReactionRemoveAll(_) => {}
             Event::ReactionRemoveEmoji(_) => {}
@@ -225,9 +220,10 @@ impl Handler{
         let id = crate::converti(new_data.id.get());
         let name = new_data.name.as_str();
         let icon = new_data.icon.map(|v| v.to_string());
+        let afk_channel_id = new_data.afk_metadata.map(|v| crate::converti(v.afk_channel_id.get()));
 
         let db = crate::get_db().await;
-        match sqlx::query!(r#"INSERT INTO guilds (guild_id, name, icon) VALUES ($1, $2, $3) ON CONFLICT (guild_id) DO UPDATE SET name = $2, icon = $3"#, id, name, icon)
+        match sqlx::query!(r#"INSERT INTO guilds (guild_id, name, icon, afk_channel_id) VALUES ($1, $2, $3, $4) ON CONFLICT (guild_id) DO UPDATE SET name = $2, icon = $3, afk_channel_id = $4"#, id, name, icon, afk_channel_id)
         .execute(&db).await {
             Ok(_) => {},
             Err(err) => {
@@ -235,15 +231,24 @@ impl Handler{
             }
         }
     }
-    async fn channel_create(&self, channel: &serenity::GuildChannel, self_created: bool) {
+    /// Keeps track of a new channel, if it was created by a creator channel
+    /// or if it is in the category of a creator, that deletes non-created channels.
+    async fn channel_create(&self, channel: &serenity::GuildChannel, created: Option<&temp_channels::CreatedChannel>) {
         let channel_name = &channel.name;
         let channel_id = crate::converti(channel.id.get());
         let category_id = channel.parent_id.map(serenity::ChannelId::get).map(crate::converti);
         let guild_id = crate::converti(channel.guild_id.get());
+        let creator_channel = created.map(|v| crate::converti(v.creator_channel.get()));
+        let number = created.map(|v| v.number);
+        let owner = created.map(|v| crate::converti(v.owner.get()));
         match sqlx::query!(
-r#"INSERT INTO temp_channels_created SELECT guild_id, $2::bigint as channel_id, NULL as mark_delete, $4::text as name FROM temp_channels
-WHERE temp_channels.guild_id = $1 AND temp_channels.create_category = $3 AND ($5::boolean OR temp_channels.delete_non_created_channels)"#,
-            guild_id, channel_id, category_id, channel_name, self_created).execute(&self.pool).await
+r#"INSERT INTO temp_channels_created (guild_id, channel_id, mark_delete, name, creator_channel, number, owner_id)
+SELECT guild_id, $2::bigint as channel_id, NULL as mark_delete, $4::text as name, creator_channel, $6::integer as number, $7::bigint as owner_id FROM temp_channels
+WHERE temp_channels.guild_id = $1 AND (temp_channels.creator_channel = $5::bigint OR ($5::bigint IS NULL AND temp_channels.create_category = $3 AND temp_channels.delete_non_created_channels))
+ORDER BY creator_channel
+LIMIT 1
+ON CONFLICT (guild_id, channel_id) DO UPDATE SET creator_channel = excluded.creator_channel, number = excluded.number, owner_id = excluded.owner_id WHERE $5::bigint IS NOT NULL"#,
+            guild_id, channel_id, category_id, channel_name, creator_channel, number, owner).execute(&self.pool).await
         {
             Ok(_) => {},
             Err(err) => {
@@ -470,6 +475,10 @@ pub async fn init_client() -> ::anyhow::Result<serenity::Client> {
                 commands::ping(),
                 commands::copy_emoji(),
                 commands::settings(),
+                commands::rank(),
+                commands::leaderboard(),
+                commands::xp(),
+                commands::vc(),
             ],
             ..Default::default()
         })
@@ -485,7 +494,12 @@ pub async fn init_client() -> ::anyhow::Result<serenity::Client> {
     let handler = Handler{
         pool: crate::get_db().await,
     };
-    let client = serenity::Client::builder(&token, GatewayIntents::default().union(GatewayIntents::MESSAGE_CONTENT).union(GatewayIntents::GUILD_MEMBERS))
+    let mut intents = GatewayIntents::default().union(GatewayIntents::MESSAGE_CONTENT).union(GatewayIntents::GUILD_MEMBERS);
+    //The presence intent is privileged and only needed for the {game} placeholder of temporary channels.
+    if std::env::var("DISCORD_PRESENCE_INTENT").is_ok_and(|v| matches!(v.trim(), "1" | "true")) {
+        intents = intents.union(GatewayIntents::GUILD_PRESENCES);
+    }
+    let client = serenity::Client::builder(&token, intents)
         .framework(framework)
         .raw_event_handler(handler.clone())
         .await?;
@@ -506,15 +520,22 @@ pub async fn init_client() -> ::anyhow::Result<serenity::Client> {
                     },
                     _ = interval.tick() => {
                         match sqlx::query!(r#"WITH vc_xp_apply AS (
-UPDATE xp_user SET vc = xp_user.vc + (now() - xp_vc_tmp.time) FROM xp_vc_tmp WHERE xp_user.guild_id = xp_vc_tmp.guild_id AND xp_user.user_id = xp_vc_tmp.user_id RETURNING xp_user.guild_id, xp_user.user_id
+UPDATE xp_user SET vc = xp_user.vc + (now() - xp_vc_tmp.time), vc_xp = xp_user.vc_xp + (now() - xp_vc_tmp.time) * xp_vc_tmp.multiplier FROM xp_vc_tmp WHERE xp_user.guild_id = xp_vc_tmp.guild_id AND xp_user.user_id = xp_vc_tmp.user_id
+RETURNING xp_user.guild_id, xp_user.user_id, now() - xp_vc_tmp.time as vc_change, (now() - xp_vc_tmp.time) * xp_vc_tmp.multiplier as vc_xp_change
+), history AS (
+INSERT INTO xp_history (guild_id, user_id, day, vc, vc_xp) SELECT guild_id, user_id, (now() AT TIME ZONE 'UTC')::date, vc_change, vc_xp_change FROM vc_xp_apply
+ON CONFLICT (guild_id, user_id, day) DO UPDATE SET vc = xp_history.vc + excluded.vc, vc_xp = xp_history.vc_xp + excluded.vc_xp
 ) UPDATE xp_vc_tmp SET time = now() FROM vc_xp_apply WHERE xp_vc_tmp.guild_id = vc_xp_apply.guild_id AND xp_vc_tmp.user_id = vc_xp_apply.user_id
+RETURNING xp_vc_tmp.guild_id, xp_vc_tmp.user_id
 "#)
-                        .execute(&db).await {
+                        .fetch_all(&db).await {
                             Ok(result) => {
-                                match result.rows_affected() {
+                                match result.len() {
                                     0 => {},
                                     v => {
                                         tracing::info!("Applied voice xp of {v} users", );
+                                        let members = result.into_iter().map(|v| (v.guild_id, v.user_id)).collect::<Vec<_>>();
+                                        xp::check_level_ups(&db, cache.http(), &members).await;
                                     }
                                 }
                             },
@@ -522,7 +543,8 @@ UPDATE xp_user SET vc = xp_user.vc + (now() - xp_vc_tmp.time) FROM xp_vc_tmp WHE
                                 tracing::error!("Error applying voice xp: {err}");
                             }
                         }
-                        handler.apply_previous_message_xp(None, None).await;
+                        handler.apply_previous_message_xp(cache.http(), None, None).await;
+                        handler.prune_txt_awards().await;
                         handler.check_delete_channels(&cache).await
                     },
                 }
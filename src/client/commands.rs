@@ -1,4 +1,7 @@
 mod settings;
+mod xp;
+mod xp_admin;
+mod vc;
 
 use poise::CreateReply;
 type Error = Box<dyn std::error::Error + Send + Sync>;
@@ -24,6 +27,9 @@ pub async fn ping(ctx: Context<'_>) -> Result<(), Error> {
 }
 
 pub use settings::settings;
+pub use xp::{rank, leaderboard};
+pub use xp_admin::xp;
+pub use vc::vc;
 
 
 ///Copies one emoji to the guild the command was run in (5 sec cooldown).
@@ -1,10 +1,16 @@
 mod temporary_channels;
 mod reaction_roles;
 mod role_limiter;
+mod level_rewards;
+mod twitch;
+mod xp;
 
 use temporary_channels::temporary_channels;
 use reaction_roles::reaction_roles;
 use role_limiter::role_limiter;
+use level_rewards::level_rewards;
+use twitch::twitch;
+use xp::xp;
 
 use crate::client::commands::{Context, Error};
 ///Various commands for changing some settings.
@@ -14,6 +20,9 @@ use crate::client::commands::{Context, Error};
         "temporary_channels",
         "reaction_roles",
         "role_limiter",
+        "level_rewards",
+        "twitch",
+        "xp",
     ),
     default_member_permissions = "MANAGE_GUILD",
     required_permissions = "MANAGE_GUILD",
@@ -0,0 +1,148 @@
+use serenity::all::{CreateAllowedMentions, RoleId, UserId};
+use crate::client::commands::{Context, Error};
+use crate::client::xp::LevelSettings;
+
+///Various commands for changing the roles members get for reaching XP levels.
+#[poise::command(
+    slash_command,
+    subcommands(
+        "add",
+        "list",
+        "remove",
+        "resync",
+    ),
+    default_member_permissions = "MANAGE_GUILD",
+    required_permissions = "MANAGE_GUILD",
+    subcommand_required,
+)]
+pub async fn level_rewards(_: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+#[poise::command(
+    slash_command,
+    default_member_permissions = "MANAGE_GUILD",
+    required_permissions = "MANAGE_GUILD",
+)]
+///Gives a role to members, once they reach a level
+pub async fn add(
+    ctx: Context<'_>,
+    role: RoleId,
+    #[min = 0] level: i64,
+    #[description = "Remove the rewards of lower levels, once this one is reached. Defaults to stacking."] replace_previous: Option<bool>,
+) -> Result<(), Error> {
+    let db = crate::get_db().await;
+    let guild_id = match ctx.guild_id() {
+        Some(v) => v,
+        None => return Err("This command needs to be run from a guild".into())
+    };
+    let replace_previous = replace_previous.unwrap_or(false);
+    sqlx::query!(
+        r#"INSERT INTO public.xp_role_rewards (guild_id, role_id, level, replace_previous) VALUES ($1, $2, $3, $4) ON CONFLICT (guild_id, role_id) DO UPDATE SET level = $3, replace_previous = $4"#,
+        guild_id.get().cast_signed(), role.get().cast_signed(), level, replace_previous
+    )
+        .execute(&db)
+        .await?;
+    let mode = if replace_previous { "replaces" } else { "stacks with" };
+    ctx.send(
+        poise::CreateReply::default()
+            .content(format!("Members will get <@&{role}> at level {level}. It {mode} the rewards of lower levels. Existing members get it with their next level-up or `/settings level_rewards resync`."))
+            .reply(true)
+            .allowed_mentions(CreateAllowedMentions::default())
+    ).await?;
+    Ok(())
+}
+
+#[poise::command(
+    slash_command,
+    default_member_permissions = "MANAGE_GUILD",
+    required_permissions = "MANAGE_GUILD",
+)]
+///Lists the roles members get for reaching levels
+pub async fn list(ctx: Context<'_>) -> Result<(), Error> {
+    let db = crate::get_db().await;
+    let guild_id = match ctx.guild_id() {
+        Some(v) => v,
+        None => return Err("This command needs to be run from a guild".into())
+    };
+    let rewards = sqlx::query!(
+        r#"SELECT role_id, level, replace_previous FROM public.xp_role_rewards WHERE guild_id = $1 ORDER BY level, role_id"#,
+        guild_id.get().cast_signed()
+    )
+        .fetch_all(&db)
+        .await?
+        .into_iter()
+        .fold(String::new(), |mut init, item|{
+            let role_id = item.role_id;
+            let level = item.level;
+            let mode = if item.replace_previous { "replaces lower rewards" } else { "stacks" };
+            init.push_str(&format!("- Level {level}: <@&{role_id}> ({mode})\n"));
+            init
+        });
+    if rewards.is_empty() {
+        ctx.say("There are no level rewards.").await?;
+        return Ok(());
+    }
+    ctx.send(poise::CreateReply::default().content(format!("The following level rewards exist: \n{rewards}")).reply(true).allowed_mentions(CreateAllowedMentions::default())).await?;
+    Ok(())
+}
+
+#[poise::command(
+    slash_command,
+    default_member_permissions = "MANAGE_GUILD",
+    required_permissions = "MANAGE_GUILD",
+)]
+/// Removes level rewards. Members keep the roles they already got.
+pub async fn remove(ctx: Context<'_>, #[min_length = 1] roles: Vec<RoleId>) -> Result<(), Error> {
+    let db = crate::get_db().await;
+    let guild_id = match ctx.guild_id() {
+        Some(v) => v,
+        None => return Err("This command needs to be run from a guild".into())
+    };
+    let roles = roles.into_iter().map(|v|v.get().cast_signed()).collect::<Vec<_>>();
+    sqlx::query!(
+        r#"DELETE FROM public.xp_role_rewards WHERE guild_id = $1 AND role_id = ANY($2)"#,
+        guild_id.get().cast_signed(), roles.as_slice(),
+    )
+        .execute(&db)
+        .await?;
+    let roles = roles.iter().fold(String::new(), |mut init, role|{
+        init.push_str(&format!("<@&{role}>"));
+        init
+    });
+    ctx.send(
+        poise::CreateReply::default()
+            .content(format!("Removed the level rewards for the roles: {roles}"))
+            .reply(true)
+            .allowed_mentions(CreateAllowedMentions::default())
+    ).await?;
+    Ok(())
+}
+
+#[poise::command(
+    slash_command,
+    default_member_permissions = "MANAGE_GUILD",
+    required_permissions = "MANAGE_GUILD",
+    guild_cooldown = 900,
+)]
+/// Gives and removes level rewards of all members with XP, according to their current level
+pub async fn resync(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = match ctx.guild_id() {
+        Some(v) => v,
+        None => return Err("This command needs to be run from a guild".into())
+    };
+    ctx.defer().await?;
+    let db = crate::get_db().await;
+    let guild = guild_id.get().cast_signed();
+    let settings = LevelSettings::get(&db, guild).await?;
+    let members = sqlx::query!(r#"SELECT user_id, txt, EXTRACT(EPOCH FROM vc_xp)::bigint as "vc!" FROM xp_user WHERE guild_id = $1"#, guild)
+        .fetch_all(&db)
+        .await?;
+    let count = members.len();
+    for member in members {
+        let level = settings.progress(settings.xp(member.txt, member.vc)).level;
+        crate::client::role_limiter::handle_level_rewards(ctx.serenity_context(), guild_id, UserId::new(member.user_id.cast_unsigned()), level).await;
+    }
+    ctx.say(format!("Resynced the level rewards of {count} members.")).await?;
+    Ok(())
+}
@@ -1,7 +1,6 @@
-use std::sync::LazyLock;
 use serenity::all::{ChunkGuildFilter, CreateAllowedMentions, RoleId};
 use crate::client::commands::{Context, Error};
-use crate::client::role_limiter::{BindRoles, BindRolesOrs};
+use crate::client::role_limiter::BindRoles;
 
 ///Various commands for changing some settings.
 #[poise::command(
@@ -27,24 +26,7 @@ pub async fn role_limiter(_: Context<'_>) -> Result<(), Error> {
 )]
 ///Add a limitation about who can have a certain role (expressed in a Disjunctive normal form formula)
 pub async fn add(ctx: Context<'_>, role: RoleId, #[description = "! = NOT, & = AND, | = OR"]bind_roles: String) -> Result<(), Error> {
-    static REMOVE_WHITESPACE:LazyLock<regex::Regex> =  LazyLock::new(||regex::Regex::new(r#"[\s<@&>]*"#).expect("Invalid Regex"));
-    let bind_roles = REMOVE_WHITESPACE.replace_all(&bind_roles, "");
-    let mut bind_roles_parsed = BindRoles::default();
-    for and_items in bind_roles.split("|") {
-        let mut items = BindRolesOrs::default();
-        for or_item in and_items.split("&") {
-            let (inverted, role) = or_item.strip_prefix("!").map_or_else(||(false, or_item), |v|(true, v));
-            log::debug!("Parsing role: {role}");
-            let role:RoleId = role.parse()?;
-            let role = role.get().cast_signed();
-            if inverted {
-                items.negated.push(role);
-            } else {
-                items.normal.push(role);
-            }
-        }
-        bind_roles_parsed.bind.push(items);
-    }
+    let bind_roles_parsed = BindRoles::parse(&bind_roles)?;
     let db = crate::get_db().await;
     let guild_id = match ctx.guild_id() {
         Some(v) => v,
@@ -1,12 +1,17 @@
 use crate::client::commands::{Context, Error};
+use crate::client::temp_channels::DEFAULT_NAME_TEMPLATE;
 use poise::serenity_prelude as serenity;
 
 ///Various commands for changing some settings.
 #[poise::command(
     slash_command,
     subcommands(
-        "set_creator_channel",
+        "add_creator_channel",
+        "remove_creator_channel",
+        "list_creator_channels",
         "set_category",
+        "name_template",
+        "channel_defaults",
         "ignored_channels",
         "list_ignored_channels",
         "delete_non_created_channels",
@@ -26,7 +31,7 @@ pub async fn temporary_channels(_: Context<'_>) -> Result<(), Error> {
     guild_only,
     required_permissions = "MANAGE_GUILD",
 )]
-async fn set_creator_channel(ctx: Context<'_>, channel: serenity::Channel, set_category: Option<bool>) -> Result<(), Error> {
+async fn add_creator_channel(ctx: Context<'_>, channel: serenity::Channel, set_category: Option<bool>) -> Result<(), Error> {
     if let Some(guild) = ctx.guild_id() {
         let db = crate::get_db().await;
         let channel_id = crate::converti(channel.id().get());
@@ -38,22 +43,22 @@ async fn set_creator_channel(ctx: Context<'_>, channel: serenity::Channel, set_c
         };
         let guild = crate::converti(guild.get());
         let old_channel = sqlx::query!("
-MERGE INTO temp_channels USING (SELECT $1::bigint, $2::bigint, $4::bigint) as input(guild_id, channel_id, category) ON temp_channels.guild_id = input.guild_id
-WHEN MATCHED THEN UPDATE SET creator_channel = input.channel_id, create_category = CASE WHEN $3::bool THEN input.category ELSE temp_channels.create_category END
+MERGE INTO temp_channels USING (SELECT $1::bigint, $2::bigint, $4::bigint) as input(guild_id, channel_id, category) ON temp_channels.guild_id = input.guild_id AND temp_channels.creator_channel = input.channel_id
+WHEN MATCHED THEN UPDATE SET create_category = CASE WHEN $3::bool THEN input.category ELSE temp_channels.create_category END
 WHEN NOT MATCHED THEN INSERT (guild_id, creator_channel, create_category) VALUES (input.guild_id, input.channel_id, input.category)
 ", guild, channel_id, set_category, category).execute(&db).await?;
         if old_channel.rows_affected() == 0 {
             ctx.say("Nothing changed in the database? This shouldn't happen.").await?;
         }
-        let mut out = format!("Set the temporary channel creator channel to <#{channel_id}>. ");
+        let mut out = format!("Made <#{channel_id}> a temporary channel creator channel. ");
         if set_category {
             let category = category.map(|v|crate::convertu(v));
             match category {
                 Some(category) => {
-                    out.push_str(format!("And made channels spawn in <#{category}>.").as_str());
+                    out.push_str(format!("Channels created by it spawn in <#{category}>.").as_str());
                 },
                 None => {
-                    out.push_str(format!("And made channels no longer spawn in any category.").as_str());
+                    out.push_str("Channels created by it don't spawn in any category.");
                 },
             }
         }
@@ -64,28 +69,182 @@ WHEN NOT MATCHED THEN INSERT (guild_id, creator_channel, create_category) VALUES
     Ok(())
 }
 
+///Stops the channel from creating temporary channels.
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+)]
+async fn remove_creator_channel(ctx: Context<'_>, channel: serenity::ChannelId) -> Result<(), Error> {
+    if let Some(guild) = ctx.guild_id() {
+        let db = crate::get_db().await;
+        let guild = crate::converti(guild.get());
+        let out = sqlx::query!("DELETE FROM temp_channels WHERE guild_id = $1 AND creator_channel = $2", guild, crate::converti(channel.get())).execute(&db).await?;
+        match out.rows_affected() {
+            0 => ctx.say(format!("<#{channel}> is not a creator channel.")).await?,
+            _ => ctx.say(format!("<#{channel}> no longer creates temporary channels.")).await?,
+        };
+    } else {
+        ctx.say("This command can only be used in a server.").await?;
+    }
+    Ok(())
+}
+
+///List the channels, that create temporary channels, and their settings.
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+)]
+async fn list_creator_channels(ctx: Context<'_>) -> Result<(), Error> {
+    if let Some(guild) = ctx.guild_id() {
+        let guild = crate::converti(guild.get());
+        let db = crate::get_db().await;
+        let creators = sqlx::query!(
+            r#"SELECT creator_channel, create_category, (EXTRACT(EPOCH FROM delete_delay)*1000000)::bigint as "delete_delay!", delete_non_created_channels, name_template FROM temp_channels WHERE guild_id = $1 ORDER BY creator_channel"#,
+            guild
+        ).fetch_all(&db).await?;
+        if creators.is_empty() {
+            ctx.say("No creator channel is configured.").await?;
+        } else {
+            let mut out = String::from("Creator channels:\n");
+            for creator in creators {
+                let category = match creator.create_category {
+                    Some(category) => format!("<#{}>", crate::convertu(category)),
+                    None => "no category".to_string(),
+                };
+                out.push_str(format!(
+                    "- <#{}>: spawns `{}` in {category}, deletes them after {} microseconds, delete non-created channels: {}\n",
+                    crate::convertu(creator.creator_channel), creator.name_template.as_deref().unwrap_or(DEFAULT_NAME_TEMPLATE), creator.delete_delay, creator.delete_non_created_channels
+                ).as_str());
+            }
+            ctx.say(out).await?;
+        }
+    } else {
+        ctx.say("This command can only be used in a server.").await?;
+    }
+    Ok(())
+}
 
-///Set's the category a channel will spawn in to the category the specified channel is in.
+
+///Makes the channels of a creator channel spawn in the category the specified channel is in.
 #[poise::command(
     slash_command,
     guild_only,
     required_permissions = "MANAGE_GUILD",
 )]
-async fn set_category(ctx: Context<'_>, category: serenity::Channel) -> Result<(), Error> {
+async fn set_category(ctx: Context<'_>, creator: serenity::ChannelId, category: serenity::Channel) -> Result<(), Error> {
     if let Some(guild) = ctx.guild_id() {
         let category = category.category().map(|v|crate::converti(v.id.get()));
         let db = crate::get_db().await;
         let guild = crate::converti(guild.get());
-        let old_channel = sqlx::query!("UPDATE temp_channels SET create_category = $2 WHERE guild_id = $1", guild, category).execute(&db).await?;
+        let old_channel = sqlx::query!("UPDATE temp_channels SET create_category = $3 WHERE guild_id = $1 AND creator_channel = $2", guild, crate::converti(creator.get()), category).execute(&db).await?;
         if old_channel.rows_affected() == 0 {
-            ctx.say("The Temporary Channel Spawn category did not change, because the guild does not have a creator channel set..").await?;
+            ctx.say(format!("The Temporary Channel Spawn category did not change, because <#{creator}> is not a creator channel.")).await?;
         } else {
             if let Some(category) = category.map(|v|crate::convertu(v)) {
-                ctx.say(format!("Made channels spawn in the category: <#{category}>.")).await?;
+                ctx.say(format!("Made channels of <#{creator}> spawn in the category: <#{category}>.")).await?;
             } else {
-                ctx.say("Made channels spawn in no category.").await?;
+                ctx.say(format!("Made channels of <#{creator}> spawn in no category.")).await?;
+            }
+        }
+    } else {
+        ctx.say("This command can only be used in a server.").await?;
+    }
+    Ok(())
+}
+
+///Names created channels. Placeholders: {name}, {username}, {count} and {game}. Empty resets it.
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+)]
+async fn name_template(ctx: Context<'_>, creator: serenity::ChannelId, #[max_length = 100] template: Option<String>) -> Result<(), Error> {
+    if let Some(guild) = ctx.guild_id() {
+        let db = crate::get_db().await;
+        let guild = crate::converti(guild.get());
+        let template = template.filter(|v| !v.trim().is_empty());
+        let out = sqlx::query!("UPDATE temp_channels SET name_template = $3 WHERE guild_id = $1 AND creator_channel = $2", guild, crate::converti(creator.get()), template).execute(&db).await?;
+        match (out.rows_affected(), template) {
+            (0, _) => ctx.say(format!("<#{creator}> is not a creator channel.")).await?,
+            (_, Some(template)) => ctx.say(format!("Channels of <#{creator}> will be named `{template}`.")).await?,
+            (_, None) => ctx.say(format!("Channels of <#{creator}> will be named `{DEFAULT_NAME_TEMPLATE}` again.")).await?,
+        };
+    } else {
+        ctx.say("This command can only be used in a server.").await?;
+    }
+    Ok(())
+}
+
+#[derive(poise::ChoiceParameter, Copy, Clone)]
+enum VideoQuality {
+    #[name_localized("de", "Automatisch")]
+    Auto = 1,
+    #[name = "720p"]
+    Full = 2,
+}
+
+///Sets defaults of created channels. Only given options change. 0 or "automatic" resets one.
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+)]
+async fn channel_defaults(
+    ctx: Context<'_>,
+    creator: serenity::ChannelId,
+    #[max = 99] user_limit: Option<u16>,
+    #[description = "Bitrate in kbps. Capped to the maximum allowed by the boost level."]
+    #[max = 384] bitrate: Option<u16>,
+    #[description = "Voice region id like rotterdam or us-east"]
+    rtc_region: Option<String>,
+    video_quality: Option<VideoQuality>,
+) -> Result<(), Error> {
+    if let Some(guild) = ctx.guild_id() {
+        if bitrate.is_some_and(|v| v != 0 && v < 8) {
+            ctx.say("The bitrate must be at least 8 kbps.").await?;
+            return Ok(());
+        }
+        let rtc_region = rtc_region.map(|v| v.trim().to_lowercase());
+        if let Some(region) = rtc_region.as_deref().filter(|v| *v != "automatic") {
+            let regions = ctx.http().get_voice_regions().await?;
+            if !regions.iter().any(|v| v.id == region) {
+                let regions = regions.iter().filter(|v| !v.deprecated).map(|v| v.id.as_str()).collect::<Vec<_>>().join(", ");
+                ctx.say(format!("`{region}` is not a voice region. Available are automatic, {regions}.")).await?;
+                return Ok(());
             }
         }
+        let db = crate::get_db().await;
+        let guild = crate::converti(guild.get());
+        let out = sqlx::query!(
+            r#"UPDATE temp_channels SET
+  user_limit = NULLIF(COALESCE($3, user_limit), 0),
+  bitrate = NULLIF(COALESCE($4, bitrate), 0),
+  rtc_region = NULLIF(COALESCE($5, rtc_region), 'automatic'),
+  video_quality = COALESCE($6, video_quality)
+WHERE guild_id = $1 AND creator_channel = $2
+RETURNING user_limit, bitrate, rtc_region, video_quality"#,
+            guild, crate::converti(creator.get()),
+            user_limit.map(i32::from),
+            bitrate.map(|v| i32::from(v) * 1000),
+            rtc_region,
+            video_quality.map(|v| v as i16),
+        ).fetch_optional(&db).await?;
+        match out {
+            None => {
+                ctx.say(format!("<#{creator}> is not a creator channel.")).await?;
+            },
+            Some(v) => {
+                ctx.say(format!(
+                    "Channels of <#{creator}> now get created with: user limit {}, bitrate {}, region {}, video quality {}.",
+                    v.user_limit.map_or("none".to_string(), |v| v.to_string()),
+                    v.bitrate.map_or("default".to_string(), |v| format!("{}kbps", v / 1000)),
+                    v.rtc_region.unwrap_or_else(|| "automatic".to_string()),
+                    if v.video_quality == Some(VideoQuality::Full as i16) { "720p" } else { "auto" },
+                )).await?;
+            },
+        }
     } else {
         ctx.say("This command can only be used in a server.").await?;
     }
@@ -164,20 +323,20 @@ async fn list_ignored_channels(ctx: Context<'_>) -> Result<(), Error> {
 }
 
 
-///Chooses to dis-/allow deletion of other channels in the same category as the creation category.
+///Chooses to dis-/allow deletion of other channels in the creation category of a creator channel.
 #[poise::command(
     slash_command,
     guild_only,
     required_permissions = "MANAGE_GUILD",
 )]
-async fn delete_non_created_channels(ctx: Context<'_>, value: bool) -> Result<(), Error> {
+async fn delete_non_created_channels(ctx: Context<'_>, creator: serenity::ChannelId, value: bool) -> Result<(), Error> {
     if let Some(guild) = ctx.guild_id() {
         let db = crate::get_db().await;
         let guild = crate::converti(guild.get());
-        let out = sqlx::query!("UPDATE temp_channels SET delete_non_created_channels = $2 WHERE guild_id = $1", guild, value).execute(&db).await?;
+        let out = sqlx::query!("UPDATE temp_channels SET delete_non_created_channels = $3 WHERE guild_id = $1 AND creator_channel = $2", guild, crate::converti(creator.get()), value).execute(&db).await?;
         match out.rows_affected() {
             0 => {
-                ctx.say(format!("No creator channel is configured.")).await?;
+                ctx.say(format!("<#{creator}> is not a creator channel.")).await?;
             },
             _ => {
                 ctx.say(format!("Changed the value for deleting non-created channels to {value}.")).await?;
@@ -197,7 +356,7 @@ async fn delete_non_created_channels(ctx: Context<'_>, value: bool) -> Result<()
     guild_only,
     required_permissions = "MANAGE_GUILD",
 )]
-async fn delete_delay(ctx: Context<'_>, microseconds: i64) -> Result<(), Error> {
+async fn delete_delay(ctx: Context<'_>, creator: serenity::ChannelId, microseconds: i64) -> Result<(), Error> {
     if let Some(guild) = ctx.guild_id() {
         let value = sqlx::postgres::types::PgInterval{
             microseconds,
@@ -206,13 +365,13 @@ async fn delete_delay(ctx: Context<'_>, microseconds: i64) -> Result<(), Error>
         };
         let guild = crate::converti(guild.get());
         let db = crate::get_db().await;
-        let out = sqlx::query!("UPDATE temp_channels SET delete_delay = $2 WHERE guild_id = $1", guild, value).execute(&db).await?;
+        let out = sqlx::query!("UPDATE temp_channels SET delete_delay = $3 WHERE guild_id = $1 AND creator_channel = $2", guild, crate::converti(creator.get()), value).execute(&db).await?;
         match out.rows_affected() {
             0 => {
-                ctx.say(format!("No creator channel is configured.")).await?;
+                ctx.say(format!("<#{creator}> is not a creator channel.")).await?;
             },
             _ => {
-                ctx.say(format!("Changed the delay for deleting temporary channels to {microseconds} microseconds. (1000microseconds = 1 millisecond)")).await?;
+                ctx.say(format!("Changed the delay for deleting temporary channels of <#{creator}> to {microseconds} microseconds. (1000microseconds = 1 millisecond)")).await?;
             },
         }
     } else {
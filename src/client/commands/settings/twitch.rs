@@ -0,0 +1,82 @@
+use crate::client::commands::{Context, Error};
+use poise::serenity_prelude as serenity;
+
+///Various commands for changing the settings of Twitch live notifications.
+#[poise::command(
+    slash_command,
+    subcommands(
+        "offline_message",
+        "request_notifications",
+    ),
+    default_member_permissions = "MANAGE_GUILD",
+    required_permissions = "MANAGE_GUILD",
+    subcommand_required,
+)]
+pub async fn twitch(_: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+#[derive(poise::ChoiceParameter)]
+enum OfflineAction {
+    #[name_localized("de", "Bearbeiten")]
+    Edit,
+    #[name_localized("de", "Löschen")]
+    Delete,
+}
+
+///Chooses if live notifications get edited to "was live" or deleted, once the stream ends.
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+)]
+async fn offline_message(ctx: Context<'_>, action: OfflineAction) -> Result<(), Error> {
+    if let Some(guild) = ctx.guild_id() {
+        let db = crate::get_db().await;
+        let guild = crate::converti(guild.get());
+        let delete = matches!(action, OfflineAction::Delete);
+        sqlx::query!("INSERT INTO twitch_live_settings (guild_id, delete_offline_message) VALUES ($1, $2) ON CONFLICT (guild_id) DO UPDATE SET delete_offline_message = $2", guild, delete).execute(&db).await?;
+        if delete {
+            ctx.say("Live notifications will be deleted, once the stream ends.").await?;
+        } else {
+            ctx.say("Live notifications will be edited to show the stream duration and VOD, once the stream ends.").await?;
+        }
+    } else {
+        ctx.say("This command can only be used in a server.").await?;
+    }
+    Ok(())
+}
+
+///Asks a streamer for live notifications in a channel. The streamer has to approve it on the website.
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+)]
+async fn request_notifications(
+    ctx: Context<'_>,
+    #[description = "Twitch login of the streamer"] streamer: String,
+    #[description = "Channel to send the notifications to. Defaults to this channel."] channel: Option<serenity::ChannelId>,
+) -> Result<(), Error> {
+    if ctx.guild_id().is_none() {
+        ctx.say("This command can only be used in a server.").await?;
+        return Ok(());
+    }
+    let channel = channel.unwrap_or_else(|| ctx.channel_id());
+    let streamer = streamer.trim().trim_start_matches('@');
+    let db = crate::get_db().await;
+    //Only streamers, that linked their Twitch account, can approve requests.
+    let Some(user) = sqlx::query!("SELECT user_id, login FROM twitch_authentications WHERE lower(login) = lower($1)", streamer).fetch_optional(&db).await? else {
+        ctx.say(format!("{streamer} has not linked their Twitch account with this bot yet. They need to do that, before you can ask for their live notifications.")).await?;
+        return Ok(());
+    };
+    let out = sqlx::query!(
+        "INSERT INTO twitch_enabled_channels (user_id, channel_id, enabled) VALUES ($1, $2, false) ON CONFLICT DO NOTHING",
+        user.user_id, crate::converti(channel.get())
+    ).execute(&db).await?;
+    match out.rows_affected() {
+        0 => ctx.say(format!("<#{channel}> already asked for the live notifications of {}.", user.login)).await?,
+        _ => ctx.say(format!("Asked {} for live notifications in <#{channel}>. They will start, once the streamer approves them.", user.login)).await?,
+    };
+    Ok(())
+}
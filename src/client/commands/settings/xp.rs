@@ -0,0 +1,636 @@
+use crate::client::commands::{Context, Error};
+use poise::serenity_prelude as serenity;
+use crate::client::xp::{LevelFormula, LevelSettings, LevelUpDestination, MultiplierRule, VoiceRules};
+
+/// Levels of a custom level table. More levels don't fit into a slash command option anyway.
+const MAX_CUSTOM_LEVELS: usize = 500;
+
+///Various commands for changing the settings of the XP system.
+#[poise::command(
+    slash_command,
+    subcommands(
+        "show",
+        "apply_interval",
+        "punish_interval",
+        "ignored_channels",
+        "list_ignored_channels",
+        "level_formula",
+        "level_table",
+        "weights",
+        "level_up_announcements",
+        "role_multiplier",
+        "channel_multiplier",
+        "multiplier_rule",
+        "list_multipliers",
+        "voice_rules",
+        "trends",
+    ),
+    default_member_permissions = "MANAGE_GUILD",
+    required_permissions = "MANAGE_GUILD",
+    subcommand_required,
+)]
+pub async fn xp(_: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Parses durations like `50ms`, `2min` or `1h 30m`.
+fn parse_interval(duration: &str) -> Result<sqlx::postgres::types::PgInterval, String> {
+    let duration = humantime::parse_duration(duration.trim())
+        .map_err(|err| format!("`{duration}` is not a valid duration ({err}). Try something like `50ms`, `2min` or `1h 30m`."))?;
+    sqlx::postgres::types::PgInterval::try_from(duration)
+        .map_err(|_| format!("`{duration:?}` is too long."))
+}
+
+fn format_interval(interval: &sqlx::postgres::types::PgInterval) -> String {
+    //Intervals set by this bot only use microseconds. Days and months are converted like `client::migrate` does.
+    const MICROSECONDS_PER_DAY: i64 = 24*60*60*1_000_000;
+    let microseconds = (i64::from(interval.months) * 30 + i64::from(interval.days)) * MICROSECONDS_PER_DAY + interval.microseconds;
+    let duration = std::time::Duration::from_micros(u64::try_from(microseconds).unwrap_or(0));
+    humantime::format_duration(duration).to_string()
+}
+
+///Shows the current XP settings of this server.
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+)]
+async fn show(ctx: Context<'_>) -> Result<(), Error> {
+    if let Some(guild) = ctx.guild_id() {
+        let db = crate::get_db().await;
+        let guild = crate::converti(guild.get());
+        let settings = sqlx::query!("SELECT txt_apply_interval, txt_punish_interval, multiplier_rule FROM xp WHERE guild_id = $1", guild).fetch_optional(&db).await?;
+        let ignored = sqlx::query!("SELECT channel_id FROM xp_channels_ignored WHERE guild_id = $1", guild).fetch_all(&db).await?;
+        let levels = LevelSettings::get(&db, guild).await?;
+        let voice_rules = VoiceRules::get(&db, guild).await?;
+        let mut out = match &settings {
+            Some(settings) => format!(
+                "Text XP is applied at most every {} and messages sent within {} are punished as spam.\n",
+                format_interval(&settings.txt_apply_interval), format_interval(&settings.txt_punish_interval)
+            ),
+            None => "This server has no XP settings yet, so no text XP is given. Changing any XP setting creates them.\n".to_string(),
+        };
+        let rule = settings.map(|v| MultiplierRule::from_db(&v.multiplier_rule)).unwrap_or_default();
+        out.push_str(format!("Multipliers are combined using the {} rule. See `/settings xp list_multipliers`.\n", poise::ChoiceParameter::name(&rule)).as_str());
+        let formula = match levels.formula {
+            LevelFormula::Linear => format!("linear, {} XP per level", levels.factor),
+            LevelFormula::Quadratic => format!("quadratic, {} XP × level²", levels.factor),
+            LevelFormula::Mee6 => "MEE6".to_string(),
+            LevelFormula::Custom => format!("custom table with {} levels", levels.table.len()),
+        };
+        out.push_str(format!("Level formula: {formula}. Level XP = text XP × {} + voice seconds × {}.\n", levels.txt_weight, levels.vc_weight).as_str());
+        out.push_str(format!("Voice time is paused {}.\n", describe_voice_rules(voice_rules)).as_str());
+        match &levels.destination {
+            None => out.push_str("Level-ups are not announced.\n"),
+            Some(LevelUpDestination::Dm) => out.push_str("Level-ups are announced via DM.\n"),
+            Some(LevelUpDestination::Channel(channel)) => out.push_str(format!("Level-ups are announced in <#{channel}>.\n").as_str()),
+        }
+        if ignored.is_empty() {
+            out.push_str("No channels are ignored for XP.");
+        } else {
+            let channel_list = ignored.into_iter().map(|v|format!("<#{}>", crate::convertu(v.channel_id))).reduce(|a,b|format!("{a}, {b}")).unwrap_or_default();
+            out.push_str(format!("Channels ignored for XP: {channel_list}").as_str());
+        }
+        ctx.say(out).await?;
+    } else {
+        ctx.say("This command can only be used in a server.").await?;
+    }
+    Ok(())
+}
+
+///Sets the minimum time between text XP being applied (e.g. `50ms`).
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+)]
+async fn apply_interval(ctx: Context<'_>, #[description = "A duration like 50ms, 2min or 1h 30m"] duration: String) -> Result<(), Error> {
+    if let Some(guild) = ctx.guild_id() {
+        let interval = match parse_interval(&duration) {
+            Ok(v) => v,
+            Err(err) => {
+                ctx.say(err).await?;
+                return Ok(());
+            }
+        };
+        //Text XP gets divided by the apply interval, so a zero interval would stop text XP for every server.
+        if interval.microseconds <= 0 {
+            ctx.say("The interval must be longer than zero.").await?;
+            return Ok(());
+        }
+        let db = crate::get_db().await;
+        let guild = crate::converti(guild.get());
+        sqlx::query!("INSERT INTO xp (guild_id, txt_apply_interval) VALUES ($1, $2) ON CONFLICT (guild_id) DO UPDATE SET txt_apply_interval = $2", guild, interval).execute(&db).await?;
+        ctx.say(format!("Text XP will be applied at most every {}.", format_interval(&interval))).await?;
+    } else {
+        ctx.say("This command can only be used in a server.").await?;
+    }
+    Ok(())
+}
+
+///Sets the time window, in which too many messages are punished as spam (e.g. `2min`).
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+)]
+async fn punish_interval(ctx: Context<'_>, #[description = "A duration like 50ms, 2min or 1h 30m"] duration: String) -> Result<(), Error> {
+    if let Some(guild) = ctx.guild_id() {
+        let interval = match parse_interval(&duration) {
+            Ok(v) => v,
+            Err(err) => {
+                ctx.say(err).await?;
+                return Ok(());
+            }
+        };
+        let db = crate::get_db().await;
+        let guild = crate::converti(guild.get());
+        sqlx::query!("INSERT INTO xp (guild_id, txt_punish_interval) VALUES ($1, $2) ON CONFLICT (guild_id) DO UPDATE SET txt_punish_interval = $2", guild, interval).execute(&db).await?;
+        ctx.say(format!("Messages sent within {} will be punished as spam.", format_interval(&interval))).await?;
+    } else {
+        ctx.say("This command can only be used in a server.").await?;
+    }
+    Ok(())
+}
+
+#[derive(poise::ChoiceParameter)]
+enum Action {
+    #[name_localized("de", "Hinzufügen")]
+    Add,
+    #[name_localized("de", "Entfernen")]
+    Remove,
+}
+
+///Add or Remove Channels from the list of channels, that don't give any XP.
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+)]
+async fn ignored_channels(ctx: Context<'_>, action: Action, channel: serenity::ChannelId) -> Result<(), Error> {
+    if let Some(guild) = ctx.guild_id() {
+        let guild = crate::converti(guild.get());
+        let channel = crate::converti(channel.get());
+        let db = crate::get_db().await;
+        sqlx::query!("INSERT INTO xp (guild_id) VALUES ($1) ON CONFLICT DO NOTHING", guild).execute(&db).await?;
+        match action {
+            Action::Add => {
+                let out = sqlx::query!("INSERT INTO xp_channels_ignored (guild_id, channel_id) VALUES ($1, $2) ON CONFLICT DO NOTHING", guild, channel).execute(&db).await?;
+                let channel = crate::convertu(channel);
+                match out.rows_affected() {
+                    0 => ctx.say(format!("Channel <#{channel}> was already on the list of ignored channels for XP.")).await?,
+                    _ => ctx.say(format!("Added <#{channel}> to the list of ignored channels for XP.")).await?,
+                };
+            },
+            Action::Remove => {
+                let out = sqlx::query!("DELETE FROM xp_channels_ignored WHERE guild_id = $1 AND channel_id = $2", guild, channel).execute(&db).await?;
+                let channel = crate::convertu(channel);
+                match out.rows_affected() {
+                    0 => ctx.say(format!("Channel <#{channel}> was not on the list of ignored channels for XP.")).await?,
+                    _ => ctx.say(format!("Removed <#{channel}> from the list of ignored channels for XP.")).await?,
+                };
+            },
+        }
+    } else {
+        ctx.say("This command can only be used in a server.").await?;
+    }
+    Ok(())
+}
+
+///List channels, that don't give any XP.
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+)]
+async fn list_ignored_channels(ctx: Context<'_>) -> Result<(), Error> {
+    if let Some(guild) = ctx.guild_id() {
+        let guild = crate::converti(guild.get());
+        let db = crate::get_db().await;
+        let channels = sqlx::query!("SELECT channel_id FROM xp_channels_ignored WHERE guild_id = $1", guild).fetch_all(&db).await?;
+        if channels.is_empty() {
+            ctx.say("No channels are currently ignored for XP.").await?;
+        } else {
+            let channel_list = channels.into_iter().map(|v|format!("<#{}>", crate::convertu(v.channel_id))).reduce(|a,b|format!("{a}, {b}")).unwrap_or_default();
+            ctx.say(format!("Channels ignored for XP: {channel_list}")).await?;
+        }
+    } else {
+        ctx.say("This command can only be used in a server.").await?;
+    }
+    Ok(())
+}
+
+///Chooses how much XP each level needs. Changing the formula doesn't announce any level-ups.
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+)]
+async fn level_formula(
+    ctx: Context<'_>,
+    formula: LevelFormula,
+    #[description = "XP per level (linear) or multiplier of level² (quadratic). Defaults to 100."] #[min = 1] factor: Option<i64>,
+) -> Result<(), Error> {
+    if let Some(guild) = ctx.guild_id() {
+        let db = crate::get_db().await;
+        let guild = crate::converti(guild.get());
+        let factor = factor.unwrap_or(100).max(1);
+        let mut transaction = db.begin().await?;
+        sqlx::query!("INSERT INTO xp (guild_id, level_formula, level_factor) VALUES ($1, $2, $3) ON CONFLICT (guild_id) DO UPDATE SET level_formula = $2, level_factor = $3", guild, formula.as_str(), factor).execute(&mut *transaction).await?;
+        //Everyone's level may jump, so the next level gets stored silently.
+        sqlx::query!("UPDATE xp_user SET level = NULL WHERE guild_id = $1", guild).execute(&mut *transaction).await?;
+        transaction.commit().await?;
+        let mut out = format!("Changed the level formula to {}.", poise::ChoiceParameter::name(&formula));
+        if formula == LevelFormula::Custom {
+            out.push_str(" Use `/settings xp level_table` to set the XP needed per level.");
+        }
+        ctx.say(out).await?;
+    } else {
+        ctx.say("This command can only be used in a server.").await?;
+    }
+    Ok(())
+}
+
+///Sets the total XP needed per level for the custom level formula.
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+)]
+async fn level_table(
+    ctx: Context<'_>,
+    #[description = "Total XP for level 1, 2, 3, ... separated by commas, e.g. 100, 250, 500"] levels: String,
+) -> Result<(), Error> {
+    if let Some(guild) = ctx.guild_id() {
+        let levels = match levels.split(',').map(|v| v.trim().parse::<i64>()).collect::<Result<Vec<_>, _>>() {
+            Ok(v) => v,
+            Err(err) => {
+                ctx.say(format!("Every level needs to be a whole number: {err}")).await?;
+                return Ok(());
+            }
+        };
+        if levels.len() > MAX_CUSTOM_LEVELS {
+            ctx.say(format!("At most {MAX_CUSTOM_LEVELS} levels are supported.")).await?;
+            return Ok(());
+        }
+        if levels.first().is_some_and(|v| *v <= 0) || levels.windows(2).any(|v| v[0] >= v[1]) {
+            ctx.say("The XP per level must be positive and increase with every level.").await?;
+            return Ok(());
+        }
+        let db = crate::get_db().await;
+        let guild = crate::converti(guild.get());
+        let numbers = (1..=levels.len() as i32).collect::<Vec<_>>();
+        let mut transaction = db.begin().await?;
+        sqlx::query!("DELETE FROM xp_level_table WHERE guild_id = $1", guild).execute(&mut *transaction).await?;
+        sqlx::query!("INSERT INTO xp_level_table (guild_id, level, xp) SELECT $1, * FROM unnest($2::integer[], $3::bigint[])", guild, numbers.as_slice(), levels.as_slice()).execute(&mut *transaction).await?;
+        sqlx::query!("UPDATE xp_user SET level = NULL WHERE guild_id = $1", guild).execute(&mut *transaction).await?;
+        transaction.commit().await?;
+        let formula = sqlx::query!("SELECT level_formula FROM xp WHERE guild_id = $1", guild).fetch_optional(&db).await?;
+        let mut out = format!("Set the custom level table to {} levels.", levels.len());
+        if formula.is_none_or(|v| v.level_formula != LevelFormula::Custom.as_str()) {
+            out.push_str(" It will be used, once the level formula is set to Custom.");
+        }
+        ctx.say(out).await?;
+    } else {
+        ctx.say("This command can only be used in a server.").await?;
+    }
+    Ok(())
+}
+
+///Sets how much text XP and voice time count towards levels.
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+)]
+async fn weights(
+    ctx: Context<'_>,
+    #[description = "Level XP per text XP. Defaults to 1."] #[min = 0] text: Option<f64>,
+    #[description = "Level XP per second in a voice channel. Defaults to 1."] #[min = 0] voice: Option<f64>,
+) -> Result<(), Error> {
+    if let Some(guild) = ctx.guild_id() {
+        let text = text.unwrap_or(1.);
+        let voice = voice.unwrap_or(1.);
+        if !text.is_finite() || !voice.is_finite() || text < 0. || voice < 0. {
+            ctx.say("The weights must be positive numbers.").await?;
+            return Ok(());
+        }
+        let db = crate::get_db().await;
+        let guild = crate::converti(guild.get());
+        let mut transaction = db.begin().await?;
+        sqlx::query!("INSERT INTO xp (guild_id, txt_weight, vc_weight) VALUES ($1, $2, $3) ON CONFLICT (guild_id) DO UPDATE SET txt_weight = $2, vc_weight = $3", guild, text, voice).execute(&mut *transaction).await?;
+        sqlx::query!("UPDATE xp_user SET level = NULL WHERE guild_id = $1", guild).execute(&mut *transaction).await?;
+        transaction.commit().await?;
+        ctx.say(format!("Level XP is now text XP × {text} + voice seconds × {voice}.")).await?;
+    } else {
+        ctx.say("This command can only be used in a server.").await?;
+    }
+    Ok(())
+}
+
+#[derive(poise::ChoiceParameter)]
+enum AnnouncementDestination {
+    #[name_localized("de", "Kanal")]
+    Channel,
+    #[name_localized("de", "Direktnachricht")]
+    Dm,
+    #[name_localized("de", "Aus")]
+    Off,
+}
+
+///Chooses where level-ups get announced. The message may contain {user} and {level}.
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+)]
+async fn level_up_announcements(
+    ctx: Context<'_>,
+    destination: AnnouncementDestination,
+    #[description = "Required, if the destination is a channel"] channel: Option<serenity::ChannelId>,
+    #[description = "e.g. \"{user} reached level {level}!\". Leave out for the default message."] #[max_length = 2000] message: Option<String>,
+) -> Result<(), Error> {
+    if let Some(guild) = ctx.guild_id() {
+        let (channel, dm) = match destination {
+            AnnouncementDestination::Channel => match channel {
+                Some(v) => (Some(crate::converti(v.get())), false),
+                None => {
+                    ctx.say("Please choose the channel, where level-ups should be announced.").await?;
+                    return Ok(());
+                }
+            },
+            AnnouncementDestination::Dm => (None, true),
+            AnnouncementDestination::Off => (None, false),
+        };
+        let message = message.filter(|v| !v.trim().is_empty());
+        let db = crate::get_db().await;
+        let guild = crate::converti(guild.get());
+        sqlx::query!(
+            "INSERT INTO xp (guild_id, level_up_channel, level_up_dm, level_up_message) VALUES ($1, $2, $3, $4) ON CONFLICT (guild_id) DO UPDATE SET level_up_channel = $2, level_up_dm = $3, level_up_message = $4",
+            guild, channel, dm, message
+        ).execute(&db).await?;
+        match (channel, dm) {
+            (Some(channel), _) => ctx.say(format!("Level-ups will be announced in <#{}>.", crate::convertu(channel))).await?,
+            (None, true) => ctx.say("Level-ups will be announced via DM.").await?,
+            (None, false) => ctx.say("Level-ups will no longer be announced.").await?,
+        };
+    } else {
+        ctx.say("This command can only be used in a server.").await?;
+    }
+    Ok(())
+}
+
+/// Checks a multiplier from a command. Returns an error message for the user, if it's invalid.
+fn check_multiplier(multiplier: f64) -> Result<(), &'static str> {
+    if !multiplier.is_finite() || multiplier < 0. {
+        return Err("The multiplier must be a positive number.");
+    }
+    Ok(())
+}
+
+///Multiplies the XP of members with a role. Leave out the multiplier to remove it.
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+)]
+async fn role_multiplier(
+    ctx: Context<'_>,
+    role: serenity::RoleId,
+    #[description = "e.g. 2 for double XP or 0 for no XP"] #[min = 0] multiplier: Option<f64>,
+) -> Result<(), Error> {
+    if let Some(guild) = ctx.guild_id() {
+        let db = crate::get_db().await;
+        let guild = crate::converti(guild.get());
+        let role_db = crate::converti(role.get());
+        match multiplier {
+            Some(multiplier) => {
+                if let Err(err) = check_multiplier(multiplier) {
+                    ctx.say(err).await?;
+                    return Ok(());
+                }
+                sqlx::query!(
+                    "INSERT INTO xp_multipliers_role (guild_id, role_id, multiplier) VALUES ($1, $2, $3) ON CONFLICT (guild_id, role_id) DO UPDATE SET multiplier = $3",
+                    guild, role_db, multiplier
+                ).execute(&db).await?;
+                ctx.send(poise::CreateReply::default().content(format!("Members with <@&{role}> now get {multiplier}× XP.")).allowed_mentions(serenity::CreateAllowedMentions::new())).await?;
+            },
+            None => {
+                sqlx::query!("DELETE FROM xp_multipliers_role WHERE guild_id = $1 AND role_id = $2", guild, role_db).execute(&db).await?;
+                ctx.send(poise::CreateReply::default().content(format!("Removed the XP multiplier of <@&{role}>.")).allowed_mentions(serenity::CreateAllowedMentions::new())).await?;
+            },
+        }
+    } else {
+        ctx.say("This command can only be used in a server.").await?;
+    }
+    Ok(())
+}
+
+///Multiplies the XP members get in a channel. Leave out the multiplier to remove it.
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+)]
+async fn channel_multiplier(
+    ctx: Context<'_>,
+    channel: serenity::ChannelId,
+    #[description = "e.g. 2 for double XP or 0 for no XP"] #[min = 0] multiplier: Option<f64>,
+) -> Result<(), Error> {
+    if let Some(guild) = ctx.guild_id() {
+        let db = crate::get_db().await;
+        let guild = crate::converti(guild.get());
+        let channel_db = crate::converti(channel.get());
+        match multiplier {
+            Some(multiplier) => {
+                if let Err(err) = check_multiplier(multiplier) {
+                    ctx.say(err).await?;
+                    return Ok(());
+                }
+                sqlx::query!(
+                    "INSERT INTO xp_multipliers_channel (guild_id, channel_id, multiplier) VALUES ($1, $2, $3) ON CONFLICT (guild_id, channel_id) DO UPDATE SET multiplier = $3",
+                    guild, channel_db, multiplier
+                ).execute(&db).await?;
+                ctx.say(format!("Members now get {multiplier}× XP in <#{channel}>.")).await?;
+            },
+            None => {
+                sqlx::query!("DELETE FROM xp_multipliers_channel WHERE guild_id = $1 AND channel_id = $2", guild, channel_db).execute(&db).await?;
+                ctx.say(format!("Removed the XP multiplier of <#{channel}>.")).await?;
+            },
+        }
+    } else {
+        ctx.say("This command can only be used in a server.").await?;
+    }
+    Ok(())
+}
+
+///Chooses how the multipliers of roles and the channel are combined.
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+)]
+async fn multiplier_rule(ctx: Context<'_>, rule: MultiplierRule) -> Result<(), Error> {
+    if let Some(guild) = ctx.guild_id() {
+        let db = crate::get_db().await;
+        let guild = crate::converti(guild.get());
+        sqlx::query!(
+            "INSERT INTO xp (guild_id, multiplier_rule) VALUES ($1, $2) ON CONFLICT (guild_id) DO UPDATE SET multiplier_rule = $2",
+            guild, rule.as_str()
+        ).execute(&db).await?;
+        let explanation = match rule {
+            MultiplierRule::Max => "Members get the highest of their multipliers.",
+            MultiplierRule::Product => "Members get all their multipliers multiplied.",
+        };
+        ctx.say(explanation).await?;
+    } else {
+        ctx.say("This command can only be used in a server.").await?;
+    }
+    Ok(())
+}
+
+///Lists the XP multipliers of roles and channels.
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+)]
+async fn list_multipliers(ctx: Context<'_>) -> Result<(), Error> {
+    if let Some(guild) = ctx.guild_id() {
+        let db = crate::get_db().await;
+        let guild = crate::converti(guild.get());
+        let roles = sqlx::query!("SELECT role_id, multiplier FROM xp_multipliers_role WHERE guild_id = $1 ORDER BY multiplier DESC", guild).fetch_all(&db).await?;
+        let channels = sqlx::query!("SELECT channel_id, multiplier FROM xp_multipliers_channel WHERE guild_id = $1 ORDER BY multiplier DESC", guild).fetch_all(&db).await?;
+        if roles.is_empty() && channels.is_empty() {
+            ctx.say("There are no XP multipliers.").await?;
+            return Ok(());
+        }
+        let mut out = String::new();
+        for role in roles {
+            out.push_str(format!("- <@&{}>: {}×\n", crate::convertu(role.role_id), role.multiplier).as_str());
+        }
+        for channel in channels {
+            out.push_str(format!("- <#{}>: {}×\n", crate::convertu(channel.channel_id), channel.multiplier).as_str());
+        }
+        ctx.send(poise::CreateReply::default().content(out).allowed_mentions(serenity::CreateAllowedMentions::new())).await?;
+    } else {
+        ctx.say("This command can only be used in a server.").await?;
+    }
+    Ok(())
+}
+
+fn describe_voice_rules(rules: VoiceRules) -> String {
+    let mut states = Vec::new();
+    if rules.pause_deafened { states.push("deafened"); }
+    if rules.pause_muted { states.push("server-muted"); }
+    if rules.pause_alone { states.push("alone in a channel"); }
+    if rules.pause_afk { states.push("in the AFK channel"); }
+    match states.split_last() {
+        None => "only in ignored channels".to_string(),
+        Some((last, [])) => format!("while members are {last} or in ignored channels"),
+        Some((last, rest)) => format!("while members are {}, {last} or in ignored channels", rest.join(", ")),
+    }
+}
+
+///Chooses when members don't get voice time. Options you leave out stay unchanged.
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+)]
+async fn voice_rules(
+    ctx: Context<'_>,
+    #[description = "Pause while self-deafened or deafened by the server"] deafened: Option<bool>,
+    #[description = "Pause while muted by the server"] muted: Option<bool>,
+    #[description = "Pause while nobody else (except bots) is in the channel"] alone: Option<bool>,
+    #[description = "Pause in the server's AFK channel"] afk: Option<bool>,
+) -> Result<(), Error> {
+    if let Some(guild) = ctx.guild_id() {
+        let db = crate::get_db().await;
+        let guild = crate::converti(guild.get());
+        sqlx::query!(
+            "INSERT INTO xp (guild_id, vc_pause_deafened, vc_pause_muted, vc_pause_alone, vc_pause_afk) VALUES ($1, COALESCE($2, true), COALESCE($3, true), COALESCE($4, true), COALESCE($5, true))
+ON CONFLICT (guild_id) DO UPDATE SET
+    vc_pause_deafened = COALESCE($2, xp.vc_pause_deafened),
+    vc_pause_muted = COALESCE($3, xp.vc_pause_muted),
+    vc_pause_alone = COALESCE($4, xp.vc_pause_alone),
+    vc_pause_afk = COALESCE($5, xp.vc_pause_afk)",
+            guild, deafened, muted, alone, afk
+        ).execute(&db).await?;
+        let rules = VoiceRules::get(&db, guild).await?;
+        ctx.say(format!("Voice time is now paused {}. This applies from the next voice state change in a channel.", describe_voice_rules(rules))).await?;
+    } else {
+        ctx.say("This command can only be used in a server.").await?;
+    }
+    Ok(())
+}
+
+/// Formats a change between two periods, e.g. `+12%`.
+fn format_change(previous: i64, current: i64) -> String {
+    if previous == 0 {
+        return if current == 0 { "±0%".to_string() } else { "new".to_string() };
+    }
+    let change = (current - previous) as f64 / previous as f64 * 100.;
+    format!("{change:+.0}%")
+}
+
+///Shows the daily activity of this server and compares it to the period before.
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+)]
+async fn trends(
+    ctx: Context<'_>,
+    #[description = "Number of days to show, including today. Defaults to 14."] #[min = 1] #[max = 60] days: Option<u64>,
+) -> Result<(), Error> {
+    if let Some(guild) = ctx.guild_id() {
+        let days = days.unwrap_or(14).clamp(1, 60);
+        let db = crate::get_db().await;
+        let guild = crate::converti(guild.get());
+        let to = chrono::Utc::now().date_naive();
+        let from = to - chrono::Days::new(days - 1);
+        let previous_from = from - chrono::Days::new(days);
+        let daily = sqlx::query!(
+            r#"SELECT
+    series.day::date as "day!: chrono::NaiveDate",
+    count(xp_history.user_id) as "members!",
+    COALESCE(sum(xp_history.txt), 0)::bigint as "txt!",
+    COALESCE(EXTRACT(EPOCH FROM sum(xp_history.vc)), 0)::bigint as "vc!"
+FROM generate_series($2::date, $3::date, interval '1 day') as series(day)
+LEFT JOIN xp_history ON xp_history.guild_id = $1 AND xp_history.day = series.day::date
+GROUP BY series.day ORDER BY series.day"#,
+            guild, from as _, to as _
+        ).fetch_all(&db).await?;
+        let totals = sqlx::query!(
+            r#"SELECT
+    count(DISTINCT user_id) FILTER (WHERE day >= $3) as "members!",
+    COALESCE(sum(txt) FILTER (WHERE day >= $3), 0)::bigint as "txt!",
+    COALESCE(EXTRACT(EPOCH FROM sum(vc) FILTER (WHERE day >= $3)), 0)::bigint as "vc!",
+    count(DISTINCT user_id) FILTER (WHERE day < $3) as "previous_members!",
+    COALESCE(sum(txt) FILTER (WHERE day < $3), 0)::bigint as "previous_txt!",
+    COALESCE(EXTRACT(EPOCH FROM sum(vc) FILTER (WHERE day < $3)), 0)::bigint as "previous_vc!"
+FROM xp_history WHERE guild_id = $1 AND day BETWEEN $2 AND $4"#,
+            guild, previous_from as _, from as _, to as _
+        ).fetch_one(&db).await?;
+
+        let mut table = format!("{:<10} {:>7} {:>8} {:>7}\n", "Day", "Members", "Text XP", "Voice");
+        for day in daily {
+            table.push_str(format!("{:<10} {:>7} {:>8} {:>6.1}h\n", day.day.format("%Y-%m-%d"), day.members, day.txt, day.vc as f64 / 3600.).as_str());
+        }
+        let summary = format!(
+            "**Last {days} days** (compared to the {days} days before)\nActive members: {} ({})\nText XP: {} ({})\nVoice: {:.1}h ({})",
+            totals.members, format_change(totals.previous_members, totals.members),
+            totals.txt, format_change(totals.previous_txt, totals.txt),
+            totals.vc as f64 / 3600., format_change(totals.previous_vc, totals.vc),
+        );
+        let embed = serenity::CreateEmbed::new()
+            .title("XP Trends")
+            .description(format!("{summary}\n```\n{table}```"))
+            .footer(serenity::CreateEmbedFooter::new("Days are in UTC"));
+        ctx.send(poise::CreateReply::default().embed(embed)).await?;
+    } else {
+        ctx.say("This command can only be used in a server.").await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,385 @@
+use poise::CreateReply;
+use poise::serenity_prelude as serenity;
+use serenity::{Permissions, PermissionOverwrite, PermissionOverwriteType};
+use crate::client::commands::{Context, Error};
+use crate::client::temp_channels::{move_owner_permissions, MAX_REMEMBERED_MEMBERS};
+
+///Commands for the owner of a temporary voice channel. They only work in your own channel.
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands(
+        "lock",
+        "unlock",
+        "hide",
+        "unhide",
+        "limit",
+        "rename",
+        "kick",
+        "ban",
+        "permit",
+        "claim",
+        "transfer",
+        "remember",
+    ),
+    subcommand_required,
+)]
+pub async fn vc(_: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// The temporary channel the author of a command is in.
+struct TempChannel {
+    guild_id: serenity::GuildId,
+    channel: serenity::GuildChannel,
+    owner: Option<serenity::UserId>,
+}
+impl TempChannel {
+    /// Whether `user` is in this channel, according to `temp_channels_created_users`.
+    async fn has_member(&self, db: &sqlx::PgPool, user: serenity::UserId) -> Result<bool, sqlx::Error> {
+        let count = sqlx::query!(
+            r#"SELECT Count(*) as "count!" FROM temp_channels_created_users WHERE guild_id = $1 AND channel_id = $2 AND user_id = $3"#,
+            crate::converti(self.guild_id.get()), crate::converti(self.channel.id.get()), crate::converti(user.get())
+        ).fetch_one(db).await?;
+        Ok(count.count > 0)
+    }
+    /// The overwrite for `kind`, or an empty one, if the channel has none.
+    fn overwrite(&self, kind: PermissionOverwriteType) -> PermissionOverwrite {
+        self.channel.permission_overwrites.iter()
+            .find(|v| v.kind == kind)
+            .cloned()
+            .unwrap_or(PermissionOverwrite { allow: Permissions::empty(), deny: Permissions::empty(), kind })
+    }
+    fn everyone(&self) -> PermissionOverwriteType {
+        PermissionOverwriteType::Role(serenity::RoleId::new(self.guild_id.get()))
+    }
+}
+
+/// A change to their channel, that gets remembered for the next channel a member creates.
+enum Preference {
+    Name(String),
+    Limit(u32),
+    Locked(bool),
+    Hidden(bool),
+    Permitted(serenity::UserId),
+    Banned(serenity::UserId),
+}
+
+/// Remembers a change to the channel of `user`, unless they opted out.
+/// Only the newest [`MAX_REMEMBERED_MEMBERS`] permitted and banned members are kept.
+async fn save_preference(db: &sqlx::PgPool, guild_id: serenity::GuildId, user: serenity::UserId, preference: Preference) -> Result<(), sqlx::Error> {
+    let guild = crate::converti(guild_id.get());
+    let user = crate::converti(user.get());
+    match preference {
+        Preference::Name(name) => sqlx::query!(
+            "INSERT INTO temp_channels_preferences (guild_id, user_id, name) VALUES ($1, $2, $3)
+ON CONFLICT (guild_id, user_id) DO UPDATE SET name = excluded.name WHERE temp_channels_preferences.remember",
+            guild, user, name
+        ).execute(db).await?,
+        Preference::Limit(limit) => sqlx::query!(
+            "INSERT INTO temp_channels_preferences (guild_id, user_id, user_limit) VALUES ($1, $2, NULLIF($3, 0))
+ON CONFLICT (guild_id, user_id) DO UPDATE SET user_limit = excluded.user_limit WHERE temp_channels_preferences.remember",
+            guild, user, i32::try_from(limit).unwrap_or(0)
+        ).execute(db).await?,
+        Preference::Locked(locked) => sqlx::query!(
+            "INSERT INTO temp_channels_preferences (guild_id, user_id, locked) VALUES ($1, $2, $3)
+ON CONFLICT (guild_id, user_id) DO UPDATE SET locked = excluded.locked WHERE temp_channels_preferences.remember",
+            guild, user, locked
+        ).execute(db).await?,
+        Preference::Hidden(hidden) => sqlx::query!(
+            "INSERT INTO temp_channels_preferences (guild_id, user_id, hidden) VALUES ($1, $2, $3)
+ON CONFLICT (guild_id, user_id) DO UPDATE SET hidden = excluded.hidden WHERE temp_channels_preferences.remember",
+            guild, user, hidden
+        ).execute(db).await?,
+        Preference::Permitted(target) => sqlx::query!(
+            "INSERT INTO temp_channels_preferences (guild_id, user_id, permitted) VALUES ($1, $2, ARRAY[$3::bigint])
+ON CONFLICT (guild_id, user_id) DO UPDATE SET
+    permitted = (SELECT v[GREATEST(cardinality(v) - $4 + 1, 1):] FROM (SELECT array_append(array_remove(temp_channels_preferences.permitted, $3), $3) as v) as appended),
+    banned = array_remove(temp_channels_preferences.banned, $3)
+WHERE temp_channels_preferences.remember",
+            guild, user, crate::converti(target.get()), MAX_REMEMBERED_MEMBERS as i32
+        ).execute(db).await?,
+        Preference::Banned(target) => sqlx::query!(
+            "INSERT INTO temp_channels_preferences (guild_id, user_id, banned) VALUES ($1, $2, ARRAY[$3::bigint])
+ON CONFLICT (guild_id, user_id) DO UPDATE SET
+    banned = (SELECT v[GREATEST(cardinality(v) - $4 + 1, 1):] FROM (SELECT array_append(array_remove(temp_channels_preferences.banned, $3), $3) as v) as appended),
+    permitted = array_remove(temp_channels_preferences.permitted, $3)
+WHERE temp_channels_preferences.remember",
+            guild, user, crate::converti(target.get()), MAX_REMEMBERED_MEMBERS as i32
+        ).execute(db).await?,
+    };
+    Ok(())
+}
+
+async fn reply(ctx: Context<'_>, content: impl Into<String>) -> Result<(), Error> {
+    ctx.send(CreateReply::default().content(content).allowed_mentions(serenity::CreateAllowedMentions::new()).ephemeral(true)).await?;
+    Ok(())
+}
+
+/// Gets the temporary channel of the author. Tells them and returns `None`, if they are not in one.
+async fn current_channel(ctx: Context<'_>, db: &sqlx::PgPool) -> Result<Option<TempChannel>, Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        ctx.say("This command can only be used in a server.").await?;
+        return Ok(None);
+    };
+    let Some(created) = sqlx::query!(
+        "SELECT channel_id, owner_id FROM temp_channels_created_users JOIN temp_channels_created USING (guild_id, channel_id) WHERE guild_id = $1 AND user_id = $2",
+        crate::converti(guild_id.get()), crate::converti(ctx.author().id.get())
+    ).fetch_optional(db).await? else {
+        reply(ctx, "You are not in a temporary voice channel.").await?;
+        return Ok(None);
+    };
+    let Some(channel) = serenity::ChannelId::new(crate::convertu(created.channel_id)).to_channel(ctx).await?.guild() else {
+        reply(ctx, "Your temporary voice channel does not exist anymore.").await?;
+        return Ok(None);
+    };
+    let owner = created.owner_id.map(|v| serenity::UserId::new(crate::convertu(v)));
+    Ok(Some(TempChannel { guild_id, channel, owner }))
+}
+
+/// Like [`current_channel`], but also requires the author to own the channel.
+async fn owned_channel(ctx: Context<'_>, db: &sqlx::PgPool) -> Result<Option<TempChannel>, Error> {
+    let Some(channel) = current_channel(ctx, db).await? else {
+        return Ok(None);
+    };
+    if channel.owner != Some(ctx.author().id) {
+        reply(ctx, "Only the owner of this channel can do that. If they left, use `/vc claim`.").await?;
+        return Ok(None);
+    }
+    Ok(Some(channel))
+}
+
+/// Allows and denies permissions for `kind` in the channel. Removes the overwrite, if nothing is left of it.
+async fn change_overwrite(ctx: Context<'_>, channel: &TempChannel, kind: PermissionOverwriteType, allow: Permissions, deny: Permissions) -> Result<(), Error> {
+    let mut overwrite = channel.overwrite(kind);
+    overwrite.allow = (overwrite.allow - deny) | allow;
+    overwrite.deny = (overwrite.deny - allow) | deny;
+    save_overwrite(ctx, channel, overwrite).await
+}
+
+/// Neither allows nor denies `permissions` for `kind` in the channel anymore.
+async fn reset_overwrite(ctx: Context<'_>, channel: &TempChannel, kind: PermissionOverwriteType, permissions: Permissions) -> Result<(), Error> {
+    let mut overwrite = channel.overwrite(kind);
+    overwrite.allow.remove(permissions);
+    overwrite.deny.remove(permissions);
+    save_overwrite(ctx, channel, overwrite).await
+}
+
+async fn save_overwrite(ctx: Context<'_>, channel: &TempChannel, overwrite: PermissionOverwrite) -> Result<(), Error> {
+    if !overwrite.allow.is_empty() || !overwrite.deny.is_empty() {
+        channel.channel.create_permission(ctx, overwrite).await?;
+    } else if channel.channel.permission_overwrites.iter().any(|v| v.kind == overwrite.kind) {
+        channel.channel.delete_permission(ctx, overwrite.kind).await?;
+    }
+    Ok(())
+}
+
+///Stops others from joining your channel.
+#[poise::command(slash_command, guild_only)]
+async fn lock(ctx: Context<'_>) -> Result<(), Error> {
+    let db = crate::get_db().await;
+    let Some(channel) = owned_channel(ctx, &db).await? else {
+        return Ok(());
+    };
+    change_overwrite(ctx, &channel, channel.everyone(), Permissions::empty(), Permissions::CONNECT).await?;
+    save_preference(&db, channel.guild_id, ctx.author().id, Preference::Locked(true)).await?;
+    reply(ctx, "Locked your channel. Use `/vc permit` to let someone in.").await
+}
+
+///Lets everyone join your channel again.
+#[poise::command(slash_command, guild_only)]
+async fn unlock(ctx: Context<'_>) -> Result<(), Error> {
+    let db = crate::get_db().await;
+    let Some(channel) = owned_channel(ctx, &db).await? else {
+        return Ok(());
+    };
+    reset_overwrite(ctx, &channel, channel.everyone(), Permissions::CONNECT).await?;
+    save_preference(&db, channel.guild_id, ctx.author().id, Preference::Locked(false)).await?;
+    reply(ctx, "Unlocked your channel.").await
+}
+
+///Hides your channel from everyone, who wasn't permitted.
+#[poise::command(slash_command, guild_only)]
+async fn hide(ctx: Context<'_>) -> Result<(), Error> {
+    let db = crate::get_db().await;
+    let Some(channel) = owned_channel(ctx, &db).await? else {
+        return Ok(());
+    };
+    change_overwrite(ctx, &channel, channel.everyone(), Permissions::empty(), Permissions::VIEW_CHANNEL).await?;
+    save_preference(&db, channel.guild_id, ctx.author().id, Preference::Hidden(true)).await?;
+    reply(ctx, "Hid your channel. Use `/vc permit` to let someone see it.").await
+}
+
+///Makes your channel visible again.
+#[poise::command(slash_command, guild_only)]
+async fn unhide(ctx: Context<'_>) -> Result<(), Error> {
+    let db = crate::get_db().await;
+    let Some(channel) = owned_channel(ctx, &db).await? else {
+        return Ok(());
+    };
+    reset_overwrite(ctx, &channel, channel.everyone(), Permissions::VIEW_CHANNEL).await?;
+    save_preference(&db, channel.guild_id, ctx.author().id, Preference::Hidden(false)).await?;
+    reply(ctx, "Your channel is visible again.").await
+}
+
+///Sets how many members can join your channel. 0 removes the limit.
+#[poise::command(slash_command, guild_only)]
+async fn limit(ctx: Context<'_>, #[max = 99] limit: u32) -> Result<(), Error> {
+    let db = crate::get_db().await;
+    let Some(mut channel) = owned_channel(ctx, &db).await? else {
+        return Ok(());
+    };
+    ctx.defer_ephemeral().await?;
+    channel.channel.edit(ctx, serenity::EditChannel::new().user_limit(limit)).await?;
+    save_preference(&db, channel.guild_id, ctx.author().id, Preference::Limit(limit)).await?;
+    match limit {
+        0 => reply(ctx, "Removed the user limit of your channel.").await,
+        limit => reply(ctx, format!("Limited your channel to {limit} members.")).await,
+    }
+}
+
+///Renames your channel.
+#[poise::command(slash_command, guild_only)]
+async fn rename(ctx: Context<'_>, #[min_length = 1] #[max_length = 100] name: String) -> Result<(), Error> {
+    let db = crate::get_db().await;
+    let Some(mut channel) = owned_channel(ctx, &db).await? else {
+        return Ok(());
+    };
+    ctx.defer_ephemeral().await?;
+    channel.channel.edit(ctx, serenity::EditChannel::new().name(name.trim())).await?;
+    save_preference(&db, channel.guild_id, ctx.author().id, Preference::Name(name.trim().to_string())).await?;
+    reply(ctx, format!("Renamed your channel to {}.", name.trim())).await
+}
+
+///Disconnects a member from your channel.
+#[poise::command(slash_command, guild_only)]
+async fn kick(ctx: Context<'_>, user: serenity::UserId) -> Result<(), Error> {
+    let db = crate::get_db().await;
+    let Some(channel) = owned_channel(ctx, &db).await? else {
+        return Ok(());
+    };
+    if user == ctx.author().id {
+        return reply(ctx, "You can't kick yourself.").await;
+    }
+    if !channel.has_member(&db, user).await? {
+        return reply(ctx, format!("<@{user}> is not in your channel.")).await;
+    }
+    channel.guild_id.disconnect_member(ctx, user).await?;
+    reply(ctx, format!("Kicked <@{user}> from your channel.")).await
+}
+
+///Disconnects a member from your channel and stops them from seeing or joining it again.
+#[poise::command(slash_command, guild_only)]
+async fn ban(ctx: Context<'_>, user: serenity::UserId) -> Result<(), Error> {
+    let db = crate::get_db().await;
+    let Some(channel) = owned_channel(ctx, &db).await? else {
+        return Ok(());
+    };
+    if user == ctx.author().id {
+        return reply(ctx, "You can't ban yourself.").await;
+    }
+    change_overwrite(ctx, &channel, PermissionOverwriteType::Member(user), Permissions::empty(), Permissions::VIEW_CHANNEL | Permissions::CONNECT).await?;
+    save_preference(&db, channel.guild_id, ctx.author().id, Preference::Banned(user)).await?;
+    if channel.has_member(&db, user).await? {
+        channel.guild_id.disconnect_member(ctx, user).await?;
+    }
+    reply(ctx, format!("Banned <@{user}> from your channel. Use `/vc permit` to undo it.")).await
+}
+
+///Lets a member see and join your channel, even if it is locked or hidden.
+#[poise::command(slash_command, guild_only)]
+async fn permit(ctx: Context<'_>, user: serenity::UserId) -> Result<(), Error> {
+    let db = crate::get_db().await;
+    let Some(channel) = owned_channel(ctx, &db).await? else {
+        return Ok(());
+    };
+    change_overwrite(ctx, &channel, PermissionOverwriteType::Member(user), Permissions::VIEW_CHANNEL | Permissions::CONNECT, Permissions::empty()).await?;
+    save_preference(&db, channel.guild_id, ctx.author().id, Preference::Permitted(user)).await?;
+    reply(ctx, format!("<@{user}> can now see and join your channel.")).await
+}
+
+///Takes over the channel you are in, if its owner left.
+#[poise::command(slash_command, guild_only)]
+async fn claim(ctx: Context<'_>) -> Result<(), Error> {
+    let db = crate::get_db().await;
+    let Some(channel) = current_channel(ctx, &db).await? else {
+        return Ok(());
+    };
+    let author = ctx.author().id;
+    match channel.owner {
+        Some(owner) if owner == author => return reply(ctx, "You already own this channel.").await,
+        Some(owner) if channel.has_member(&db, owner).await? => return reply(ctx, format!("<@{owner}> still owns this channel.")).await,
+        Some(_) => {},
+        //Channels without an owner weren't created for anyone (e.g. made by admins), so only moderators may take them.
+        None => {
+            let member = ctx.author_member().await;
+            let manages = match (ctx.guild(), member) {
+                (Some(guild), Some(member)) => guild.user_permissions_in(&channel.channel, &member).manage_channels(),
+                _ => false,
+            };
+            if !manages {
+                return reply(ctx, "This channel has no owner, so only members who can manage channels can claim it.").await;
+            }
+        },
+    }
+    let claimed = sqlx::query!(
+        "UPDATE temp_channels_created SET owner_id = $3 WHERE guild_id = $1 AND channel_id = $2 AND owner_id IS NOT DISTINCT FROM $4",
+        crate::converti(channel.guild_id.get()), crate::converti(channel.channel.id.get()), crate::converti(author.get()), channel.owner.map(|v| crate::converti(v.get()))
+    ).execute(&db).await?;
+    if claimed.rows_affected() == 0 {
+        return reply(ctx, "Someone else got this channel first.").await;
+    }
+    move_owner_permissions(ctx.http(), channel.channel.id, channel.owner, author).await?;
+    reply(ctx, "You now own this channel.").await
+}
+
+///Gives your channel to another member in it.
+#[poise::command(slash_command, guild_only)]
+async fn transfer(ctx: Context<'_>, user: serenity::UserId) -> Result<(), Error> {
+    let db = crate::get_db().await;
+    let Some(channel) = owned_channel(ctx, &db).await? else {
+        return Ok(());
+    };
+    let author = ctx.author().id;
+    if user == author {
+        return reply(ctx, "You already own this channel.").await;
+    }
+    if !channel.has_member(&db, user).await? {
+        return reply(ctx, format!("<@{user}> is not in your channel.")).await;
+    }
+    let transferred = sqlx::query!(
+        "UPDATE temp_channels_created SET owner_id = $3 WHERE guild_id = $1 AND channel_id = $2 AND owner_id = $4",
+        crate::converti(channel.guild_id.get()), crate::converti(channel.channel.id.get()), crate::converti(user.get()), crate::converti(author.get())
+    ).execute(&db).await?;
+    if transferred.rows_affected() == 0 {
+        return reply(ctx, "You don't own this channel anymore.").await;
+    }
+    move_owner_permissions(ctx.http(), channel.channel.id, Some(author), user).await?;
+    reply(ctx, format!("<@{user}> now owns this channel.")).await
+}
+
+///Chooses whether the changes to your channel are remembered for the next channel you create.
+#[poise::command(slash_command, guild_only)]
+async fn remember(ctx: Context<'_>, enabled: bool) -> Result<(), Error> {
+    let Some(guild) = ctx.guild_id() else {
+        ctx.say("This command can only be used in a server.").await?;
+        return Ok(());
+    };
+    let db = crate::get_db().await;
+    //Opting out also forgets everything remembered so far.
+    sqlx::query!(
+        "INSERT INTO temp_channels_preferences (guild_id, user_id, remember) VALUES ($1, $2, $3)
+ON CONFLICT (guild_id, user_id) DO UPDATE SET remember = excluded.remember,
+    name = CASE WHEN excluded.remember THEN temp_channels_preferences.name END,
+    user_limit = CASE WHEN excluded.remember THEN temp_channels_preferences.user_limit END,
+    locked = excluded.remember AND temp_channels_preferences.locked,
+    hidden = excluded.remember AND temp_channels_preferences.hidden,
+    permitted = CASE WHEN excluded.remember THEN temp_channels_preferences.permitted ELSE '{}' END,
+    banned = CASE WHEN excluded.remember THEN temp_channels_preferences.banned ELSE '{}' END",
+        crate::converti(guild.get()), crate::converti(ctx.author().id.get()), enabled
+    ).execute(&db).await?;
+    match enabled {
+        true => reply(ctx, "Changes to your channel will be applied to the next channel you create.").await,
+        false => reply(ctx, "Changes to your channel won't be remembered anymore. Everything remembered so far was forgotten.").await,
+    }
+}
@@ -0,0 +1,389 @@
+use poise::CreateReply;
+use poise::serenity_prelude as serenity;
+use crate::client::commands::{Context, Error};
+use crate::client::xp::LevelSettings;
+
+/// Members shown per leaderboard page.
+const PAGE_SIZE: i64 = 10;
+/// After this long without a button press, the leaderboard buttons are removed.
+const NAVIGATION_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10*60);
+
+#[derive(poise::ChoiceParameter, Debug, Copy, Clone, Default)]
+pub enum Kind {
+    #[name_localized("de", "Text")]
+    Text,
+    #[name_localized("de", "Sprache")]
+    Voice,
+    #[default]
+    #[name_localized("de", "Kombiniert")]
+    Combined,
+}
+impl Kind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Text => "text",
+            Self::Voice => "voice",
+            Self::Combined => "combined",
+        }
+    }
+}
+
+#[derive(poise::ChoiceParameter, Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum Window {
+    #[default]
+    #[name = "All time"]
+    #[name_localized("de", "Gesamt")]
+    AllTime,
+    #[name = "Today"]
+    #[name_localized("de", "Heute")]
+    Day,
+    #[name = "Last 7 days"]
+    #[name_localized("de", "Letzte 7 Tage")]
+    Week,
+    #[name = "Last 30 days"]
+    #[name_localized("de", "Letzte 30 Tage")]
+    Month,
+    #[name = "Custom range"]
+    #[name_localized("de", "Eigener Zeitraum")]
+    Custom,
+}
+
+/// An inclusive range of UTC days from the XP history.
+#[derive(Debug, Copy, Clone)]
+struct Days {
+    from: chrono::NaiveDate,
+    to: chrono::NaiveDate,
+}
+impl Days {
+    /// Resolves the time window options of a command. `None` means all time.
+    fn new(window: Option<Window>, from: Option<String>, to: Option<String>) -> Result<Option<Self>, String> {
+        let today = chrono::Utc::now().date_naive();
+        let parse = |date: &str| chrono::NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+            .map_err(|_| format!("`{date}` is not a valid date. Please use the format YYYY-MM-DD."));
+        //Setting a date without a window implies a custom range.
+        let window = window.unwrap_or(if from.is_some() || to.is_some() { Window::Custom } else { Window::AllTime });
+        match window {
+            Window::AllTime => Ok(None),
+            Window::Day => Ok(Some(Self { from: today, to: today })),
+            Window::Week => Ok(Some(Self { from: today - chrono::Days::new(6), to: today })),
+            Window::Month => Ok(Some(Self { from: today - chrono::Days::new(29), to: today })),
+            Window::Custom => {
+                let Some(from) = from else {
+                    return Err("Please set the start date (`from`) of the custom range.".to_string());
+                };
+                let from = parse(&from)?;
+                let to = match to {
+                    Some(to) => parse(&to)?,
+                    None => today,
+                };
+                if from > to {
+                    return Err("The start date has to be before the end date.".to_string());
+                }
+                Ok(Some(Self { from, to }))
+            },
+        }
+    }
+    fn describe(self) -> String {
+        if self.from == self.to {
+            self.from.format("%Y-%m-%d").to_string()
+        } else {
+            format!("{} to {}", self.from.format("%Y-%m-%d"), self.to.format("%Y-%m-%d"))
+        }
+    }
+}
+
+fn format_seconds(seconds: i64) -> String {
+    humantime::format_duration(std::time::Duration::from_secs(u64::try_from(seconds).unwrap_or(0))).to_string()
+}
+
+///Shows the XP, voice time, level and leaderboard position of a member.
+#[poise::command(
+    slash_command,
+    guild_only,
+)]
+pub async fn rank(
+    ctx: Context<'_>,
+    #[description = "Defaults to yourself"] user: Option<serenity::User>,
+    #[description = "Defaults to all time"] window: Option<Window>,
+    #[description = "Start of a custom range (YYYY-MM-DD, UTC)"] from: Option<String>,
+    #[description = "End of a custom range (YYYY-MM-DD, UTC). Defaults to today."] to: Option<String>,
+) -> Result<(), Error> {
+    let Some(guild) = ctx.guild_id() else {
+        ctx.say("This command can only be used in a server.").await?;
+        return Ok(());
+    };
+    let days = match Days::new(window, from, to) {
+        Ok(v) => v,
+        Err(err) => {
+            ctx.send(CreateReply::default().content(err).ephemeral(true)).await?;
+            return Ok(());
+        }
+    };
+    let user = user.as_ref().unwrap_or_else(|| ctx.author());
+    let db = crate::get_db().await;
+    let guild_db = crate::converti(guild.get());
+    let user_db = crate::converti(user.id.get());
+    let settings = LevelSettings::get(&db, guild_db).await?;
+    if let Some(days) = days {
+        return window_rank(ctx, &db, &settings, guild_db, user, days).await;
+    }
+    let Some(rank) = sqlx::query!(r#"
+WITH ranked AS (
+    SELECT user_id, txt, EXTRACT(EPOCH FROM vc)::bigint as vc, EXTRACT(EPOCH FROM vc_xp)::bigint as vc_xp, rank() OVER (ORDER BY txt * $3::float8 + EXTRACT(EPOCH FROM vc_xp)::float8 * $4::float8 DESC) as position
+    FROM xp_user WHERE guild_id = $1
+)
+SELECT
+    ranked.txt as "txt!",
+    ranked.vc as "vc!",
+    ranked.vc_xp as "vc_xp!",
+    ranked.position as "position!",
+    (SELECT count(*) FROM xp_user WHERE guild_id = $1) as "members!",
+    COALESCE((SELECT xp FROM xp_txt_tmp WHERE guild_id = $1 AND user_id = $2), 0) as "pending_txt!",
+    COALESCE((SELECT EXTRACT(EPOCH FROM now() - time)::bigint FROM xp_vc_tmp WHERE guild_id = $1 AND user_id = $2), 0) as "pending_vc!"
+FROM ranked WHERE ranked.user_id = $2"#,
+        guild_db, user_db, settings.txt_weight, settings.vc_weight
+    ).fetch_optional(&db).await? else {
+        ctx.send(CreateReply::default().content(format!("{} has no XP yet.", user.name)).ephemeral(true)).await?;
+        return Ok(());
+    };
+
+    let level = settings.progress(settings.xp(rank.txt, rank.vc_xp));
+    let level = match level.next_level_xp {
+        Some(next_level_xp) => format!("{} ({}/{next_level_xp} XP)", level.level, level.xp),
+        None => format!("{} (max level, +{} XP)", level.level, level.xp),
+    };
+    let mut text = format!("{} XP", rank.txt);
+    if rank.pending_txt > 0 {
+        text.push_str(format!(" (+{} pending)", rank.pending_txt).as_str());
+    }
+    let mut voice = format_seconds(rank.vc);
+    if rank.pending_vc > 0 {
+        voice.push_str(format!(" (+{} pending)", format_seconds(rank.pending_vc)).as_str());
+    }
+    let embed = serenity::CreateEmbed::new()
+        .author(serenity::CreateEmbedAuthor::new(user.name.as_str()).icon_url(user.face()))
+        .field("Level", level, true)
+        .field("Position", format!("#{} of {}", rank.position, rank.members), true)
+        .field("Text", text, false)
+        .field("Voice", voice, false);
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+/// Shows the XP and voice time a member got within `days`, according to the XP history.
+async fn window_rank(ctx: Context<'_>, db: &sqlx::PgPool, settings: &LevelSettings, guild: i64, user: &serenity::User, days: Days) -> Result<(), Error> {
+    let Some(rank) = sqlx::query!(r#"
+WITH totals AS (
+    SELECT user_id, sum(txt)::bigint as txt, EXTRACT(EPOCH FROM sum(vc))::bigint as vc, EXTRACT(EPOCH FROM sum(vc_xp))::bigint as vc_xp
+    FROM xp_history WHERE guild_id = $1 AND day BETWEEN $3 AND $4 GROUP BY user_id
+), ranked AS (
+    SELECT user_id, txt, vc, vc_xp, rank() OVER (ORDER BY txt * $5::float8 + vc_xp::float8 * $6::float8 DESC) as position FROM totals
+)
+SELECT
+    ranked.txt as "txt!",
+    ranked.vc as "vc!",
+    ranked.vc_xp as "vc_xp!",
+    ranked.position as "position!",
+    (SELECT count(*) FROM totals) as "members!"
+FROM ranked WHERE ranked.user_id = $2"#,
+        guild, crate::converti(user.id.get()), days.from as _, days.to as _, settings.txt_weight, settings.vc_weight
+    ).fetch_optional(db).await? else {
+        ctx.send(CreateReply::default().content(format!("{} got no XP from {}.", user.name, days.describe())).ephemeral(true)).await?;
+        return Ok(());
+    };
+    let embed = serenity::CreateEmbed::new()
+        .author(serenity::CreateEmbedAuthor::new(user.name.as_str()).icon_url(user.face()))
+        .field("XP", settings.xp(rank.txt, rank.vc_xp).to_string(), true)
+        .field("Position", format!("#{} of {}", rank.position, rank.members), true)
+        .field("Text", format!("{} XP", rank.txt), false)
+        .field("Voice", format_seconds(rank.vc), false)
+        .footer(serenity::CreateEmbedFooter::new(days.describe()));
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+async fn leaderboard_page(db: &sqlx::PgPool, settings: &LevelSettings, guild: i64, kind: Kind, days: Option<Days>, page: i64) -> Result<String, sqlx::Error> {
+    if let Some(days) = days {
+        return window_leaderboard_page(db, settings, guild, kind, days, page).await;
+    }
+    let rows = sqlx::query!(r#"
+SELECT
+    xp_user.user_id,
+    xp_user.txt,
+    EXTRACT(EPOCH FROM xp_user.vc)::bigint as "vc!",
+    EXTRACT(EPOCH FROM xp_user.vc_xp)::bigint as "vc_xp!",
+    COALESCE(xp_txt_tmp.xp, 0) as "pending_txt!",
+    COALESCE(EXTRACT(EPOCH FROM now() - xp_vc_tmp.time)::bigint, 0) as "pending_vc!",
+    COALESCE(EXTRACT(EPOCH FROM (now() - xp_vc_tmp.time) * xp_vc_tmp.multiplier)::bigint, 0) as "pending_vc_xp!"
+FROM xp_user
+LEFT JOIN xp_txt_tmp ON xp_txt_tmp.guild_id = xp_user.guild_id AND xp_txt_tmp.user_id = xp_user.user_id
+LEFT JOIN xp_vc_tmp ON xp_vc_tmp.guild_id = xp_user.guild_id AND xp_vc_tmp.user_id = xp_user.user_id
+WHERE xp_user.guild_id = $1
+ORDER BY CASE $2
+    WHEN 'text' THEN xp_user.txt::float8
+    WHEN 'voice' THEN EXTRACT(EPOCH FROM xp_user.vc)::float8
+    ELSE xp_user.txt * $5::float8 + EXTRACT(EPOCH FROM xp_user.vc_xp)::float8 * $6::float8
+END DESC, xp_user.user_id
+LIMIT $3 OFFSET $4"#,
+        guild, kind.as_str(), PAGE_SIZE, page * PAGE_SIZE, settings.txt_weight, settings.vc_weight
+    ).fetch_all(db).await?;
+
+    let mut out = String::new();
+    for (i, row) in rows.into_iter().enumerate() {
+        let position = page * PAGE_SIZE + i as i64 + 1;
+        let user = crate::convertu(row.user_id);
+        let value = match kind {
+            Kind::Text => format!("{} XP", row.txt),
+            Kind::Voice => format_seconds(row.vc),
+            Kind::Combined => {
+                let xp = settings.xp(row.txt, row.vc_xp);
+                format!("Level {} ({xp} XP)", settings.progress(xp).level)
+            },
+        };
+        let pending = match kind {
+            Kind::Text if row.pending_txt > 0 => format!(" (+{} pending)", row.pending_txt),
+            Kind::Voice if row.pending_vc > 0 => format!(" (+{} pending)", format_seconds(row.pending_vc)),
+            Kind::Combined if row.pending_txt + row.pending_vc_xp > 0 => format!(" (+{} pending)", settings.xp(row.pending_txt, row.pending_vc_xp)),
+            _ => String::new(),
+        };
+        out.push_str(format!("**#{position}** <@{user}>: {value}{pending}\n").as_str());
+    }
+    Ok(out)
+}
+
+async fn window_leaderboard_page(db: &sqlx::PgPool, settings: &LevelSettings, guild: i64, kind: Kind, days: Days, page: i64) -> Result<String, sqlx::Error> {
+    let rows = sqlx::query!(r#"
+WITH totals AS (
+    SELECT user_id, sum(txt)::bigint as txt, EXTRACT(EPOCH FROM sum(vc))::bigint as vc, EXTRACT(EPOCH FROM sum(vc_xp))::bigint as vc_xp
+    FROM xp_history WHERE guild_id = $1 AND day BETWEEN $2 AND $3 GROUP BY user_id
+)
+SELECT user_id, txt as "txt!", vc as "vc!", vc_xp as "vc_xp!" FROM totals
+ORDER BY CASE $4
+    WHEN 'text' THEN txt::float8
+    WHEN 'voice' THEN vc::float8
+    ELSE txt * $7::float8 + vc_xp::float8 * $8::float8
+END DESC, user_id
+LIMIT $5 OFFSET $6"#,
+        guild, days.from as _, days.to as _, kind.as_str(), PAGE_SIZE, page * PAGE_SIZE, settings.txt_weight, settings.vc_weight
+    ).fetch_all(db).await?;
+
+    let mut out = String::new();
+    for (i, row) in rows.into_iter().enumerate() {
+        let position = page * PAGE_SIZE + i as i64 + 1;
+        let user = crate::convertu(row.user_id);
+        let value = match kind {
+            Kind::Text => format!("{} XP", row.txt),
+            Kind::Voice => format_seconds(row.vc),
+            Kind::Combined => format!("{} XP", settings.xp(row.txt, row.vc_xp)),
+        };
+        out.push_str(format!("**#{position}** <@{user}>: {value}\n").as_str());
+    }
+    Ok(out)
+}
+
+fn leaderboard_embed(kind: Kind, days: Option<Days>, page: i64, pages: i64, content: String) -> serenity::CreateEmbed {
+    let title = match kind {
+        Kind::Text => "Text Leaderboard",
+        Kind::Voice => "Voice Leaderboard",
+        Kind::Combined => "Leaderboard",
+    };
+    let title = match days {
+        Some(days) => format!("{title} ({})", days.describe()),
+        None => title.to_string(),
+    };
+    serenity::CreateEmbed::new()
+        .title(title)
+        .description(content)
+        .footer(serenity::CreateEmbedFooter::new(format!("Page {}/{pages}", page + 1)))
+}
+
+///Shows the members with the most XP in this server.
+#[poise::command(
+    slash_command,
+    guild_only,
+)]
+pub async fn leaderboard(
+    ctx: Context<'_>,
+    #[description = "Defaults to combined"] kind: Option<Kind>,
+    #[description = "Defaults to all time"] window: Option<Window>,
+    #[description = "Start of a custom range (YYYY-MM-DD, UTC)"] from: Option<String>,
+    #[description = "End of a custom range (YYYY-MM-DD, UTC). Defaults to today."] to: Option<String>,
+) -> Result<(), Error> {
+    let Some(guild) = ctx.guild_id() else {
+        ctx.say("This command can only be used in a server.").await?;
+        return Ok(());
+    };
+    let days = match Days::new(window, from, to) {
+        Ok(v) => v,
+        Err(err) => {
+            ctx.send(CreateReply::default().content(err).ephemeral(true)).await?;
+            return Ok(());
+        }
+    };
+    let kind = kind.unwrap_or_default();
+    let guild = crate::converti(guild.get());
+    let db = crate::get_db().await;
+    let members = match days {
+        None => sqlx::query!(r#"SELECT count(*) as "count!" FROM xp_user WHERE guild_id = $1"#, guild).fetch_one(&db).await?.count,
+        Some(days) => sqlx::query!(
+            r#"SELECT count(DISTINCT user_id) as "count!" FROM xp_history WHERE guild_id = $1 AND day BETWEEN $2 AND $3"#,
+            guild, days.from as _, days.to as _
+        ).fetch_one(&db).await?.count,
+    };
+    if members == 0 {
+        match days {
+            None => ctx.say("Nobody has any XP in this server yet.").await?,
+            Some(days) => ctx.say(format!("Nobody got any XP in this server from {}.", days.describe())).await?,
+        };
+        return Ok(());
+    }
+    let pages = (members + PAGE_SIZE - 1) / PAGE_SIZE;
+    let settings = LevelSettings::get(&db, guild).await?;
+
+    // Define some unique identifiers for the navigation buttons
+    let ctx_id = ctx.id();
+    let prev_button_id = format!("{ctx_id}prev");
+    let next_button_id = format!("{ctx_id}next");
+    let buttons = |page: i64| vec![serenity::CreateActionRow::Buttons(vec![
+        serenity::CreateButton::new(&prev_button_id).emoji('◀').disabled(page == 0),
+        serenity::CreateButton::new(&next_button_id).emoji('▶').disabled(page + 1 >= pages),
+    ])];
+
+    let mut page = 0;
+    let mut reply = CreateReply::default().embed(leaderboard_embed(kind, days, page, pages, leaderboard_page(&db, &settings, guild, kind, days, page).await?));
+    if pages > 1 {
+        reply = reply.components(buttons(page));
+    }
+    let handle = ctx.send(reply).await?;
+    if pages <= 1 {
+        return Ok(());
+    }
+
+    while let Some(press) = serenity::collector::ComponentInteractionCollector::new(ctx)
+        // We defined our button IDs to start with `ctx_id`. If they don't, some other command's button was pressed
+        .filter(move |press| press.data.custom_id.starts_with(&ctx_id.to_string()))
+        .timeout(NAVIGATION_TIMEOUT)
+        .await
+    {
+        if press.data.custom_id == next_button_id {
+            page = (page + 1).min(pages - 1);
+        } else if press.data.custom_id == prev_button_id {
+            page = (page - 1).max(0);
+        } else {
+            continue;
+        }
+
+        let content = leaderboard_page(&db, &settings, guild, kind, days, page).await?;
+        press.create_response(
+            ctx.serenity_context(),
+            serenity::CreateInteractionResponse::UpdateMessage(
+                serenity::CreateInteractionResponseMessage::new()
+                    .embed(leaderboard_embed(kind, days, page, pages, content))
+                    .components(buttons(page))
+            ),
+        ).await?;
+    }
+    //Navigation timed out, so the buttons don't do anything anymore.
+    handle.edit(ctx, CreateReply::default().components(Vec::new())).await?;
+    Ok(())
+}
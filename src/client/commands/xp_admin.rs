@@ -0,0 +1,599 @@
+use poise::CreateReply;
+use poise::serenity_prelude as serenity;
+use sqlx::postgres::types::PgInterval;
+use crate::client::commands::{Context, Error};
+use crate::client::portability::{Conversion, ExportFormat, ImportOptions, ImportSource};
+
+/// How long the confirmation buttons of a reset can be pressed.
+const CONFIRMATION_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+/// Audit entries shown by `/xp audit`.
+const AUDIT_ENTRIES: i64 = 15;
+/// Reasons longer than this get shortened in `/xp audit`, so the entries fit into one message.
+const AUDIT_REASON_LENGTH: usize = 80;
+/// Discord's limit for the content of a message.
+const MAX_MESSAGE_LENGTH: usize = 2000;
+/// Largest export of another bot, that can be imported.
+const MAX_IMPORT_SIZE: u32 = 10*1024*1024;
+
+///Commands for moderators to correct the XP of members. Every change is recorded.
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands(
+        "give",
+        "take",
+        "set",
+        "reset",
+        "transfer",
+        "audit",
+        "import",
+        "export",
+    ),
+    default_member_permissions = "MANAGE_GUILD",
+    required_permissions = "MANAGE_GUILD",
+    subcommand_required,
+)]
+pub async fn xp(_: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Text XP and voice time given to a command.
+#[derive(Debug, Clone)]
+struct Amount {
+    txt: Option<i64>,
+    vc: Option<std::time::Duration>,
+}
+impl Amount {
+    fn new(text: Option<i64>, voice: Option<String>) -> Result<Self, String> {
+        let vc = match voice {
+            Some(voice) => Some(humantime::parse_duration(voice.trim())
+                .map_err(|err| format!("`{voice}` is not a valid duration ({err}). Try something like `30min` or `2h`."))?),
+            None => None,
+        };
+        if text.is_none() && vc.is_none() {
+            return Err("Please set the text XP, the voice time or both.".to_string());
+        }
+        Ok(Self { txt: text, vc })
+    }
+    fn interval(&self) -> Result<Option<PgInterval>, String> {
+        self.vc.map(PgInterval::try_from).transpose().map_err(|_| "The voice time is too long.".to_string())
+    }
+    fn describe(&self) -> String {
+        match (self.txt, self.vc) {
+            (Some(txt), Some(vc)) => format!("{txt} text XP and {} of voice time", humantime::format_duration(vc)),
+            (Some(txt), None) => format!("{txt} text XP"),
+            (None, Some(vc)) => format!("{} of voice time", humantime::format_duration(vc)),
+            (None, None) => "nothing".to_string(),
+        }
+    }
+}
+
+/// A row of `xp_audit`.
+struct AuditEntry<'a> {
+    guild_id: i64,
+    actor_id: i64,
+    action: &'a str,
+    user_id: Option<i64>,
+    target_user_id: Option<i64>,
+    txt: Option<i64>,
+    vc: Option<PgInterval>,
+    reason: Option<&'a str>,
+}
+impl AuditEntry<'_> {
+    async fn insert(self, transaction: &mut sqlx::PgConnection) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            "INSERT INTO xp_audit (guild_id, actor_id, action, user_id, target_user_id, txt, vc, reason) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+            self.guild_id, self.actor_id, self.action, self.user_id, self.target_user_id, self.txt, self.vc, self.reason
+        ).execute(transaction).await?;
+        Ok(())
+    }
+}
+
+/// Stores the new levels of members, updating their level rewards.
+async fn check_levels(ctx: Context<'_>, db: &sqlx::PgPool, members: &[(i64, i64)]) {
+    crate::client::xp::check_level_ups(db, ctx.http(), members).await;
+}
+
+/// Asks the author to confirm a destructive action with buttons. Returns `true`, if they confirmed it.
+async fn confirm(ctx: Context<'_>, prompt: String) -> Result<bool, Error> {
+    let ctx_id = ctx.id();
+    let confirm_button_id = format!("{ctx_id}confirm");
+    let cancel_button_id = format!("{ctx_id}cancel");
+    let handle = ctx.send(
+        CreateReply::default()
+            .content(prompt)
+            .allowed_mentions(serenity::CreateAllowedMentions::new())
+            .components(vec![serenity::CreateActionRow::Buttons(vec![
+                serenity::CreateButton::new(&confirm_button_id).label("Confirm").style(serenity::ButtonStyle::Danger),
+                serenity::CreateButton::new(&cancel_button_id).label("Cancel").style(serenity::ButtonStyle::Secondary),
+            ])])
+    ).await?;
+    let author = ctx.author().id;
+    let press = serenity::collector::ComponentInteractionCollector::new(ctx)
+        // We defined our button IDs to start with `ctx_id`. If they don't, some other command's button was pressed
+        .filter(move |press| press.data.custom_id.starts_with(&ctx_id.to_string()) && press.user.id == author)
+        .timeout(CONFIRMATION_TIMEOUT)
+        .await;
+    let confirmed = press.as_ref().is_some_and(|press| press.data.custom_id == confirm_button_id);
+    let content = if confirmed { "Confirmed." } else if press.is_some() { "Cancelled." } else { "Nothing was changed, because the confirmation timed out." };
+    match press {
+        Some(press) => {
+            press.create_response(
+                ctx.serenity_context(),
+                serenity::CreateInteractionResponse::UpdateMessage(
+                    serenity::CreateInteractionResponseMessage::new().content(content).components(Vec::new())
+                ),
+            ).await?;
+        },
+        None => {
+            handle.edit(ctx, CreateReply::default().content(content).components(Vec::new())).await?;
+        },
+    }
+    Ok(confirmed)
+}
+
+///Gives text XP and/or voice time to a member.
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+)]
+async fn give(
+    ctx: Context<'_>,
+    user: serenity::User,
+    #[description = "Text XP to give"] #[min = 1] text: Option<i64>,
+    #[description = "Voice time to give, e.g. 30min or 2h"] voice: Option<String>,
+    #[max_length = 500] reason: Option<String>,
+) -> Result<(), Error> {
+    let Some(guild) = ctx.guild_id() else {
+        ctx.say("This command can only be used in a server.").await?;
+        return Ok(());
+    };
+    let (amount, vc) = match Amount::new(text, voice).and_then(|amount| amount.interval().map(|vc| (amount, vc))) {
+        Ok(v) => v,
+        Err(err) => {
+            ctx.send(CreateReply::default().content(err).ephemeral(true)).await?;
+            return Ok(());
+        }
+    };
+    let db = crate::get_db().await;
+    let guild = crate::converti(guild.get());
+    let user_db = crate::converti(user.id.get());
+    let mut transaction = db.begin().await?;
+    sqlx::query!(
+        "INSERT INTO xp_user (guild_id, user_id, txt, vc, vc_xp) VALUES ($1, $2, COALESCE($3::bigint, 0), COALESCE($4, '0'::interval), COALESCE($4, '0'::interval))
+ON CONFLICT (guild_id, user_id) DO UPDATE SET txt = xp_user.txt + COALESCE($3::bigint, 0), vc = xp_user.vc + COALESCE($4, '0'::interval), vc_xp = xp_user.vc_xp + COALESCE($4, '0'::interval)",
+        guild, user_db, amount.txt, vc.clone()
+    ).execute(&mut *transaction).await?;
+    AuditEntry {
+        guild_id: guild,
+        actor_id: crate::converti(ctx.author().id.get()),
+        action: "give",
+        user_id: Some(user_db),
+        target_user_id: None,
+        txt: amount.txt,
+        vc,
+        reason: reason.as_deref(),
+    }.insert(&mut transaction).await?;
+    transaction.commit().await?;
+    ctx.send(CreateReply::default().content(format!("Gave {} to <@{}>.", amount.describe(), user.id)).allowed_mentions(serenity::CreateAllowedMentions::new())).await?;
+    check_levels(ctx, &db, &[(guild, user_db)]).await;
+    Ok(())
+}
+
+///Takes text XP and/or voice time from a member. Neither goes below zero.
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+)]
+async fn take(
+    ctx: Context<'_>,
+    user: serenity::User,
+    #[description = "Text XP to take"] #[min = 1] text: Option<i64>,
+    #[description = "Voice time to take, e.g. 30min or 2h"] voice: Option<String>,
+    #[max_length = 500] reason: Option<String>,
+) -> Result<(), Error> {
+    let Some(guild) = ctx.guild_id() else {
+        ctx.say("This command can only be used in a server.").await?;
+        return Ok(());
+    };
+    let (amount, vc) = match Amount::new(text, voice).and_then(|amount| amount.interval().map(|vc| (amount, vc))) {
+        Ok(v) => v,
+        Err(err) => {
+            ctx.send(CreateReply::default().content(err).ephemeral(true)).await?;
+            return Ok(());
+        }
+    };
+    let db = crate::get_db().await;
+    let guild = crate::converti(guild.get());
+    let user_db = crate::converti(user.id.get());
+    let mut transaction = db.begin().await?;
+    let updated = sqlx::query!(
+        "UPDATE xp_user SET txt = GREATEST(txt - COALESCE($3::bigint, 0), 0), vc = GREATEST(vc - COALESCE($4, '0'::interval), '0'::interval), vc_xp = GREATEST(vc_xp - COALESCE($4, '0'::interval), '0'::interval) WHERE guild_id = $1 AND user_id = $2",
+        guild, user_db, amount.txt, vc.clone()
+    ).execute(&mut *transaction).await?;
+    if updated.rows_affected() == 0 {
+        ctx.send(CreateReply::default().content(format!("<@{}> has no XP.", user.id)).allowed_mentions(serenity::CreateAllowedMentions::new()).ephemeral(true)).await?;
+        return Ok(());
+    }
+    sqlx::query!(
+        "UPDATE xp_history SET txt = GREATEST(txt - COALESCE($3::bigint, 0), 0), vc = GREATEST(vc - COALESCE($4, '0'::interval), '0'::interval), vc_xp = GREATEST(vc_xp - COALESCE($4, '0'::interval), '0'::interval)
+WHERE guild_id = $1 AND user_id = $2 AND day = (now() AT TIME ZONE 'UTC')::date",
+        guild, user_db, amount.txt, vc.clone()
+    ).execute(&mut *transaction).await?;
+    AuditEntry {
+        guild_id: guild,
+        actor_id: crate::converti(ctx.author().id.get()),
+        action: "take",
+        user_id: Some(user_db),
+        target_user_id: None,
+        txt: amount.txt,
+        vc,
+        reason: reason.as_deref(),
+    }.insert(&mut transaction).await?;
+    transaction.commit().await?;
+    ctx.send(CreateReply::default().content(format!("Took {} from <@{}>.", amount.describe(), user.id)).allowed_mentions(serenity::CreateAllowedMentions::new())).await?;
+    check_levels(ctx, &db, &[(guild, user_db)]).await;
+    Ok(())
+}
+
+///Sets the text XP and/or voice time of a member. Values you leave out stay unchanged.
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+)]
+async fn set(
+    ctx: Context<'_>,
+    user: serenity::User,
+    #[description = "New text XP"] #[min = 0] text: Option<i64>,
+    #[description = "New voice time, e.g. 30min or 2h"] voice: Option<String>,
+    #[max_length = 500] reason: Option<String>,
+) -> Result<(), Error> {
+    let Some(guild) = ctx.guild_id() else {
+        ctx.say("This command can only be used in a server.").await?;
+        return Ok(());
+    };
+    let (amount, vc) = match Amount::new(text, voice).and_then(|amount| amount.interval().map(|vc| (amount, vc))) {
+        Ok(v) => v,
+        Err(err) => {
+            ctx.send(CreateReply::default().content(err).ephemeral(true)).await?;
+            return Ok(());
+        }
+    };
+    let db = crate::get_db().await;
+    let guild = crate::converti(guild.get());
+    let user_db = crate::converti(user.id.get());
+    let mut transaction = db.begin().await?;
+    //Lowered XP also comes out of today's history, like with /xp take.
+    sqlx::query!(
+        "UPDATE xp_history SET
+    txt = GREATEST(xp_history.txt - GREATEST(xp_user.txt - COALESCE($3::bigint, xp_user.txt), 0), 0),
+    vc = GREATEST(xp_history.vc - GREATEST(xp_user.vc - COALESCE($4, xp_user.vc), '0'::interval), '0'::interval),
+    vc_xp = GREATEST(xp_history.vc_xp - GREATEST(xp_user.vc_xp - COALESCE($4, xp_user.vc_xp), '0'::interval), '0'::interval)
+FROM xp_user WHERE xp_user.guild_id = $1 AND xp_user.user_id = $2
+    AND xp_history.guild_id = $1 AND xp_history.user_id = $2 AND xp_history.day = (now() AT TIME ZONE 'UTC')::date",
+        guild, user_db, amount.txt, vc.clone()
+    ).execute(&mut *transaction).await?;
+    sqlx::query!(
+        "INSERT INTO xp_user (guild_id, user_id, txt, vc, vc_xp) VALUES ($1, $2, COALESCE($3::bigint, 0), COALESCE($4, '0'::interval), COALESCE($4, '0'::interval))
+ON CONFLICT (guild_id, user_id) DO UPDATE SET txt = COALESCE($3, xp_user.txt), vc = COALESCE($4, xp_user.vc), vc_xp = COALESCE($4, xp_user.vc_xp)",
+        guild, user_db, amount.txt, vc.clone()
+    ).execute(&mut *transaction).await?;
+    AuditEntry {
+        guild_id: guild,
+        actor_id: crate::converti(ctx.author().id.get()),
+        action: "set",
+        user_id: Some(user_db),
+        target_user_id: None,
+        txt: amount.txt,
+        vc,
+        reason: reason.as_deref(),
+    }.insert(&mut transaction).await?;
+    transaction.commit().await?;
+    ctx.send(CreateReply::default().content(format!("Set <@{}> to {}.", user.id, amount.describe())).allowed_mentions(serenity::CreateAllowedMentions::new())).await?;
+    check_levels(ctx, &db, &[(guild, user_db)]).await;
+    Ok(())
+}
+
+///Resets the XP of a member or the whole server.
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands(
+        "reset_user",
+        "reset_guild",
+    ),
+    required_permissions = "MANAGE_GUILD",
+    subcommand_required,
+)]
+async fn reset(_: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Sets the XP of the members to zero and removes their pending XP and history.
+/// Resets the whole guild, if `user_id` is `None`.
+async fn reset_xp(transaction: &mut sqlx::PgConnection, guild_id: i64, user_id: Option<i64>) -> Result<Vec<(i64, i64)>, sqlx::Error> {
+    let members = sqlx::query!(
+        "UPDATE xp_user SET txt = 0, vc = '0'::interval, vc_xp = '0'::interval WHERE guild_id = $1 AND ($2::bigint IS NULL OR user_id = $2) RETURNING user_id",
+        guild_id, user_id
+    ).fetch_all(&mut *transaction).await?;
+    sqlx::query!("DELETE FROM xp_txt_tmp WHERE guild_id = $1 AND ($2::bigint IS NULL OR user_id = $2)", guild_id, user_id).execute(&mut *transaction).await?;
+    //Members in a voice channel keep accruing from now on.
+    sqlx::query!("UPDATE xp_vc_tmp SET time = now() WHERE guild_id = $1 AND ($2::bigint IS NULL OR user_id = $2)", guild_id, user_id).execute(&mut *transaction).await?;
+    sqlx::query!("DELETE FROM xp_history WHERE guild_id = $1 AND ($2::bigint IS NULL OR user_id = $2)", guild_id, user_id).execute(&mut *transaction).await?;
+    sqlx::query!("DELETE FROM xp_txt_awards WHERE guild_id = $1 AND ($2::bigint IS NULL OR user_id = $2)", guild_id, user_id).execute(&mut *transaction).await?;
+    Ok(members.into_iter().map(|v| (guild_id, v.user_id)).collect())
+}
+
+///Resets the XP, voice time and history of a member.
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "user",
+    required_permissions = "MANAGE_GUILD",
+)]
+async fn reset_user(ctx: Context<'_>, user: serenity::User, #[max_length = 500] reason: Option<String>) -> Result<(), Error> {
+    let Some(guild) = ctx.guild_id() else {
+        ctx.say("This command can only be used in a server.").await?;
+        return Ok(());
+    };
+    if !confirm(ctx, format!("Do you really want to reset all XP, voice time and history of <@{}>?", user.id)).await? {
+        return Ok(());
+    }
+    let db = crate::get_db().await;
+    let guild = crate::converti(guild.get());
+    let user_db = crate::converti(user.id.get());
+    let mut transaction = db.begin().await?;
+    let members = reset_xp(&mut transaction, guild, Some(user_db)).await?;
+    AuditEntry {
+        guild_id: guild,
+        actor_id: crate::converti(ctx.author().id.get()),
+        action: "reset_user",
+        user_id: Some(user_db),
+        target_user_id: None,
+        txt: None,
+        vc: None,
+        reason: reason.as_deref(),
+    }.insert(&mut transaction).await?;
+    transaction.commit().await?;
+    ctx.send(CreateReply::default().content(format!("Reset the XP of <@{}>.", user.id)).allowed_mentions(serenity::CreateAllowedMentions::new())).await?;
+    check_levels(ctx, &db, &members).await;
+    Ok(())
+}
+
+///Resets the XP, voice time and history of everyone in this server.
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "guild",
+    required_permissions = "MANAGE_GUILD",
+    guild_cooldown = 60,
+)]
+async fn reset_guild(ctx: Context<'_>, #[max_length = 500] reason: Option<String>) -> Result<(), Error> {
+    let Some(guild) = ctx.guild_id() else {
+        ctx.say("This command can only be used in a server.").await?;
+        return Ok(());
+    };
+    if !confirm(ctx, "Do you really want to reset all XP, voice time and history of **everyone** in this server? This can't be undone.".to_string()).await? {
+        return Ok(());
+    }
+    let db = crate::get_db().await;
+    let guild = crate::converti(guild.get());
+    let mut transaction = db.begin().await?;
+    let members = reset_xp(&mut transaction, guild, None).await?;
+    AuditEntry {
+        guild_id: guild,
+        actor_id: crate::converti(ctx.author().id.get()),
+        action: "reset_guild",
+        user_id: None,
+        target_user_id: None,
+        txt: None,
+        vc: None,
+        reason: reason.as_deref(),
+    }.insert(&mut transaction).await?;
+    transaction.commit().await?;
+    ctx.say(format!("Reset the XP of {} members.", members.len())).await?;
+    check_levels(ctx, &db, &members).await;
+    Ok(())
+}
+
+///Moves all XP, voice time and history of a member to another account, e.g. an alt.
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+)]
+async fn transfer(
+    ctx: Context<'_>,
+    #[description = "The member losing their XP"] from: serenity::User,
+    #[description = "The member getting the XP"] to: serenity::User,
+    #[max_length = 500] reason: Option<String>,
+) -> Result<(), Error> {
+    let Some(guild) = ctx.guild_id() else {
+        ctx.say("This command can only be used in a server.").await?;
+        return Ok(());
+    };
+    if from.id == to.id {
+        ctx.send(CreateReply::default().content("Please choose two different members.").ephemeral(true)).await?;
+        return Ok(());
+    }
+    let db = crate::get_db().await;
+    let guild = crate::converti(guild.get());
+    let from_db = crate::converti(from.id.get());
+    let to_db = crate::converti(to.id.get());
+    let mut transaction = db.begin().await?;
+    let Some(source) = sqlx::query!(
+        r#"SELECT txt, vc, vc_xp, EXTRACT(EPOCH FROM vc)::bigint as "vc_seconds!" FROM xp_user WHERE guild_id = $1 AND user_id = $2 FOR UPDATE"#,
+        guild, from_db
+    ).fetch_optional(&mut *transaction).await? else {
+        ctx.send(CreateReply::default().content(format!("<@{}> has no XP.", from.id)).allowed_mentions(serenity::CreateAllowedMentions::new()).ephemeral(true)).await?;
+        return Ok(());
+    };
+    sqlx::query!("UPDATE xp_user SET txt = 0, vc = '0'::interval, vc_xp = '0'::interval WHERE guild_id = $1 AND user_id = $2", guild, from_db).execute(&mut *transaction).await?;
+    sqlx::query!(
+        "INSERT INTO xp_user (guild_id, user_id, txt, vc, vc_xp) VALUES ($1, $2, $3, $4, $5) ON CONFLICT (guild_id, user_id) DO UPDATE SET txt = xp_user.txt + $3, vc = xp_user.vc + $4, vc_xp = xp_user.vc_xp + $5",
+        guild, to_db, source.txt, source.vc.clone(), source.vc_xp
+    ).execute(&mut *transaction).await?;
+    sqlx::query!(
+        "INSERT INTO xp_history (guild_id, user_id, day, txt, vc, vc_xp) SELECT guild_id, $3, day, txt, vc, vc_xp FROM xp_history WHERE guild_id = $1 AND user_id = $2
+ON CONFLICT (guild_id, user_id, day) DO UPDATE SET txt = xp_history.txt + excluded.txt, vc = xp_history.vc + excluded.vc, vc_xp = xp_history.vc_xp + excluded.vc_xp",
+        guild, from_db, to_db
+    ).execute(&mut *transaction).await?;
+    sqlx::query!("DELETE FROM xp_history WHERE guild_id = $1 AND user_id = $2", guild, from_db).execute(&mut *transaction).await?;
+    //Deleting old messages of the previous account now takes the XP from the new one.
+    sqlx::query!("UPDATE xp_txt_awards SET user_id = $3 WHERE guild_id = $1 AND user_id = $2", guild, from_db, to_db).execute(&mut *transaction).await?;
+    AuditEntry {
+        guild_id: guild,
+        actor_id: crate::converti(ctx.author().id.get()),
+        action: "transfer",
+        user_id: Some(from_db),
+        target_user_id: Some(to_db),
+        txt: Some(source.txt),
+        vc: Some(source.vc),
+        reason: reason.as_deref(),
+    }.insert(&mut transaction).await?;
+    transaction.commit().await?;
+    let amount = Amount { txt: Some(source.txt), vc: Some(std::time::Duration::from_secs(u64::try_from(source.vc_seconds).unwrap_or(0))) };
+    ctx.send(CreateReply::default().content(format!("Moved {} from <@{}> to <@{}>.", amount.describe(), from.id, to.id)).allowed_mentions(serenity::CreateAllowedMentions::new())).await?;
+    check_levels(ctx, &db, &[(guild, from_db), (guild, to_db)]).await;
+    Ok(())
+}
+
+///Shows the latest manual XP changes, optionally only those affecting a member.
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+)]
+async fn audit(ctx: Context<'_>, user: Option<serenity::User>) -> Result<(), Error> {
+    let Some(guild) = ctx.guild_id() else {
+        ctx.say("This command can only be used in a server.").await?;
+        return Ok(());
+    };
+    let db = crate::get_db().await;
+    let entries = sqlx::query!(
+        r#"SELECT actor_id, action, user_id, target_user_id, txt, EXTRACT(EPOCH FROM vc)::bigint as vc, reason, time as "time: chrono::DateTime<chrono::Utc>"
+FROM xp_audit WHERE guild_id = $1 AND ($2::bigint IS NULL OR user_id = $2 OR target_user_id = $2) ORDER BY time DESC LIMIT $3"#,
+        crate::converti(guild.get()), user.as_ref().map(|v| crate::converti(v.id.get())), AUDIT_ENTRIES
+    ).fetch_all(&db).await?;
+    if entries.is_empty() {
+        ctx.send(CreateReply::default().content("There are no manual XP changes.").ephemeral(true)).await?;
+        return Ok(());
+    }
+    let mut out = String::new();
+    for entry in entries {
+        let amount = Amount { txt: entry.txt, vc: entry.vc.map(|v| std::time::Duration::from_secs(u64::try_from(v).unwrap_or(0))) };
+        let member = entry.user_id.map(|v| format!("<@{}>", crate::convertu(v))).unwrap_or_default();
+        let action = match entry.action.as_str() {
+            "give" => format!("gave {} to {member}", amount.describe()),
+            "take" => format!("took {} from {member}", amount.describe()),
+            "set" => format!("set {member} to {}", amount.describe()),
+            "reset_user" => format!("reset {member}"),
+            "reset_guild" => "reset the whole server".to_string(),
+            "transfer" => format!("moved {} from {member} to <@{}>", amount.describe(), entry.target_user_id.map_or(0, crate::convertu)),
+            "import" => format!("imported {}", amount.describe()),
+            other => other.to_string(),
+        };
+        let actor = entry.actor_id.map_or_else(|| "The command line".to_string(), |v| format!("<@{}>", crate::convertu(v)));
+        let mut line = format!("- <t:{}:f> {actor} {action}", entry.time.timestamp());
+        if let Some(reason) = entry.reason {
+            match reason.char_indices().nth(AUDIT_REASON_LENGTH) {
+                Some((end, _)) => line.push_str(format!(": {}…", &reason[..end]).as_str()),
+                None => line.push_str(format!(": {reason}").as_str()),
+            }
+        }
+        line.push('\n');
+        //Older entries are left out, if they don't fit anymore.
+        if out.len() + line.len() > MAX_MESSAGE_LENGTH {
+            break;
+        }
+        out.push_str(line.as_str());
+    }
+    ctx.send(CreateReply::default().content(out).allowed_mentions(serenity::CreateAllowedMentions::new())).await?;
+    Ok(())
+}
+
+///Imports XP from the export of another leveling bot (JSON or CSV).
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    guild_cooldown = 60,
+)]
+async fn import(
+    ctx: Context<'_>,
+    #[description = "The exported leaderboard, e.g. MEE6's leaderboard JSON"] file: serenity::Attachment,
+    source: ImportSource,
+    #[description = "How the XP is converted. Defaults to text XP."] conversion: Option<Conversion>,
+    #[description = "Multiplies the XP for text and voice conversions. Defaults to 1."] #[min = 0] factor: Option<f64>,
+    #[description = "Replace the current XP of the imported members instead of adding to it"] replace: Option<bool>,
+) -> Result<(), Error> {
+    let Some(guild) = ctx.guild_id() else {
+        ctx.say("This command can only be used in a server.").await?;
+        return Ok(());
+    };
+    let options = ImportOptions {
+        source,
+        conversion: conversion.unwrap_or_default(),
+        factor: factor.unwrap_or(1.),
+        replace: replace.unwrap_or(false),
+    };
+    if !options.factor.is_finite() || options.factor < 0. {
+        ctx.send(CreateReply::default().content("The factor must be a positive number.").ephemeral(true)).await?;
+        return Ok(());
+    }
+    if file.size > MAX_IMPORT_SIZE {
+        ctx.send(CreateReply::default().content("The file is too large.").ephemeral(true)).await?;
+        return Ok(());
+    }
+    if options.replace && !confirm(ctx, "Do you really want to replace the XP of every member in the file? This can't be undone.".to_string()).await? {
+        return Ok(());
+    }
+    ctx.defer().await?;
+    let data = file.download().await?;
+    let db = crate::get_db().await;
+    let guild = crate::converti(guild.get());
+    let summary = match crate::client::portability::import(&db, guild, Some(crate::converti(ctx.author().id.get())), &data, &options).await {
+        Ok(v) => v,
+        Err(err) => {
+            ctx.say(format!("Nothing was imported: {err}")).await?;
+            return Ok(());
+        }
+    };
+    let amount = Amount { txt: Some(summary.txt), vc: Some(std::time::Duration::from_secs(u64::try_from(summary.vc_seconds).unwrap_or(0))) };
+    ctx.say(format!("Imported {} for {} members. Their levels and level rewards update silently, when they next get XP or after `/settings level_rewards resync`.", amount.describe(), summary.members)).await?;
+    Ok(())
+}
+
+///Exports the XP and names of all members with XP in this server.
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    guild_cooldown = 60,
+)]
+async fn export(ctx: Context<'_>, #[description = "Defaults to CSV"] format: Option<ExportFormat>) -> Result<(), Error> {
+    let Some(guild) = ctx.guild_id() else {
+        ctx.say("This command can only be used in a server.").await?;
+        return Ok(());
+    };
+    let format = format.unwrap_or_default();
+    ctx.defer_ephemeral().await?;
+    let db = crate::get_db().await;
+    let data = match crate::client::portability::export(&db, crate::converti(guild.get()), format).await {
+        Ok(v) => v,
+        Err(err) => {
+            ctx.say(format!("Failed to export the XP: {err}")).await?;
+            return Ok(());
+        }
+    };
+    ctx.send(
+        CreateReply::default()
+            .content("Here is the XP of this server.")
+            .attachment(serenity::CreateAttachment::bytes(data, format!("xp-{guild}.{}", format.extension())))
+            .ephemeral(true)
+    ).await?;
+    Ok(())
+}
@@ -1,5 +1,6 @@
 use serde_derive::{Serialize, Deserialize};
-use serenity::all::Member;
+use std::sync::LazyLock;
+use serenity::all::{Member, RoleId};
 use serenity::client::Context;
 
 #[derive(Debug, Default, Serialize, Deserialize)]
@@ -13,6 +14,32 @@ pub struct BindRolesOrs {
     pub negated: Vec<i64>,
 }
 
+impl BindRoles {
+    /// Parses a formula in disjunctive normal form (! = NOT, & = AND, | = OR).
+    /// Roles can be given as ids or mentions.
+    pub fn parse(bind_roles: &str) -> Result<Self, std::num::ParseIntError> {
+        static REMOVE_WHITESPACE:LazyLock<regex::Regex> =  LazyLock::new(||regex::Regex::new(r#"[\s<@&>]*"#).expect("Invalid Regex"));
+        let bind_roles = REMOVE_WHITESPACE.replace_all(bind_roles, "");
+        let mut bind_roles_parsed = BindRoles::default();
+        for and_items in bind_roles.split("|") {
+            let mut items = BindRolesOrs::default();
+            for or_item in and_items.split("&") {
+                let (inverted, role) = or_item.strip_prefix("!").map_or_else(||(false, or_item), |v|(true, v));
+                log::debug!("Parsing role: {role}");
+                let role:RoleId = role.parse()?;
+                let role = role.get().cast_signed();
+                if inverted {
+                    items.negated.push(role);
+                } else {
+                    items.normal.push(role);
+                }
+            }
+            bind_roles_parsed.bind.push(items);
+        }
+        Ok(bind_roles_parsed)
+    }
+}
+
 pub async fn handle_role_change(ctx: &Context, member: &Member) {
     let roles = member.roles.iter().map(|v|v.get().cast_signed()).collect::<Vec<_>>();
     let db = crate::get_db().await;
@@ -38,4 +65,77 @@ pub async fn handle_role_change(ctx: &Context, member: &Member) {
         }
     }
     
-}
\ No newline at end of file
+}
+/// Grants the level rewards a member has earned at `level` and removes the ones they haven't (anymore).
+/// Granted roles still have to satisfy `role_limit_predicate`, so a level reward can't bypass a role limit.
+pub async fn handle_level_rewards(cache_http: impl serenity::http::CacheHttp, guild_id: serenity::all::GuildId, user_id: serenity::all::UserId, level: i64) {
+    let db = crate::get_db().await;
+    let guild = guild_id.get().cast_signed();
+    let rewards = match sqlx::query!(
+        "SELECT role_id, level, replace_previous FROM xp_role_rewards WHERE guild_id = $1 ORDER BY level, role_id",
+        guild
+    ).fetch_all(&db).await {
+        Ok(v) => v,
+        Err(err) => {
+            log::error!("Could not get level rewards for guild {guild_id}: {err}");
+            return;
+        }
+    };
+    if rewards.is_empty() {
+        return;
+    }
+    let mut earned = Vec::new();
+    for reward in rewards.iter().filter(|v| v.level <= level) {
+        if reward.replace_previous {
+            earned.clear();
+        }
+        earned.push(reward.role_id);
+    }
+
+    let member = match guild_id.member(&cache_http, user_id).await {
+        Ok(v) => v,
+        Err(err) => {
+            log::info!("Failed to get member {user_id} of guild {guild_id} to handle level rewards: {err}");
+            return;
+        }
+    };
+    let current = member.roles.iter().map(|v|v.get().cast_signed()).collect::<Vec<_>>();
+    let remove = rewards.iter()
+        .map(|v| v.role_id)
+        .filter(|role| current.contains(role) && !earned.contains(role))
+        .collect::<Vec<_>>();
+    let mut add = earned.iter()
+        .copied()
+        .filter(|role| !current.contains(role))
+        .collect::<Vec<_>>();
+    if !add.is_empty() {
+        let roles = current.iter()
+            .filter(|role| !remove.contains(role))
+            .chain(add.iter())
+            .copied()
+            .collect::<Vec<_>>();
+        match sqlx::query!(
+            "SELECT role_id FROM role_limiter WHERE guild_id = $1 AND role_id = ANY($2) AND NOT role_limit_predicate($3, bind_roles)",
+            guild, add.as_slice(), roles.as_slice()
+        ).fetch_all(&db).await {
+            Ok(v) => add.retain(|role| !v.iter().any(|limited| limited.role_id == *role)),
+            Err(err) => {
+                log::error!("Could not check the role limits of level rewards for guild {guild_id} and user {user_id}: {err}");
+                return;
+            }
+        }
+    }
+
+    if !remove.is_empty() {
+        let remove = remove.iter().map(|v|RoleId::new(v.cast_unsigned())).collect::<Vec<_>>();
+        if let Err(err) = member.remove_roles(cache_http.http(), remove.as_slice()).await {
+            log::error!("Failed to remove level rewards from {user_id} in guild {guild_id}: {err}");
+        }
+    }
+    if !add.is_empty() {
+        let add = add.iter().map(|v|RoleId::new(v.cast_unsigned())).collect::<Vec<_>>();
+        if let Err(err) = member.add_roles(cache_http.http(), add.as_slice()).await {
+            log::error!("Failed to add level rewards to {user_id} in guild {guild_id}: {err}");
+        }
+    }
+}
@@ -7,7 +7,7 @@ impl super::Handler {
         match sqlx::query!(r#"SELECT $2 = ANY(SELECT creator_channel FROM temp_channels WHERE guild_id = $1) as "is_creator_channel!""#, crate::converti(guild_id.get()), crate::converti(channel_id.get())).fetch_one(&self.pool).await {
             Ok(v) => match v.is_creator_channel {
                 true => {
-                    self.create_channel(&ctx, user_id, guild_id).await;
+                    self.create_channel(&ctx, user_id, guild_id, channel_id).await;
                 }
                 false => {
                     match sqlx::query!("UPDATE temp_channels_created SET mark_delete = NULL WHERE channel_id = $1 AND guild_id = $2", crate::converti(channel_id.get()), crate::converti(guild_id.get())).execute(&self.pool).await {
@@ -32,7 +32,8 @@ MERGE INTO temp_channels_created_users USING
     ) AS input
     ON temp_channels_created_users.guild_id = input.guild_id AND temp_channels_created_users.user_id = input.user_id
 WHEN MATCHED AND input.is_ignored_channel THEN DELETE
-WHEN MATCHED AND input.is_created_channel THEN UPDATE SET channel_id = input.channel_id
+WHEN MATCHED AND input.is_created_channel THEN UPDATE SET channel_id = input.channel_id,
+    joined_at = CASE WHEN temp_channels_created_users.channel_id = input.channel_id THEN temp_channels_created_users.joined_at ELSE now() END
 WHEN MATCHED THEN DELETE
 WHEN NOT MATCHED AND input.is_ignored_channel THEN DO NOTHING
 WHEN NOT MATCHED AND input.is_created_channel THEN INSERT (guild_id, channel_id, user_id) VALUES (input.guild_id, input.channel_id, input.user_id)
@@ -48,6 +49,7 @@ WHEN NOT MATCHED THEN DO NOTHING
                             tracing::error!("Error updating temp_channels_created_users: {err}");
                         }
                     }
+                    self.transfer_left_channels(&ctx, guild_id).await;
                     self.check_delete_channels(ctx).await
                 },
             }
@@ -117,6 +119,7 @@ RETURNING mark_delete = now() as "mark_delete!", now() as "deleted_at!"
             },
         };
 
+        //Use the settings of the creator, that created the channel. If that creator was removed, fall back to any other creator of the guild.
         let info = match sqlx::query!(r#"
 SELECT
   guild_id,
@@ -126,6 +129,8 @@ SELECT
   delete_delay
 from temp_channels
 WHERE guild_id = $1
+ORDER BY creator_channel IS NOT DISTINCT FROM (SELECT creator_channel FROM temp_channels_created WHERE temp_channels_created.guild_id = $1 AND temp_channels_created.channel_id = $2) DESC, creator_channel
+LIMIT 1
         "#,
             crate::converti(guild_id.get()), crate::converti(channel.get())).fetch_one(&self.pool).await
         {
@@ -222,10 +227,60 @@ SELECT COUNT(*) FROM temp_channels_created_users WHERE temp_channels_created_use
             self.check_delete_channel(&ctx, serenity::ChannelId::new(crate::convertu(channel.channel_id)), serenity::GuildId::new(crate::convertu(channel.guild_id))).await;
         }
     }
-    async fn create_channel(&self, ctx: &poise::serenity_prelude::Context, user_id: serenity::UserId, guild_id: serenity::GuildId) {
+    /// Passes the ownership of created channels, whose owner left, to the member who has been in them the longest.
+    pub(crate) async fn transfer_left_channels(&self, http: impl AsRef<serenity::Http>, guild_id: serenity::GuildId) {
+        let transfers = match sqlx::query!(r#"
+WITH left_channels AS (
+    SELECT guild_id, channel_id, owner_id FROM temp_channels_created
+    WHERE guild_id = $1 AND owner_id IS NOT NULL AND NOT EXISTS(
+        SELECT 1 FROM temp_channels_created_users
+        WHERE temp_channels_created_users.guild_id = temp_channels_created.guild_id AND temp_channels_created_users.channel_id = temp_channels_created.channel_id AND temp_channels_created_users.user_id = temp_channels_created.owner_id
+    )
+), longest_present AS (
+    SELECT DISTINCT ON (channel_id) guild_id, channel_id, user_id FROM temp_channels_created_users
+    WHERE guild_id = $1
+    ORDER BY channel_id, joined_at, user_id
+)
+UPDATE temp_channels_created SET owner_id = longest_present.user_id
+FROM left_channels JOIN longest_present USING (guild_id, channel_id)
+WHERE temp_channels_created.guild_id = left_channels.guild_id AND temp_channels_created.channel_id = left_channels.channel_id AND temp_channels_created.owner_id = left_channels.owner_id
+RETURNING temp_channels_created.channel_id, left_channels.owner_id as "old_owner!", longest_present.user_id as new_owner
+        "#, crate::converti(guild_id.get())).fetch_all(&self.pool).await {
+            Ok(v) => v,
+            Err(err) => {
+                tracing::error!("Error transferring the ownership of created channels: {err}");
+                return;
+            }
+        };
+        for transfer in transfers {
+            let channel = serenity::ChannelId::new(crate::convertu(transfer.channel_id));
+            let old_owner = serenity::UserId::new(crate::convertu(transfer.old_owner));
+            let new_owner = serenity::UserId::new(crate::convertu(transfer.new_owner));
+            tracing::info!("Owner {old_owner} left channel {channel}. Transferring it to {new_owner}");
+            if let Err(err) = move_owner_permissions(&http, channel, Some(old_owner), new_owner).await {
+                tracing::error!("Error moving the owner permissions of channel {channel} from {old_owner} to {new_owner}: {err}");
+                self.log_error(http.as_ref(), channel, format!("Failed to give <@{new_owner}> the permissions of the channel owner: {err}")).await;
+            }
+        }
+    }
+
+    async fn create_channel(&self, ctx: &poise::serenity_prelude::Context, user_id: serenity::UserId, guild_id: serenity::GuildId, creator_channel: serenity::ChannelId) {
         let res = match sqlx::query!(
-            r#"SELECT create_category FROM temp_channels WHERE guild_id = $1"#,
-            crate::converti(guild_id.get())
+            r#"
+SELECT
+  create_category,
+  name_template,
+  user_limit,
+  bitrate,
+  rtc_region,
+  video_quality,
+  (SELECT MIN(n) FROM generate_series(1, (SELECT COUNT(*) FROM temp_channels_created WHERE guild_id = $1 AND creator_channel = $2)::integer + 1) as n
+    WHERE n NOT IN (SELECT number FROM temp_channels_created WHERE guild_id = $1 AND creator_channel = $2 AND number IS NOT NULL)
+  ) as "number!"
+FROM temp_channels
+WHERE guild_id = $1 AND creator_channel = $2
+            "#,
+            crate::converti(guild_id.get()), crate::converti(creator_channel.get())
         ).fetch_one(&self.pool).await {
             Ok(v) => v,
             Err(err) => {
@@ -250,23 +305,82 @@ SELECT COUNT(*) FROM temp_channels_created_users WHERE temp_channels_created_use
             })().await
         };
 
+        //What the member did to their last channel with /vc
+        let preferences = match sqlx::query!(
+            "SELECT name, user_limit, locked, hidden, permitted, banned FROM temp_channels_preferences WHERE guild_id = $1 AND user_id = $2 AND remember",
+            crate::converti(guild_id.get()), crate::converti(user_id.get())
+        ).fetch_optional(&self.pool).await {
+            Ok(v) => v,
+            Err(err) => {
+                tracing::error!("Error getting the channel preferences of {user_id}: {err}");
+                None
+            }
+        };
+        let mut permissions = vec![
+            serenity::model::channel::PermissionOverwrite {
+                allow: OWNER_PERMISSIONS,
+                deny: serenity::all::Permissions::empty(),
+                kind: serenity::model::channel::PermissionOverwriteType::Member(user_id),
+            },
+        ];
+        if let Some(preferences) = &preferences {
+            let mut everyone_deny = serenity::all::Permissions::empty();
+            everyone_deny.set(serenity::all::Permissions::CONNECT, preferences.locked);
+            everyone_deny.set(serenity::all::Permissions::VIEW_CHANNEL, preferences.hidden);
+            if !everyone_deny.is_empty() {
+                permissions.push(serenity::model::channel::PermissionOverwrite {
+                    allow: serenity::all::Permissions::empty(),
+                    deny: everyone_deny,
+                    kind: serenity::model::channel::PermissionOverwriteType::Role(serenity::RoleId::new(guild_id.get())),
+                });
+            }
+            //Bans take precedence, so they don't get cut off by too many permitted members. The newest are kept first.
+            let members = preferences.banned.iter().rev().map(|v| (v, false))
+                .chain(preferences.permitted.iter().rev().map(|v| (v, true)))
+                .map(|(user, permitted)| (serenity::UserId::new(crate::convertu(*user)), permitted))
+                .filter(|(user, _)| *user != user_id)
+                .take(MAX_REMEMBERED_MEMBERS);
+            for (user, permitted) in members {
+                let (allow, deny) = match permitted {
+                    true => (serenity::all::Permissions::VIEW_CHANNEL | serenity::all::Permissions::CONNECT, serenity::all::Permissions::empty()),
+                    false => (serenity::all::Permissions::empty(), serenity::all::Permissions::VIEW_CHANNEL | serenity::all::Permissions::CONNECT),
+                };
+                permissions.push(serenity::model::channel::PermissionOverwrite {
+                    allow,
+                    deny,
+                    kind: serenity::model::channel::PermissionOverwriteType::Member(user),
+                });
+            }
+        }
         let mut new_channel = serenity::CreateChannel::new("New Channel")
             .kind(serenity::model::channel::ChannelType::Voice)
-            .permissions([
-                serenity::model::channel::PermissionOverwrite {
-                    allow:
-                    serenity::all::Permissions::MANAGE_CHANNELS |
-                        serenity::all::Permissions::MANAGE_ROLES |
-                        serenity::all::Permissions::MOVE_MEMBERS |
-                        serenity::all::Permissions::VIEW_CHANNEL |
-                        serenity::all::Permissions::CONNECT,
-                    deny: serenity::all::Permissions::empty(),
-                    kind: serenity::model::channel::PermissionOverwriteType::Member(user_id),
-                },
-            ]);
+            .permissions(permissions);
         if let Some(position) = position {
             new_channel = new_channel.position(position);
         }
+        //Presences and the boost tier are only available from the cache.
+        let (game, max_bitrate) = match ctx.cache.guild(guild_id) {
+            Some(guild) => (
+                guild.presences.get(&user_id)
+                    .and_then(|presence| presence.activities.iter().find(|v| v.kind == serenity::ActivityType::Playing))
+                    .map(|v| v.name.clone()),
+                Some(max_bitrate(guild.premium_tier)),
+            ),
+            None => (None, None),
+        };
+        let user_limit = preferences.as_ref().and_then(|v| v.user_limit).or(res.user_limit);
+        if let Some(user_limit) = user_limit.and_then(|v| u32::try_from(v).ok()) {
+            new_channel = new_channel.user_limit(user_limit);
+        }
+        if let Some(bitrate) = res.bitrate.and_then(|v| u32::try_from(v).ok()) {
+            new_channel = new_channel.bitrate(max_bitrate.map_or(bitrate, |max| bitrate.min(max)));
+        }
+        if let Some(rtc_region) = res.rtc_region {
+            new_channel = new_channel.rtc_region(rtc_region);
+        }
+        if let Some(video_quality) = res.video_quality.and_then(|v| u8::try_from(v).ok()) {
+            new_channel = new_channel.video_quality_mode(serenity::VideoQualityMode::from(video_quality));
+        }
         if let Some(create_category) = res.create_category {
             match NonZeroU64::new(crate::convertu(create_category)) {
                 None => {},
@@ -276,11 +390,14 @@ SELECT COUNT(*) FROM temp_channels_created_users WHERE temp_channels_created_use
             }
         }
         let mut error = None;
-        match user_id.to_user(&ctx).await{
-            Ok(user) => {
-                new_channel = new_channel.name(format!("{}'s Channel", user.global_name.unwrap_or(user.name)));
+        match (user_id.to_user(&ctx).await, preferences.and_then(|v| v.name)) {
+            (_, Some(name)) => {
+                new_channel = new_channel.name(name);
             },
-            Err(v) => {
+            (Ok(user), None) => {
+                new_channel = new_channel.name(channel_name(res.name_template.as_deref(), &user, res.number, game.as_deref()));
+            },
+            (Err(v), None) => {
                 error = Some(v);
             }
         }
@@ -289,7 +406,7 @@ SELECT COUNT(*) FROM temp_channels_created_users WHERE temp_channels_created_use
                 if let Some(error) = error {
                     self.log_error(&ctx, v.id, format!("There was an error getting the Creator's User: {error}")).await;
                 }
-               self.channel_create(&v, true).await;
+               self.channel_create(&v, Some(&CreatedChannel { creator_channel, number: res.number, owner: user_id })).await;
                 tracing::info!("Created channel {v}");
                 match guild_id.move_member(&ctx, user_id, v.id).await {
                     Ok(_) => {},
@@ -315,4 +432,78 @@ SELECT COUNT(*) FROM temp_channels_created_users WHERE temp_channels_created_use
             }
         }
     }
-}
\ No newline at end of file
+}
+
+/// How a channel was created by a creator channel.
+pub(crate) struct CreatedChannel {
+    pub creator_channel: serenity::ChannelId,
+    /// Number for the `{count}` placeholder of the name template
+    pub number: i32,
+    pub owner: serenity::UserId,
+}
+
+/// Moves the permission overwrite of the old owner of a channel to the new owner.
+/// Gives the new owner the default owner permissions, if the old owner had no overwrite.
+pub(crate) async fn move_owner_permissions(http: impl AsRef<serenity::Http>, channel: serenity::ChannelId, old_owner: Option<serenity::UserId>, new_owner: serenity::UserId) -> Result<(), serenity::Error> {
+    let Some(channel) = channel.to_channel(http.as_ref()).await?.guild() else {
+        return Ok(());
+    };
+    let old_overwrite = old_owner.and_then(|old_owner| channel.permission_overwrites.iter()
+        .find(|v| v.kind == serenity::PermissionOverwriteType::Member(old_owner)));
+    let new_overwrite = channel.permission_overwrites.iter()
+        .find(|v| v.kind == serenity::PermissionOverwriteType::Member(new_owner));
+    let allow = old_overwrite.map_or(OWNER_PERMISSIONS, |v| v.allow) | new_overwrite.map_or(serenity::Permissions::empty(), |v| v.allow);
+    channel.create_permission(http.as_ref(), serenity::PermissionOverwrite {
+        allow,
+        deny: old_overwrite.map_or(serenity::Permissions::empty(), |v| v.deny) - allow,
+        kind: serenity::PermissionOverwriteType::Member(new_owner),
+    }).await?;
+    if let Some(old_overwrite) = old_overwrite {
+        channel.delete_permission(http.as_ref(), old_overwrite.kind).await?;
+    }
+    Ok(())
+}
+
+/// Permissions the owner of a created channel gets on it.
+pub(crate) const OWNER_PERMISSIONS: serenity::all::Permissions = serenity::all::Permissions::MANAGE_CHANNELS
+    .union(serenity::all::Permissions::MANAGE_ROLES)
+    .union(serenity::all::Permissions::MOVE_MEMBERS)
+    .union(serenity::all::Permissions::VIEW_CHANNEL)
+    .union(serenity::all::Permissions::CONNECT);
+
+/// Remembered permitted or banned members, that get applied to a new channel at most.
+/// Discord limits the amount of permission overwrites of a channel.
+pub(crate) const MAX_REMEMBERED_MEMBERS: usize = 50;
+
+pub(crate) const DEFAULT_NAME_TEMPLATE: &str = "{name}'s Channel";
+/// Discord doesn't allow longer channel names.
+const MAX_CHANNEL_NAME_CHARS: usize = 100;
+
+/// Fills in the placeholders of a name template:
+/// `{name}` is the display name of the creator, `{username}` their username, `{count}` the number of the channel
+/// and `{game}` the game the creator is currently playing, which is empty if they don't play anything.
+fn channel_name(template: Option<&str>, user: &serenity::User, number: i32, game: Option<&str>) -> String {
+    let fill = |template: &str| template
+        .replace("{name}", user.global_name.as_deref().unwrap_or(&user.name))
+        .replace("{username}", &user.name)
+        .replace("{count}", &number.to_string())
+        .replace("{game}", game.unwrap_or_default())
+        .trim()
+        .chars()
+        .take(MAX_CHANNEL_NAME_CHARS)
+        .collect::<String>();
+    match fill(template.unwrap_or(DEFAULT_NAME_TEMPLATE)) {
+        name if name.is_empty() => fill(DEFAULT_NAME_TEMPLATE),
+        name => name,
+    }
+}
+
+/// The highest bitrate a voice channel may have at a boost tier.
+fn max_bitrate(tier: serenity::PremiumTier) -> u32 {
+    match tier {
+        serenity::PremiumTier::Tier1 => 128_000,
+        serenity::PremiumTier::Tier2 => 256_000,
+        serenity::PremiumTier::Tier3 => 384_000,
+        _ => 96_000,
+    }
+}
@@ -1,3 +1,14 @@
+mod level;
+mod multiplier;
+pub(crate) mod portability;
+mod revoke;
+mod voice;
+
+pub(crate) use level::{LevelFormula, LevelSettings, LevelUpDestination};
+pub(in crate::client) use level::check_level_ups;
+pub(crate) use multiplier::MultiplierRule;
+pub(crate) use voice::VoiceRules;
+pub(in crate::client) use voice::VoiceMember;
 use poise::{serenity_prelude as serenity};
 use serde_derive::{Deserialize, Serialize};
 
@@ -9,13 +20,14 @@ pub struct TextXpInfo {
 }
 
 impl super::Handler {
-    pub(super) async fn apply_previous_message_xp(&self, user_id: Option<serenity::UserId>, guild_id: Option<serenity::GuildId>) {
+    pub(super) async fn apply_previous_message_xp(&self, http: &serenity::Http, user_id: Option<serenity::UserId>, guild_id: Option<serenity::GuildId>) {
         let result = match sqlx::query!(
 r#"SELECT
     guild_id as "guild_id!",
     user_id as "user_id!",
     total_xp as "total_xp!",
     applyable_xp as "applyable_xp!",
+    xp_change as "xp_change!",
     xp_punish as "xp_punish!",
     duration as "duration!"
 FROM apply_previous_message_xp($1, $2)"#,
@@ -29,16 +41,45 @@ user_id.map(|v|crate::converti(v.get()))
                 return;
             }
         };
+        let mut changed = Vec::new();
+        let mut history = Vec::new();
         for result in result {
+            if result.xp_change != 0 {
+                changed.push((result.guild_id, result.user_id));
+                history.push((result.guild_id, result.user_id, result.xp_change));
+            }
             if result.xp_punish {
+                self.penalize_txt_awards(result.guild_id, result.user_id, result.total_xp, result.xp_change, &result.duration).await;
                 tracing::warn!("User {} has triggered the xp spam limit. Queued are {} xp from {:?} ago. {} xp are applyable. {} xp are removed as spam.", result.user_id, result.total_xp, result.duration, result.applyable_xp, result.total_xp - result.applyable_xp);
             }
             if result.applyable_xp != result.total_xp {
                 tracing::info!("User {} has gotten an unusual amount of xp. Queued outstanding xp for application. Queued are {} xp. {} xp were already applied", result.user_id, result.total_xp, result.applyable_xp);
             }
         }
+        self.add_txt_history(&history).await;
+        check_level_ups(&self.pool, http, &changed).await;
     }
-    pub(in super) async fn message_xp(&self, message: serenity::Message) {
+    /// Adds applied text XP to today's history of the members.
+    async fn add_txt_history(&self, history: &[(i64, i64, i64)]) {
+        if history.is_empty() {
+            return;
+        }
+        let guilds = history.iter().map(|(guild, _, _)| *guild).collect::<Vec<_>>();
+        let users = history.iter().map(|(_, user, _)| *user).collect::<Vec<_>>();
+        let xp = history.iter().map(|(_, _, xp)| *xp).collect::<Vec<_>>();
+        match sqlx::query!(
+            "INSERT INTO xp_history (guild_id, user_id, day, txt)
+SELECT guild_id, user_id, (now() AT TIME ZONE 'UTC')::date, txt FROM unnest($1::bigint[], $2::bigint[], $3::bigint[]) as input(guild_id, user_id, txt)
+ON CONFLICT (guild_id, user_id, day) DO UPDATE SET txt = xp_history.txt + excluded.txt",
+            guilds.as_slice(), users.as_slice(), xp.as_slice()
+        ).execute(&self.pool).await {
+            Ok(_) => {},
+            Err(err) => {
+                tracing::error!("Error adding text xp history: {err}");
+            }
+        }
+    }
+    pub(in super) async fn message_xp(&self, http: &serenity::Http, message: serenity::Message) {
         let guild_id = match message.guild_id {
             Some(v) => v,
             None => return,
@@ -49,12 +90,15 @@ user_id.map(|v|crate::converti(v.get()))
         }
         //Apply message xp
         {
-            let xp = calculate_message_text_xp(BASE_TEXT_XP, &message);
-            self.apply_previous_message_xp(Some(message.author.id), Some(guild_id)).await;
+            let roles = message.member.as_ref().map(|v| v.roles.as_slice()).unwrap_or_default();
+            let multiplier = self.xp_multiplier(guild_id, message.channel_id, roles).await;
+            let xp = multiplier::multiply(calculate_message_text_xp(BASE_TEXT_XP, &message), multiplier);
+            self.apply_previous_message_xp(http, Some(message.author.id), Some(guild_id)).await;
             self.add_tmp_txt_xp(message.author.id, guild_id, xp).await;
+            self.record_message_xp(guild_id, message.id, message.author.id, xp).await;
         };
     }
-    pub(crate) async fn message_xp_react(&self, reaction: &serenity::Reaction) {
+    pub(crate) async fn message_xp_react(&self, ctx: &serenity::Context, reaction: &serenity::Reaction) {
         let guild_id = match reaction.guild_id {
             Some(v) => v,
             None => return,
@@ -65,11 +109,25 @@ user_id.map(|v|crate::converti(v.get()))
         if reaction.burst { xp*=2; }
         {
             if let Some(member) = &reaction.member {
-                self.apply_previous_message_xp(Some(member.user.id), Some(guild_id)).await;
+                let multiplier = self.xp_multiplier(guild_id, reaction.channel_id, &member.roles).await;
+                self.apply_previous_message_xp(&ctx.http, Some(member.user.id), Some(guild_id)).await;
+                let xp = multiplier::multiply(xp, multiplier);
                 self.add_tmp_txt_xp(member.user.id, guild_id, xp).await;
+                self.record_reaction_xp(reaction, guild_id, member.user.id, xp).await;
             }
             if let Some(member) =  reaction.message_author_id {
+                //The reaction only contains the reacting member, so the roles of the author come from the cache (or Discord).
+                let roles = match guild_id.member(ctx, member).await {
+                    Ok(v) => v.roles,
+                    Err(err) => {
+                        tracing::info!("Failed to get the roles of {member} for their xp multiplier: {err}");
+                        Vec::new()
+                    }
+                };
+                let multiplier = self.xp_multiplier(guild_id, reaction.channel_id, &roles).await;
+                let xp = multiplier::multiply(xp, multiplier);
                 self.add_tmp_txt_xp(member, guild_id, xp).await;
+                self.record_reaction_xp(reaction, guild_id, member, xp).await;
             }
         }
     }
@@ -112,4 +170,4 @@ fn calculate_message_text_xp(base_xp: i64, message: &serenity::Message) -> i64 {
         message.embeds.len() as f64 * 0.1;
 
     xp as i64
-}
\ No newline at end of file
+}
@@ -0,0 +1,282 @@
+use poise::serenity_prelude as serenity;
+
+const DEFAULT_LEVEL_UP_MESSAGE: &str = "{user} reached level {level}!";
+
+#[derive(poise::ChoiceParameter, Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum LevelFormula {
+    /// Every level needs the same amount of XP.
+    Linear,
+    /// Reaching level `l` needs `factor * l²` XP in total.
+    Quadratic,
+    /// Every level needs `5l² + 50l + 100` more XP, like MEE6.
+    #[default]
+    #[name = "MEE6"]
+    Mee6,
+    /// The total XP per level is configured per guild.
+    Custom,
+}
+impl LevelFormula {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Linear => "linear",
+            Self::Quadratic => "quadratic",
+            Self::Mee6 => "mee6",
+            Self::Custom => "custom",
+        }
+    }
+    fn from_db(value: &str) -> Self {
+        match value {
+            "linear" => Self::Linear,
+            "quadratic" => Self::Quadratic,
+            "mee6" => Self::Mee6,
+            "custom" => Self::Custom,
+            _ => {
+                tracing::warn!("Unknown level formula {value}, using the MEE6 formula instead");
+                Self::Mee6
+            }
+        }
+    }
+}
+
+/// The XP needed for level `level + 1` after reaching `level` with the MEE6 formula.
+fn mee6_level_xp(level: i64) -> i64 {
+    level.saturating_mul(level).saturating_mul(5).saturating_add(level.saturating_mul(50)).saturating_add(100)
+}
+
+/// Where a member is on their way to the next level.
+#[derive(Debug, Default, Copy, Clone)]
+#[non_exhaustive]
+pub(crate) struct LevelProgress {
+    pub level: i64,
+    /// XP gained since reaching `level`
+    pub xp: i64,
+    /// XP needed to get from `level` to the next level. `None` if there is no next level.
+    pub next_level_xp: Option<i64>,
+}
+
+/// Where level-ups get announced.
+#[derive(Debug, Clone)]
+pub(crate) enum LevelUpDestination {
+    Channel(serenity::ChannelId),
+    Dm,
+}
+
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub(crate) struct LevelSettings {
+    pub formula: LevelFormula,
+    pub factor: i64,
+    pub txt_weight: f64,
+    pub vc_weight: f64,
+    /// Total XP needed for level 1, 2, ... for [`LevelFormula::Custom`]
+    pub table: Vec<i64>,
+    pub destination: Option<LevelUpDestination>,
+    pub message: Option<String>,
+}
+impl Default for LevelSettings {
+    fn default() -> Self {
+        Self {
+            formula: LevelFormula::default(),
+            factor: 100,
+            txt_weight: 1.,
+            vc_weight: 1.,
+            table: Vec::new(),
+            destination: None,
+            message: None,
+        }
+    }
+}
+
+impl LevelSettings {
+    pub(crate) async fn get(pool: &sqlx::PgPool, guild_id: i64) -> Result<Self, sqlx::Error> {
+        let Some(settings) = sqlx::query!(
+            "SELECT level_formula, level_factor, txt_weight, vc_weight, level_up_channel, level_up_dm, level_up_message FROM xp WHERE guild_id = $1",
+            guild_id
+        ).fetch_optional(pool).await? else {
+            return Ok(Self::default());
+        };
+        let formula = LevelFormula::from_db(&settings.level_formula);
+        let table = if formula == LevelFormula::Custom {
+            sqlx::query!("SELECT xp FROM xp_level_table WHERE guild_id = $1 ORDER BY level", guild_id)
+                .fetch_all(pool).await?
+                .into_iter()
+                .map(|v| v.xp)
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let destination = match (settings.level_up_channel, settings.level_up_dm) {
+            (_, true) => Some(LevelUpDestination::Dm),
+            (Some(channel), false) => Some(LevelUpDestination::Channel(serenity::ChannelId::new(crate::convertu(channel)))),
+            (None, false) => None,
+        };
+        Ok(Self {
+            formula,
+            factor: settings.level_factor.max(1),
+            txt_weight: settings.txt_weight,
+            vc_weight: settings.vc_weight,
+            table,
+            destination,
+            message: settings.level_up_message,
+        })
+    }
+
+    /// Combines text XP and voice seconds (with multipliers applied) into the XP used for levels.
+    pub(crate) fn xp(&self, txt: i64, vc_seconds: i64) -> i64 {
+        (txt as f64 * self.txt_weight + vc_seconds as f64 * self.vc_weight) as i64
+    }
+
+    pub(crate) fn progress(&self, xp: i64) -> LevelProgress {
+        let xp = xp.max(0);
+        match self.formula {
+            LevelFormula::Linear => LevelProgress {
+                level: xp / self.factor,
+                xp: xp % self.factor,
+                next_level_xp: Some(self.factor),
+            },
+            LevelFormula::Quadratic => {
+                let mut level = (xp as f64 / self.factor as f64).sqrt() as i64;
+                //Correct floating point errors. level_xp saturates, so stop once it can't grow anymore.
+                while level > 0 && self.level_xp(level) > xp { level -= 1; }
+                while self.level_xp(level + 1) <= xp && self.level_xp(level + 1) < i64::MAX { level += 1; }
+                let reached = self.level_xp(level);
+                LevelProgress {
+                    level,
+                    xp: xp - reached,
+                    next_level_xp: Some(self.level_xp(level + 1) - reached),
+                }
+            },
+            LevelFormula::Mee6 => {
+                //Binary search for the highest level reached, as imported XP can be arbitrarily large.
+                let (mut low, mut high) = (0, 2_000_000);
+                while low < high {
+                    let middle = low + (high - low + 1) / 2;
+                    if self.level_xp(middle) <= xp { low = middle; } else { high = middle - 1; }
+                }
+                LevelProgress {
+                    level: low,
+                    xp: xp - self.level_xp(low),
+                    next_level_xp: Some(mee6_level_xp(low)),
+                }
+            },
+            LevelFormula::Custom => {
+                let level = self.table.iter().take_while(|v| **v <= xp).count();
+                let reached = level.checked_sub(1).map_or(0, |i| self.table[i]);
+                LevelProgress {
+                    level: level as i64,
+                    xp: xp - reached,
+                    next_level_xp: self.table.get(level).map(|v| v - reached),
+                }
+            },
+        }
+    }
+
+    /// The total XP needed to reach `level`. The inverse of [`Self::progress`].
+    /// Custom tables can't go beyond their highest level.
+    pub(crate) fn level_xp(&self, level: i64) -> i64 {
+        let level = level.max(0);
+        match self.formula {
+            LevelFormula::Linear => self.factor.saturating_mul(level),
+            LevelFormula::Quadratic => self.factor.saturating_mul(level).saturating_mul(level),
+            LevelFormula::Mee6 => {
+                //Sum of 5l² + 50l + 100 for l in 0..level. Levels beyond ~1.8 million exceed i64 anyway.
+                let n = i128::from(level.min(2_000_000));
+                let total = 5 * (n - 1) * n * (2 * n - 1) / 6 + 25 * n * (n - 1) + 100 * n;
+                i64::try_from(total).unwrap_or(i64::MAX)
+            },
+            LevelFormula::Custom => usize::try_from(level).ok()
+                .and_then(|level| level.checked_sub(1))
+                .map_or(0, |i| self.table.get(i).or(self.table.last()).copied().unwrap_or(0)),
+        }
+    }
+}
+
+/// Stores the current level of the members and announces, if they reached a new level.
+pub(in crate::client) async fn check_level_ups(pool: &sqlx::PgPool, http: &serenity::Http, members: &[(i64, i64)]) {
+    let mut guilds = members.iter().map(|(guild, _)| *guild).collect::<Vec<_>>();
+    guilds.sort_unstable();
+    guilds.dedup();
+    for guild_id in guilds {
+        let users = members.iter().filter(|(guild, _)| *guild == guild_id).map(|(_, user)| *user).collect::<Vec<_>>();
+        if let Err(err) = check_guild_level_ups(pool, http, guild_id, &users).await {
+            tracing::error!("Error checking level ups in guild {guild_id}: {err}");
+        }
+    }
+}
+
+async fn check_guild_level_ups(pool: &sqlx::PgPool, http: &serenity::Http, guild_id: i64, users: &[i64]) -> Result<(), sqlx::Error> {
+    let settings = LevelSettings::get(pool, guild_id).await?;
+    let members = sqlx::query!(
+        r#"SELECT user_id, txt, EXTRACT(EPOCH FROM vc_xp)::bigint as "vc!", level FROM xp_user WHERE guild_id = $1 AND user_id = ANY($2)"#,
+        guild_id, users
+    ).fetch_all(pool).await?;
+    for member in members {
+        let level = settings.progress(settings.xp(member.txt, member.vc)).level;
+        if member.level == Some(level) {
+            continue;
+        }
+        //Only one caller may announce a level-up, if levels get checked concurrently.
+        let updated = sqlx::query!(
+            "UPDATE xp_user SET level = $3 WHERE guild_id = $1 AND user_id = $2 AND level IS NOT DISTINCT FROM $4",
+            guild_id, member.user_id, level, member.level
+        ).execute(pool).await?;
+        if updated.rows_affected() == 0 {
+            continue;
+        }
+        let user = serenity::UserId::new(crate::convertu(member.user_id));
+        if member.level.is_some_and(|old| old < level) {
+            announce_level_up(http, &settings, user, level).await;
+        }
+        crate::client::role_limiter::handle_level_rewards(http, serenity::GuildId::new(crate::convertu(guild_id)), user, level).await;
+    }
+    Ok(())
+}
+
+impl crate::client::Handler {
+    /// Computes the current level of a member. `None`, if they never got any XP.
+    pub(in crate::client) async fn member_level(pool: &sqlx::PgPool, guild_id: i64, user_id: i64) -> Result<Option<i64>, sqlx::Error> {
+        let Some(member) = sqlx::query!(
+            r#"SELECT txt, EXTRACT(EPOCH FROM vc_xp)::bigint as "vc!" FROM xp_user WHERE guild_id = $1 AND user_id = $2"#,
+            guild_id, user_id
+        ).fetch_optional(pool).await? else {
+            return Ok(None);
+        };
+        let settings = LevelSettings::get(pool, guild_id).await?;
+        Ok(Some(settings.progress(settings.xp(member.txt, member.vc)).level))
+    }
+
+    /// Re-evaluates the level rewards of a member, e.g. when they rejoin the guild.
+    pub(in crate::client) async fn apply_level_rewards(&self, http: &serenity::Http, guild_id: serenity::GuildId, user_id: serenity::UserId) {
+        match Self::member_level(&self.pool, crate::converti(guild_id.get()), crate::converti(user_id.get())).await {
+            Ok(Some(level)) => crate::client::role_limiter::handle_level_rewards(http, guild_id, user_id, level).await,
+            Ok(None) => {},
+            Err(err) => {
+                tracing::error!("Error getting the level of {user_id} in guild {guild_id}: {err}");
+            }
+        }
+    }
+}
+
+async fn announce_level_up(http: &serenity::Http, settings: &LevelSettings, user: serenity::UserId, level: i64) {
+    let channel = match &settings.destination {
+        None => return,
+        Some(LevelUpDestination::Channel(channel)) => *channel,
+        Some(LevelUpDestination::Dm) => match user.create_dm_channel(http).await {
+            Ok(v) => v.id,
+            Err(err) => {
+                tracing::info!("Failed to open a DM with {user} to announce level {level}: {err}");
+                return;
+            }
+        },
+    };
+    let content = settings.message.as_deref()
+        .unwrap_or(DEFAULT_LEVEL_UP_MESSAGE)
+        .replace("{user}", format!("<@{user}>").as_str())
+        .replace("{level}", level.to_string().as_str());
+    let message = serenity::CreateMessage::new()
+        .content(content)
+        .allowed_mentions(serenity::CreateAllowedMentions::new().users(vec![user]));
+    if let Err(err) = channel.send_message(http, message).await {
+        tracing::warn!("Failed to announce level {level} of {user} in {channel}: {err}");
+    }
+}
@@ -0,0 +1,62 @@
+use poise::serenity_prelude as serenity;
+
+/// How the multipliers of a member's roles and the channel get combined.
+#[derive(poise::ChoiceParameter, Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum MultiplierRule {
+    /// The highest multiplier wins.
+    #[default]
+    #[name_localized("de", "Maximum")]
+    Max,
+    /// All multipliers get multiplied, so a 0x role always wins.
+    #[name_localized("de", "Produkt")]
+    Product,
+}
+impl MultiplierRule {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Max => "max",
+            Self::Product => "product",
+        }
+    }
+    pub(crate) fn from_db(value: &str) -> Self {
+        match value {
+            "product" => Self::Product,
+            _ => Self::Max,
+        }
+    }
+    /// Combines the multipliers. Without any multipliers, XP is unchanged.
+    fn combine(self, multipliers: &[f64]) -> f64 {
+        if multipliers.is_empty() {
+            return 1.;
+        }
+        match self {
+            Self::Max => multipliers.iter().copied().fold(f64::MIN, f64::max),
+            Self::Product => multipliers.iter().product(),
+        }
+    }
+}
+
+impl crate::client::Handler {
+    /// Gets the XP multiplier for a member with `roles` in `channel_id`.
+    pub(in crate::client) async fn xp_multiplier(&self, guild_id: serenity::GuildId, channel_id: serenity::ChannelId, roles: &[serenity::RoleId]) -> f64 {
+        let roles = roles.iter().map(|v| crate::converti(v.get())).collect::<Vec<_>>();
+        match sqlx::query!(
+            r#"SELECT
+    COALESCE((SELECT multiplier_rule FROM xp WHERE guild_id = $1), 'max') as "rule!",
+    array(SELECT multiplier FROM xp_multipliers_role WHERE guild_id = $1 AND role_id = ANY($3))
+        || array(SELECT multiplier FROM xp_multipliers_channel WHERE guild_id = $1 AND channel_id = $2) as "multipliers!""#,
+            crate::converti(guild_id.get()), crate::converti(channel_id.get()), roles.as_slice()
+        ).fetch_one(&self.pool).await {
+            Ok(v) => MultiplierRule::from_db(&v.rule).combine(&v.multipliers),
+            Err(err) => {
+                tracing::error!("Error getting the xp multiplier in guild {guild_id}: {err}");
+                1.
+            }
+        }
+    }
+}
+
+/// Applies a multiplier to an amount of XP.
+pub(crate) fn multiply(xp: i64, multiplier: f64) -> i64 {
+    (xp as f64 * multiplier).round() as i64
+}
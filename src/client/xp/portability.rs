@@ -0,0 +1,299 @@
+//! Importing XP from other leveling bots and exporting it again.
+use std::collections::BTreeMap;
+use super::{LevelFormula, LevelSettings};
+
+/// Field names used for member ids, XP and levels by the exports of other bots, in order of preference.
+const ID_FIELDS: &[&str] = &["id", "user_id", "userid", "member_id", "user"];
+const XP_FIELDS: &[&str] = &["xp", "score", "exp", "experience", "total_xp"];
+const LEVEL_FIELDS: &[&str] = &["level", "lvl"];
+/// Keys of the member list in JSON exports, e.g. `{"players": [...]}` for MEE6 or `{"rankings": [...]}` for Tatsu.
+const LIST_FIELDS: &[&str] = &["players", "rankings", "users", "members", "leaderboard", "data"];
+
+/// The leveling bot an export comes from.
+#[derive(poise::ChoiceParameter, Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum ImportSource {
+    #[name = "MEE6"]
+    Mee6,
+    Arcane,
+    Tatsu,
+}
+impl ImportSource {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Mee6 => "MEE6",
+            Self::Arcane => "Arcane",
+            Self::Tatsu => "Tatsu",
+        }
+    }
+}
+impl std::str::FromStr for ImportSource {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "mee6" => Ok(Self::Mee6),
+            "arcane" => Ok(Self::Arcane),
+            "tatsu" => Ok(Self::Tatsu),
+            other => Err(format!("Unknown source {other}. Expected mee6, arcane or tatsu.")),
+        }
+    }
+}
+
+/// How imported XP maps onto text XP and voice time.
+#[derive(poise::ChoiceParameter, Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Conversion {
+    /// XP × factor becomes text XP.
+    #[default]
+    #[name_localized("de", "Text")]
+    Text,
+    /// XP × factor becomes seconds of voice time.
+    #[name_localized("de", "Sprache")]
+    Voice,
+    /// Members get the text XP needed to keep their level with this server's level formula.
+    #[name_localized("de", "Level")]
+    Level,
+}
+impl std::str::FromStr for Conversion {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "voice" => Ok(Self::Voice),
+            "level" => Ok(Self::Level),
+            other => Err(format!("Unknown conversion {other}. Expected text, voice or level.")),
+        }
+    }
+}
+
+#[derive(poise::ChoiceParameter, Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum ExportFormat {
+    #[default]
+    #[name = "CSV"]
+    Csv,
+    #[name = "JSON"]
+    Json,
+}
+impl ExportFormat {
+    pub(crate) fn extension(self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Json => "json",
+        }
+    }
+}
+impl std::str::FromStr for ExportFormat {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "csv" => Ok(Self::Csv),
+            "json" => Ok(Self::Json),
+            other => Err(format!("Unknown format {other}. Expected csv or json.")),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct ImportOptions {
+    pub source: ImportSource,
+    pub conversion: Conversion,
+    /// Multiplies the imported XP for [`Conversion::Text`] and [`Conversion::Voice`]
+    pub factor: f64,
+    /// Replace the current XP of imported members instead of adding to it
+    pub replace: bool,
+}
+
+/// A member read from the export of another bot.
+#[derive(Debug, Copy, Clone)]
+struct ImportedMember {
+    xp: i64,
+    level: Option<i64>,
+}
+
+/// Reads numbers, that are either JSON numbers or strings (ids are strings in most exports).
+fn json_number(value: &serde_json::Value) -> Option<i64> {
+    match value {
+        serde_json::Value::Number(v) => v.as_i64().or_else(|| v.as_f64().map(|v| v as i64)),
+        serde_json::Value::String(v) => v.trim().parse().ok(),
+        _ => None,
+    }
+}
+fn json_field(entry: &serde_json::Map<String, serde_json::Value>, fields: &[&str]) -> Option<i64> {
+    fields.iter().find_map(|field| {
+        entry.iter().find(|(key, _)| key.eq_ignore_ascii_case(field)).and_then(|(_, value)| json_number(value))
+    })
+}
+
+fn parse_json(data: &[u8]) -> Result<BTreeMap<u64, ImportedMember>, String> {
+    let value: serde_json::Value = serde_json::from_slice(data).map_err(|err| format!("The file is not valid JSON: {err}"))?;
+    let list = match &value {
+        serde_json::Value::Array(list) => Some(list),
+        serde_json::Value::Object(object) => LIST_FIELDS.iter().find_map(|field| object.get(*field)?.as_array()),
+        _ => None,
+    }.ok_or_else(|| format!("Couldn't find the list of members. Expected a list or an object with one of {LIST_FIELDS:?}."))?;
+    let mut members = BTreeMap::new();
+    for (i, entry) in list.iter().enumerate() {
+        let entry = entry.as_object().ok_or_else(|| format!("Entry {i} is not an object."))?;
+        let id = json_field(entry, ID_FIELDS).ok_or_else(|| format!("Entry {i} has no member id."))?;
+        let xp = json_field(entry, XP_FIELDS).ok_or_else(|| format!("Entry {i} has no XP."))?;
+        members.insert(crate::convertu(id), ImportedMember { xp, level: json_field(entry, LEVEL_FIELDS) });
+    }
+    Ok(members)
+}
+
+fn parse_csv(data: &[u8]) -> Result<BTreeMap<u64, ImportedMember>, String> {
+    let mut reader = csv::ReaderBuilder::new().trim(csv::Trim::All).flexible(true).from_reader(data);
+    let headers = reader.headers().map_err(|err| format!("The file is not valid CSV: {err}"))?.clone();
+    let column = |fields: &[&str]| fields.iter().find_map(|field| headers.iter().position(|header| header.eq_ignore_ascii_case(field)));
+    let id_column = column(ID_FIELDS).ok_or_else(|| format!("There is no member id column. Expected one of {ID_FIELDS:?}."))?;
+    let xp_column = column(XP_FIELDS).ok_or_else(|| format!("There is no XP column. Expected one of {XP_FIELDS:?}."))?;
+    let level_column = column(LEVEL_FIELDS);
+    let mut members = BTreeMap::new();
+    for (i, record) in reader.records().enumerate() {
+        //The header is line 1
+        let line = i + 2;
+        let record = record.map_err(|err| format!("Line {line} is not valid CSV: {err}"))?;
+        let number = |column: usize| record.get(column).and_then(|v| v.parse::<f64>().ok()).map(|v| v as i64);
+        let id = record.get(id_column).and_then(|v| v.parse::<u64>().ok()).ok_or_else(|| format!("Line {line} has no valid member id."))?;
+        let xp = number(xp_column).ok_or_else(|| format!("Line {line} has no valid XP."))?;
+        members.insert(id, ImportedMember { xp, level: level_column.and_then(number) });
+    }
+    Ok(members)
+}
+
+/// The result of an import.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct ImportSummary {
+    pub members: usize,
+    pub txt: i64,
+    pub vc_seconds: i64,
+}
+
+/// Imports the XP export of another bot into a guild.
+/// `actor` is the member running the import, or `None` for the command line.
+pub(crate) async fn import(pool: &sqlx::PgPool, guild_id: i64, actor: Option<i64>, data: &[u8], options: &ImportOptions) -> Result<ImportSummary, String> {
+    let is_json = data.iter().find(|v| !v.is_ascii_whitespace()).is_some_and(|v| *v == b'{' || *v == b'[');
+    let members = if is_json { parse_json(data)? } else { parse_csv(data)? };
+    if members.is_empty() {
+        return Err("The file contains no members.".to_string());
+    }
+    let settings = LevelSettings::get(pool, guild_id).await.map_err(|err| format!("Failed to get the level settings: {err}"))?;
+    //MEE6 and Arcane use the same level curve, so missing levels can be derived from the XP.
+    let source_levels = LevelSettings { formula: LevelFormula::Mee6, ..LevelSettings::default() };
+
+    let mut users = Vec::with_capacity(members.len());
+    let mut txt = Vec::with_capacity(members.len());
+    let mut vc = Vec::with_capacity(members.len());
+    for (user, member) in members {
+        let (member_txt, member_vc) = match options.conversion {
+            Conversion::Text => ((member.xp as f64 * options.factor).round() as i64, 0),
+            Conversion::Voice => (0, (member.xp as f64 * options.factor).round() as i64),
+            Conversion::Level => {
+                let level = match (member.level, options.source) {
+                    (Some(level), _) => level,
+                    (None, ImportSource::Mee6 | ImportSource::Arcane) => source_levels.progress(member.xp).level,
+                    (None, ImportSource::Tatsu) => return Err("Tatsu exports contain no levels. Please convert the XP to text XP or voice time instead.".to_string()),
+                };
+                let level_xp = settings.level_xp(level) as f64;
+                if settings.txt_weight > 0. {
+                    ((level_xp / settings.txt_weight).ceil() as i64, 0)
+                } else if settings.vc_weight > 0. {
+                    (0, (level_xp / settings.vc_weight).ceil() as i64)
+                } else {
+                    return Err("Text XP and voice time don't count towards levels on this server, so levels can't be kept.".to_string());
+                }
+            },
+        };
+        users.push(crate::converti(user));
+        txt.push(member_txt.max(0));
+        vc.push(member_vc.max(0) as f64);
+    }
+    let summary = ImportSummary {
+        members: users.len(),
+        txt: txt.iter().sum(),
+        vc_seconds: vc.iter().sum::<f64>() as i64,
+    };
+
+    let result = async {
+        let mut transaction = pool.begin().await?;
+        //Levels are stored again without announcing them, when the members next get XP.
+        sqlx::query!(
+            "INSERT INTO xp_user (guild_id, user_id, txt, vc, vc_xp, level)
+SELECT $1, user_id, txt, make_interval(secs => vc), make_interval(secs => vc), NULL FROM unnest($2::bigint[], $3::bigint[], $4::float8[]) as input(user_id, txt, vc)
+ON CONFLICT (guild_id, user_id) DO UPDATE SET
+    txt = CASE WHEN $5 THEN excluded.txt ELSE xp_user.txt + excluded.txt END,
+    vc = CASE WHEN $5 THEN excluded.vc ELSE xp_user.vc + excluded.vc END,
+    vc_xp = CASE WHEN $5 THEN excluded.vc_xp ELSE xp_user.vc_xp + excluded.vc_xp END,
+    level = NULL",
+            guild_id, users.as_slice(), txt.as_slice(), vc.as_slice(), options.replace
+        ).execute(&mut *transaction).await?;
+        let reason = format!(
+            "Imported {} members from {} ({}{})",
+            summary.members, options.source.name(), poise::ChoiceParameter::name(&options.conversion),
+            if options.replace { ", replacing their XP" } else { "" },
+        );
+        sqlx::query!(
+            "INSERT INTO xp_audit (guild_id, actor_id, action, txt, vc, reason) VALUES ($1, $2, 'import', $3, make_interval(secs => $4), $5)",
+            guild_id, actor, summary.txt, summary.vc_seconds as f64, reason
+        ).execute(&mut *transaction).await?;
+        transaction.commit().await
+    }.await;
+    result.map_err(|err| format!("Failed to store the imported XP: {err}"))?;
+    Ok(summary)
+}
+
+/// A member as exported by [`export`].
+#[derive(Debug, serde_derive::Serialize)]
+struct ExportedMember {
+    /// A string, because JSON numbers can't hold Discord ids in many languages
+    user_id: String,
+    username: Option<String>,
+    display_name: Option<String>,
+    nickname: Option<String>,
+    txt: i64,
+    vc_seconds: i64,
+    level: Option<i64>,
+}
+
+/// Exports the XP of all members of a guild, including their stored names.
+pub(crate) async fn export(pool: &sqlx::PgPool, guild_id: i64, format: ExportFormat) -> Result<Vec<u8>, String> {
+    let members = sqlx::query!(
+        r#"SELECT
+    xp_user.user_id,
+    users_username.username as "username?",
+    users.display_name as "display_name?",
+    guild_user.nickname as "nickname?",
+    xp_user.txt,
+    EXTRACT(EPOCH FROM xp_user.vc)::bigint as "vc_seconds!",
+    xp_user.level
+FROM xp_user
+LEFT JOIN users_username ON users_username.id = xp_user.user_id
+LEFT JOIN users ON users.id = xp_user.user_id
+LEFT JOIN guild_user ON guild_user.guild_id = xp_user.guild_id AND guild_user.user_id = xp_user.user_id
+WHERE xp_user.guild_id = $1
+ORDER BY xp_user.txt DESC, xp_user.user_id"#,
+        guild_id
+    ).fetch_all(pool).await.map_err(|err| format!("Failed to get the XP of the members: {err}"))?
+        .into_iter()
+        .map(|v| ExportedMember {
+            user_id: crate::convertu(v.user_id).to_string(),
+            username: v.username,
+            display_name: v.display_name,
+            nickname: v.nickname,
+            txt: v.txt,
+            vc_seconds: v.vc_seconds,
+            level: v.level,
+        })
+        .collect::<Vec<_>>();
+    match format {
+        ExportFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(Vec::new());
+            for member in &members {
+                writer.serialize(member).map_err(|err| format!("Failed to write CSV: {err}"))?;
+            }
+            writer.into_inner().map_err(|err| format!("Failed to write CSV: {err}"))
+        },
+        ExportFormat::Json => serde_json::to_vec_pretty(&serde_json::json!({
+            "guild_id": crate::convertu(guild_id).to_string(),
+            "members": members,
+        })).map_err(|err| format!("Failed to write JSON: {err}")),
+    }
+}
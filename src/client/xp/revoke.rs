@@ -0,0 +1,133 @@
+use poise::serenity_prelude as serenity;
+
+/// How long awarded text XP can be revoked by deleting the message or reaction.
+const AWARD_RETENTION: std::time::Duration = std::time::Duration::from_secs(7*24*60*60);
+
+/// Identifies an emoji across reaction events. Custom emojis can be renamed, so only their id is used.
+fn emoji_key(emoji: &serenity::ReactionType) -> String {
+    match emoji {
+        serenity::ReactionType::Custom { id, .. } => id.to_string(),
+        serenity::ReactionType::Unicode(name) => name.clone(),
+        other => other.to_string(),
+    }
+}
+
+impl crate::client::Handler {
+    /// Records XP awarded for a message, so it can be revoked if the message gets deleted.
+    pub(in crate::client) async fn record_message_xp(&self, guild_id: serenity::GuildId, message_id: serenity::MessageId, user_id: serenity::UserId, xp: i64) {
+        self.record_txt_xp(guild_id, message_id, user_id, None, xp).await
+    }
+    /// Records XP awarded for a reaction, so it can be revoked if the reaction or message gets removed.
+    pub(in crate::client) async fn record_reaction_xp(&self, reaction: &serenity::Reaction, guild_id: serenity::GuildId, user_id: serenity::UserId, xp: i64) {
+        let Some(reactor) = reaction.user_id else { return; };
+        self.record_txt_xp(guild_id, reaction.message_id, user_id, Some((reactor, emoji_key(&reaction.emoji))), xp).await
+    }
+    async fn record_txt_xp(&self, guild_id: serenity::GuildId, message_id: serenity::MessageId, user_id: serenity::UserId, reaction: Option<(serenity::UserId, String)>, xp: i64) {
+        if xp == 0 {
+            return;
+        }
+        let (reaction_user, reaction_emoji) = reaction.map_or((None, None), |(user, emoji)| (Some(crate::converti(user.get())), Some(emoji)));
+        match sqlx::query!(
+            "INSERT INTO xp_txt_awards (guild_id, message_id, user_id, reaction_user_id, reaction_emoji, xp) VALUES ($1, $2, $3, $4, $5, $6)",
+            crate::converti(guild_id.get()), crate::converti(message_id.get()), crate::converti(user_id.get()), reaction_user, reaction_emoji, xp
+        ).execute(&self.pool).await {
+            Ok(_) => {},
+            Err(err) => {
+                tracing::error!("Error recording text xp of message {message_id}: {err}");
+            }
+        }
+    }
+
+    /// Scales down the recorded XP of pending messages and reactions, when a spam penalty took most of it.
+    /// Revoking them later then only takes the XP that was actually applied.
+    pub(in crate::client) async fn penalize_txt_awards(&self, guild_id: i64, user_id: i64, total_xp: i64, applied_xp: i64, pending_since: &sqlx::postgres::types::PgInterval) {
+        if total_xp <= 0 {
+            return;
+        }
+        //The pending XP was queued a moment before its award got recorded, so allow for some delay.
+        match sqlx::query!(
+            "UPDATE xp_txt_awards SET xp = xp * GREATEST($3::bigint, 0) / $4 WHERE guild_id = $1 AND user_id = $2 AND time >= now() - $5::interval - '1 second'::interval",
+            guild_id, user_id, applied_xp, total_xp, pending_since
+        ).execute(&self.pool).await {
+            Ok(_) => {},
+            Err(err) => {
+                tracing::error!("Error penalizing the text xp awards of {user_id} in guild {guild_id}: {err}");
+            }
+        }
+    }
+
+    /// Revokes the XP of deleted messages, including XP for reactions on them.
+    pub(in crate::client) async fn revoke_message_xp(&self, http: &serenity::Http, guild_id: Option<serenity::GuildId>, message_ids: &[serenity::MessageId]) {
+        let Some(guild_id) = guild_id else { return; };
+        let messages = message_ids.iter().map(|v| crate::converti(v.get())).collect::<Vec<_>>();
+        match sqlx::query!(
+            "DELETE FROM xp_txt_awards WHERE guild_id = $1 AND message_id = ANY($2) RETURNING user_id, xp",
+            crate::converti(guild_id.get()), messages.as_slice()
+        ).fetch_all(&self.pool).await {
+            Ok(v) => self.revoke_txt_xp(http, guild_id, v.into_iter().map(|v| (v.user_id, v.xp)).collect()).await,
+            Err(err) => {
+                tracing::error!("Error getting the text xp of deleted messages: {err}");
+            }
+        }
+    }
+
+    /// Revokes the XP the reacting member and the message author got for a reaction.
+    pub(in crate::client) async fn revoke_reaction_xp(&self, http: &serenity::Http, reaction: &serenity::Reaction) {
+        let (Some(guild_id), Some(reactor)) = (reaction.guild_id, reaction.user_id) else { return; };
+        match sqlx::query!(
+            "DELETE FROM xp_txt_awards WHERE guild_id = $1 AND message_id = $2 AND reaction_user_id = $3 AND reaction_emoji = $4 RETURNING user_id, xp",
+            crate::converti(guild_id.get()), crate::converti(reaction.message_id.get()), crate::converti(reactor.get()), emoji_key(&reaction.emoji)
+        ).fetch_all(&self.pool).await {
+            Ok(v) => self.revoke_txt_xp(http, guild_id, v.into_iter().map(|v| (v.user_id, v.xp)).collect()).await,
+            Err(err) => {
+                tracing::error!("Error getting the text xp of a removed reaction: {err}");
+            }
+        }
+    }
+
+    /// Takes XP from members. Pending XP gets reduced first, the rest comes from their applied XP and today's history.
+    async fn revoke_txt_xp(&self, http: &serenity::Http, guild_id: serenity::GuildId, awards: Vec<(i64, i64)>) {
+        let guild = crate::converti(guild_id.get());
+        let mut users = awards.iter().map(|(user, _)| *user).collect::<Vec<_>>();
+        users.sort_unstable();
+        users.dedup();
+        for user in users {
+            let xp = awards.iter().filter(|(v, _)| *v == user).map(|(_, xp)| *xp).sum::<i64>();
+            match sqlx::query!(
+                "WITH pending AS (
+    SELECT LEAST(xp, $3) as xp FROM xp_txt_tmp WHERE guild_id = $1 AND user_id = $2 FOR UPDATE
+), tmp AS (
+    UPDATE xp_txt_tmp SET xp = xp_txt_tmp.xp - pending.xp FROM pending WHERE xp_txt_tmp.guild_id = $1 AND xp_txt_tmp.user_id = $2
+), history AS (
+    UPDATE xp_history SET txt = GREATEST(xp_history.txt - ($3 - COALESCE((SELECT xp FROM pending), 0)), 0)
+    WHERE guild_id = $1 AND user_id = $2 AND day = (now() AT TIME ZONE 'UTC')::date
+) UPDATE xp_user SET txt = GREATEST(xp_user.txt - ($3 - COALESCE((SELECT xp FROM pending), 0)), 0) WHERE guild_id = $1 AND user_id = $2",
+                guild, user, xp
+            ).execute(&self.pool).await {
+                Ok(_) => tracing::debug!("Revoked {xp} text xp of {user} in guild {guild_id}"),
+                Err(err) => {
+                    tracing::error!("Error revoking text xp of {user} in guild {guild_id}: {err}");
+                }
+            }
+        }
+        let members = awards.iter().map(|(user, _)| (guild, *user)).collect::<Vec<_>>();
+        super::check_level_ups(&self.pool, http, &members).await;
+    }
+
+    /// Forgets awarded XP older than [`AWARD_RETENTION`]. It can no longer be revoked afterwards.
+    pub(in crate::client) async fn prune_txt_awards(&self) {
+        let retention = match sqlx::postgres::types::PgInterval::try_from(AWARD_RETENTION) {
+            Ok(v) => v,
+            Err(err) => {
+                tracing::error!("Invalid text xp award retention: {err}");
+                return;
+            }
+        };
+        match sqlx::query!("DELETE FROM xp_txt_awards WHERE time < now() - $1::interval", retention).execute(&self.pool).await {
+            Ok(_) => {},
+            Err(err) => {
+                tracing::error!("Error pruning text xp awards: {err}");
+            }
+        }
+    }
+}
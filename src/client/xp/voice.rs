@@ -0,0 +1,239 @@
+use poise::serenity_prelude as serenity;
+
+/// When voice time is paused for a guild.
+#[derive(Debug, Copy, Clone)]
+#[non_exhaustive]
+pub(crate) struct VoiceRules {
+    /// Pause while self-deafened or deafened by the server
+    pub pause_deafened: bool,
+    /// Pause while muted by the server
+    pub pause_muted: bool,
+    /// Pause while no other member (except bots) is in the channel
+    pub pause_alone: bool,
+    /// Pause in the guild's AFK channel
+    pub pause_afk: bool,
+}
+impl Default for VoiceRules {
+    fn default() -> Self {
+        Self {
+            pause_deafened: true,
+            pause_muted: true,
+            pause_alone: true,
+            pause_afk: true,
+        }
+    }
+}
+impl VoiceRules {
+    pub(crate) async fn get(pool: &sqlx::PgPool, guild_id: i64) -> Result<Self, sqlx::Error> {
+        Ok(sqlx::query!(
+            "SELECT vc_pause_deafened, vc_pause_muted, vc_pause_alone, vc_pause_afk FROM xp WHERE guild_id = $1",
+            guild_id
+        ).fetch_optional(pool).await?
+            .map(|v| Self {
+                pause_deafened: v.vc_pause_deafened,
+                pause_muted: v.vc_pause_muted,
+                pause_alone: v.vc_pause_alone,
+                pause_afk: v.vc_pause_afk,
+            })
+            .unwrap_or_default())
+    }
+}
+
+/// A member in a voice channel, as stored in `xp_vc_state`.
+#[derive(Debug, Copy, Clone)]
+pub(in crate::client) struct VoiceMember {
+    pub user_id: serenity::UserId,
+    pub channel_id: serenity::ChannelId,
+    pub deafened: bool,
+    pub muted: bool,
+    pub bot: bool,
+}
+impl VoiceMember {
+    pub(in crate::client) fn new(state: &serenity::VoiceState, bot: bool) -> Option<Self> {
+        Some(Self {
+            user_id: state.user_id,
+            channel_id: state.channel_id?,
+            deafened: state.self_deaf || state.deaf,
+            muted: state.mute,
+            bot,
+        })
+    }
+}
+
+impl crate::client::Handler {
+    /// Stores the new voice state of a member and re-evaluates everyone in the channel they left and joined.
+    pub(in crate::client) async fn voice_state_xp(&self, ctx: &serenity::Context, guild_id: serenity::GuildId, state: &serenity::VoiceState) {
+        let guild = crate::converti(guild_id.get());
+        let user = crate::converti(state.user_id.get());
+        let bot = state.member.as_ref().is_some_and(|v| v.user.bot);
+        let old_channel = match VoiceMember::new(state, bot) {
+            None => sqlx::query!("DELETE FROM xp_vc_state WHERE guild_id = $1 AND user_id = $2 RETURNING channel_id", guild, user)
+                .fetch_optional(&self.pool).await
+                .map(|v| v.map(|v| v.channel_id)),
+            Some(member) => sqlx::query!(
+                r#"WITH old AS (SELECT channel_id FROM xp_vc_state WHERE guild_id = $1 AND user_id = $2)
+INSERT INTO xp_vc_state (guild_id, user_id, channel_id, deafened, muted, bot) VALUES ($1, $2, $3, $4, $5, $6)
+ON CONFLICT (guild_id, user_id) DO UPDATE SET channel_id = $3, deafened = $4, muted = $5, bot = $6
+RETURNING (SELECT channel_id FROM old) as channel_id"#,
+                guild, user, crate::converti(member.channel_id.get()), member.deafened, member.muted, member.bot
+            ).fetch_one(&self.pool).await
+                .map(|v| v.channel_id),
+        };
+        let old_channel = match old_channel {
+            Ok(v) => v.map(|v| serenity::ChannelId::new(crate::convertu(v))),
+            Err(err) => {
+                tracing::error!("Error updating the voice state of {} in guild {guild_id}: {err}", state.user_id);
+                return;
+            }
+        };
+        if state.channel_id.is_none() {
+            self.stop_voice_xp(&ctx.http, guild_id, state.user_id).await;
+        }
+        let mut channels = old_channel.into_iter().chain(state.channel_id).collect::<Vec<_>>();
+        channels.dedup();
+        self.evaluate_voice_channels(ctx, guild_id, &channels).await;
+    }
+
+    /// Replaces the stored voice states of a guild, e.g. after the bot was offline.
+    pub(in crate::client) async fn resync_voice_xp(&self, ctx: &serenity::Context, guild_id: serenity::GuildId, members: &[VoiceMember]) {
+        let guild = crate::converti(guild_id.get());
+        let users = members.iter().map(|v| crate::converti(v.user_id.get())).collect::<Vec<_>>();
+        let channels = members.iter().map(|v| crate::converti(v.channel_id.get())).collect::<Vec<_>>();
+        let deafened = members.iter().map(|v| v.deafened).collect::<Vec<_>>();
+        let muted = members.iter().map(|v| v.muted).collect::<Vec<_>>();
+        let bots = members.iter().map(|v| v.bot).collect::<Vec<_>>();
+        let result = async {
+            let mut transaction = self.pool.begin().await?;
+            sqlx::query!("DELETE FROM xp_vc_state WHERE guild_id = $1", guild).execute(&mut *transaction).await?;
+            sqlx::query!(
+                "INSERT INTO xp_vc_state (guild_id, user_id, channel_id, deafened, muted, bot) SELECT $1, * FROM unnest($2::bigint[], $3::bigint[], $4::bool[], $5::bool[], $6::bool[])",
+                guild, users.as_slice(), channels.as_slice(), deafened.as_slice(), muted.as_slice(), bots.as_slice()
+            ).execute(&mut *transaction).await?;
+            //Members, that left while the bot was offline, stop accruing voice time.
+            let left = sqlx::query!("SELECT user_id FROM xp_vc_tmp WHERE guild_id = $1 AND NOT user_id = ANY($2)", guild, users.as_slice())
+                .fetch_all(&mut *transaction).await?;
+            transaction.commit().await?;
+            Ok::<_, sqlx::Error>(left)
+        }.await;
+        match result {
+            Ok(left) => {
+                for member in left {
+                    self.stop_voice_xp(&ctx.http, guild_id, serenity::UserId::new(crate::convertu(member.user_id))).await;
+                }
+            },
+            Err(err) => {
+                tracing::error!("Error resyncing the voice states of guild {guild_id}: {err}");
+                return;
+            }
+        }
+        let mut channels = members.iter().map(|v| v.channel_id).collect::<Vec<_>>();
+        channels.sort_unstable();
+        channels.dedup();
+        self.evaluate_voice_channels(ctx, guild_id, &channels).await;
+    }
+
+    /// Starts or pauses the voice time of everyone in the channels according to the guild's [`VoiceRules`].
+    async fn evaluate_voice_channels(&self, ctx: &serenity::Context, guild_id: serenity::GuildId, channels: &[serenity::ChannelId]) {
+        if channels.is_empty() {
+            return;
+        }
+        let guild = crate::converti(guild_id.get());
+        let rules = match VoiceRules::get(&self.pool, guild).await {
+            Ok(v) => v,
+            Err(err) => {
+                tracing::error!("Error getting the voice xp rules of guild {guild_id}: {err}");
+                return;
+            }
+        };
+        let channels = channels.iter().map(|v| crate::converti(v.get())).collect::<Vec<_>>();
+        let members = match sqlx::query!(
+            r#"SELECT
+    xp_vc_state.user_id,
+    xp_vc_state.channel_id,
+    xp_vc_state.deafened,
+    xp_vc_state.muted,
+    EXISTS(SELECT 1 FROM xp_channels_ignored WHERE xp_channels_ignored.guild_id = $1 AND xp_channels_ignored.channel_id = xp_vc_state.channel_id) as "ignored!",
+    (SELECT count(*) FROM xp_vc_state others WHERE others.guild_id = $1 AND others.channel_id = xp_vc_state.channel_id AND others.user_id <> xp_vc_state.user_id AND NOT others.bot) as "others!",
+    COALESCE((SELECT afk_channel_id FROM guilds WHERE guild_id = $1) = xp_vc_state.channel_id, false) as "afk!"
+FROM xp_vc_state WHERE xp_vc_state.guild_id = $1 AND xp_vc_state.channel_id = ANY($2)"#,
+            guild, channels.as_slice()
+        ).fetch_all(&self.pool).await {
+            Ok(v) => v,
+            Err(err) => {
+                tracing::error!("Error getting the voice states of guild {guild_id}: {err}");
+                return;
+            }
+        };
+        for member in members {
+            let user_id = serenity::UserId::new(crate::convertu(member.user_id));
+            let paused = member.ignored
+                || (rules.pause_deafened && member.deafened)
+                || (rules.pause_muted && member.muted)
+                || (rules.pause_alone && member.others == 0)
+                || (rules.pause_afk && member.afk);
+            if paused {
+                self.stop_voice_xp(&ctx.http, guild_id, user_id).await;
+                continue;
+            }
+            let channel_id = serenity::ChannelId::new(crate::convertu(member.channel_id));
+            let roles = match guild_id.member(ctx, user_id).await {
+                Ok(v) => v.roles,
+                Err(err) => {
+                    tracing::info!("Failed to get the roles of {user_id} for their xp multiplier: {err}");
+                    Vec::new()
+                }
+            };
+            let multiplier = self.xp_multiplier(guild_id, channel_id, &roles).await;
+            self.start_voice_xp(&ctx.http, guild_id, user_id, multiplier).await;
+        }
+    }
+
+    /// Applies the voice time accrued since joining (or the last periodic apply) and stops accruing more.
+    pub(in crate::client) async fn stop_voice_xp(&self, http: &serenity::Http, guild_id: serenity::GuildId, user_id: serenity::UserId) {
+        match sqlx::query!("WITH vc_xp_apply AS (
+    DELETE FROM xp_vc_tmp WHERE guild_id = $1 AND user_id = $2 RETURNING *
+), history AS (
+    INSERT INTO xp_history (guild_id, user_id, day, vc, vc_xp)
+    SELECT guild_id, user_id, (now() AT TIME ZONE 'UTC')::date, now() - time, (now() - time) * multiplier FROM vc_xp_apply
+    ON CONFLICT (guild_id, user_id, day) DO UPDATE SET vc = xp_history.vc + excluded.vc, vc_xp = xp_history.vc_xp + excluded.vc_xp
+) MERGE INTO xp_user USING vc_xp_apply ON xp_user.guild_id = vc_xp_apply.guild_id AND xp_user.user_id = vc_xp_apply.user_id
+WHEN MATCHED THEN UPDATE SET vc = xp_user.vc + (now() - vc_xp_apply.time), vc_xp = xp_user.vc_xp + (now() - vc_xp_apply.time) * vc_xp_apply.multiplier
+WHEN NOT MATCHED THEN INSERT (guild_id, user_id, vc, vc_xp) VALUES (vc_xp_apply.guild_id, vc_xp_apply.user_id, now() - vc_xp_apply.time, (now() - vc_xp_apply.time) * vc_xp_apply.multiplier)
+", crate::converti(guild_id.get()), crate::converti(user_id.get())).execute(&self.pool).await {
+            Ok(v) => match v.rows_affected(){
+                //Members get re-evaluated, even if they weren't accruing voice time.
+                0 => {},
+                _ => {
+                    super::check_level_ups(&self.pool, http, &[(crate::converti(guild_id.get()), crate::converti(user_id.get()))]).await;
+                },
+            },
+            Err(err) => {
+                tracing::error!("Error applying voice xp: {err}");
+            }
+        }
+    }
+    /// Starts accruing voice time with the multiplier.
+    /// If the multiplier changed (e.g. after switching channels), the time so far gets applied with the old multiplier first.
+    async fn start_voice_xp(&self, http: &serenity::Http, guild_id: serenity::GuildId, user_id: serenity::UserId, multiplier: f64) {
+        let guild = crate::converti(guild_id.get());
+        let user = crate::converti(user_id.get());
+        match sqlx::query!("SELECT multiplier FROM xp_vc_tmp WHERE guild_id = $1 AND user_id = $2", guild, user).fetch_optional(&self.pool).await {
+            Ok(Some(v)) if v.multiplier == multiplier => return,
+            Ok(Some(_)) => self.stop_voice_xp(http, guild_id, user_id).await,
+            Ok(None) => {},
+            Err(err) => {
+                tracing::error!("Error getting voice xp tmp: {err}");
+                return;
+            }
+        }
+        match sqlx::query!(
+            "INSERT INTO xp_vc_tmp (guild_id, user_id, time, multiplier) VALUES ($1, $2, now(), $3) ON CONFLICT DO NOTHING",
+            guild, user, multiplier
+        ).execute(&self.pool).await {
+            Ok(_) => {},
+            Err(err) => {
+                tracing::error!("Error adding user to voice xp tmp: {err}");
+            }
+        }
+    }
+}
@@ -30,4 +30,13 @@ impl DiscordClient {
     pub async fn get_guild(&self, guild_id: serenity::model::id::GuildId) -> Result<serenity::model::guild::PartialGuild, serenity::Error> {
         guild_id.to_partial_guild(&self).await
     }
-}
\ No newline at end of file
+    /// Gets the name, channels and roles of a guild the bot is in from the cache.
+    pub fn cached_guild(&self, guild_id: serenity::model::id::GuildId) -> Option<(String, Vec<serenity::model::channel::GuildChannel>, Vec<serenity::model::guild::Role>)> {
+        let guild = self.cache.guild(guild_id)?;
+        let mut channels = guild.channels.values().cloned().collect::<Vec<_>>();
+        channels.sort_by_key(|channel| (channel.position, channel.id));
+        let mut roles = guild.roles.values().cloned().collect::<Vec<_>>();
+        roles.sort_by_key(|role| std::cmp::Reverse(role.position));
+        Some((guild.name.clone(), channels, roles))
+    }
+}
@@ -2,6 +2,8 @@ mod client;
 mod twitch_client;
 mod rocket;
 mod discord_client;
+mod token_encryption;
+mod cli;
 
 use ::rocket::{Orbit, Rocket};
 
@@ -77,6 +79,10 @@ fn main() -> ::anyhow::Result<()>{
 #[tokio::main]
 async fn async_main() -> ::anyhow::Result<()>{
     let _a = get_db().await;
+    token_encryption::init().await?;
+    if let Some(result) = cli::run(&std::env::args().skip(1).collect::<Vec<_>>()).await {
+        return result;
+    }
 
     let shutdown_watcher = tokio::spawn(async{
         let mut js = tokio::task::JoinSet::new();
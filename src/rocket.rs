@@ -4,6 +4,7 @@ pub(self) mod csrf;
 pub(self) mod base64;
 mod index;
 mod discord;
+mod dashboard;
 pub mod auth;
 
 pub(crate) const BASE_SCHEME: &'static str = "https";
@@ -22,9 +23,23 @@ pub(in super) async fn launch() -> anyhow::Result<(rocket::Rocket<rocket::Build>
             twitch::oauth::new::new_oauth,
             twitch::oauth::ok::oauth_ok,
             twitch::oauth::err::oauth_err,
+            twitch::settings::settings,
+            twitch::settings::channels,
+            twitch::settings::live_message,
             discord::oauth::new::new_oauth,
             discord::oauth::ok::oauth_ok,
             discord::oauth::err::oauth_err,
+            discord::oauth::logout::logout,
+            dashboard::guilds::guilds,
+            dashboard::guilds::guild,
+            dashboard::forms::temp_channels,
+            dashboard::forms::remove_temp_channels,
+            dashboard::forms::xp,
+            dashboard::forms::xp_ignored_channels,
+            dashboard::forms::add_reaction_role,
+            dashboard::forms::remove_reaction_role,
+            dashboard::forms::add_role_limit,
+            dashboard::forms::remove_role_limit,
         ])
     ;
 
@@ -28,28 +28,59 @@ impl<'a, 'b: 'a> rocket::response::Responder<'a, 'b> for NoAuth {
 }
 #[non_exhaustive]
 pub struct Auth {
-    pub(super) csrf_tokens: scc::HashIndex<[u8; CSRF_TOKEN_LENGTH], std::time::Instant>,
+    csrf_tokens: scc::HashIndex<[u8; CSRF_TOKEN_LENGTH], super::csrf::CsrfTokenInfo>,
+    /// Requests without a known IP share the `None` entry.
+    csrf_tokens_per_ip: scc::HashMap<Option<std::net::IpAddr>, usize>,
+    /// Number of entries in `csrf_tokens`
+    csrf_token_count: std::sync::atomic::AtomicUsize,
     pub twitch: crate::twitch_client::Twitch,
     pub discord: discord::Discord,
+    pub(crate) discord_client: crate::discord_client::DiscordClient,
 }
 impl Auth {
     pub(super) async fn new(rocket: rocket::Rocket<rocket::Build>) -> ::anyhow::Result<(rocket::Rocket<rocket::Build>, Arc<Self>, serenity::Client, (tokio::task::JoinHandle<()>, tokio::sync::oneshot::Sender<()>))> {
         let (rocket, twitch) = crate::twitch_client::create_twitch_client(rocket).await?;
+        let discord = crate::client::init_client().await?;
         let slf = Arc::new(Self {
             csrf_tokens: scc::HashIndex::new(),
+            csrf_tokens_per_ip: scc::HashMap::new(),
+            csrf_token_count: std::sync::atomic::AtomicUsize::new(0),
             twitch,
             discord: discord::Discord::new().await?,
+            discord_client: crate::discord_client::DiscordClient::new(&discord),
         });
-        let discord = crate::client::init_client().await?;
         let rocket = rocket.manage(slf.clone());
         let refresh = refresh_tokens(slf.clone());
+        super::csrf::sweep_csrf_tokens(slf.clone());
         Ok((rocket, slf, discord, refresh))
     }
-    pub async fn get_new_csrf(&self) -> String {
-        let time = std::time::Instant::now();
+    pub async fn get_new_csrf(&self, ip: Option<std::net::IpAddr>) -> Result<String, super::csrf::TooManyCsrfTokens> {
+        use std::sync::atomic::Ordering;
+        if self.csrf_token_count.fetch_add(1, Ordering::AcqRel) >= super::csrf::MAX_CSRF_TOKENS {
+            self.csrf_token_count.fetch_sub(1, Ordering::AcqRel);
+            tracing::warn!("Reached the limit of {} unused CSRF Tokens", super::csrf::MAX_CSRF_TOKENS);
+            return Err(super::csrf::TooManyCsrfTokens::default());
+        }
+        {
+            let mut count = self.csrf_tokens_per_ip.entry_async(ip).await.or_insert(0);
+            if *count >= super::csrf::MAX_CSRF_TOKENS_PER_IP {
+                drop(count);
+                self.csrf_token_count.fetch_sub(1, Ordering::AcqRel);
+                match ip {
+                    Some(ip) => tracing::warn!(target: crate::FAIL2BAN_TARGET, "{ip} exceeded the limit of {} unused CSRF Tokens", super::csrf::MAX_CSRF_TOKENS_PER_IP),
+                    None => tracing::warn!("Requests without a known IP exceeded the limit of {} unused CSRF Tokens", super::csrf::MAX_CSRF_TOKENS_PER_IP),
+                }
+                return Err(super::csrf::TooManyCsrfTokens::default());
+            }
+            *count += 1;
+        }
+        let info = super::csrf::CsrfTokenInfo {
+            created: std::time::Instant::now(),
+            ip,
+        };
         let mut csrf:[u8; CSRF_TOKEN_LENGTH] = rand::random();
         loop {
-            match self.csrf_tokens.insert_async(csrf, time).await{
+            match self.csrf_tokens.insert_async(csrf, info).await{
                 Ok(_) => break,
                 Err(_) => {
                     //Collision?, generate a new token
@@ -57,7 +88,31 @@ impl Auth {
                 }
             }
         }
-        base64::engine::general_purpose::URL_SAFE.encode(&csrf)
+        Ok(base64::engine::general_purpose::URL_SAFE.encode(&csrf))
+    }
+    /// Removes the CSRF Token and returns, when and for whom it was created.
+    pub(super) async fn remove_csrf(&self, csrf: &[u8]) -> Option<super::csrf::CsrfTokenInfo> {
+        let entry = self.csrf_tokens.get_async(csrf).await?;
+        let info = *entry.get();
+        entry.remove_entry();
+        self.csrf_token_count.fetch_sub(1, std::sync::atomic::Ordering::AcqRel);
+        self.csrf_tokens_per_ip.remove_if_async(&info.ip, |count| {
+            *count = count.saturating_sub(1);
+            *count == 0
+        }).await;
+        Some(info)
+    }
+    pub(super) async fn remove_expired_csrf(&self) {
+        let expired = self.csrf_tokens.iter(&sdd::Guard::new())
+            .filter(|(_, info)| info.created.elapsed() > super::csrf::CSRF_TOKEN_TTL)
+            .map(|(csrf, _)| *csrf)
+            .collect::<Vec<_>>();
+        for csrf in &expired {
+            self.remove_csrf(csrf).await;
+        }
+        if !expired.is_empty() {
+            tracing::debug!("Removed {} expired CSRF Tokens", expired.len());
+        }
     }
 }
 
@@ -82,16 +137,15 @@ fn refresh_tokens(auth: std::sync::Arc<Auth>) -> (tokio::task::JoinHandle<()>, t
         let auth = auth;
         let loop_work = ||async {
             tracing::info!("Refreshing Twitch Tokens");
-            let mut token = auth.twitch.auth.authentications.first_entry_async().await;
-            while let Some(mut entry) = token {
-                let user_id = entry.key().clone();
-                let valid = !entry.check_valid(&auth.twitch).await;
-                token = entry.next_async().await;
-                if !valid {
-                    auth.twitch.auth.authentications.remove_async(&user_id).await;
+            for mut token in auth.twitch.auth.all().await {
+                if token.check_valid(&auth.twitch).await {
+                    auth.twitch.auth.upsert(&token).await;
+                } else {
+                    auth.twitch.auth.remove(&token.user_id).await;
                 }
             }
             tracing::info!("Done Refreshing Twitch Tokens");
+            auth.discord.auth.remove_expired().await;
         };
         loop {
             tokio::select! {
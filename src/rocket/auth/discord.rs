@@ -13,7 +13,7 @@ pub struct Discord {
 
 impl Discord {
     pub async fn new() -> ::anyhow::Result<Self> {
-        let auth = DiscordAuthentications::load().await;
+        let auth = DiscordAuthentications;
         //Get Twitch Client ID and Secret from .env
         let client_id = ::std::env::var("DISCORD_CLIENT_ID")?;
         let client_secret = ::std::env::var("DISCORD_CLIENT_SECRET")?;
@@ -27,50 +27,96 @@ impl Discord {
     }
 }
 const TOKEN_LENGTH: usize = 32;
-#[derive(Debug, Default, serde::Deserialize, serde::Serialize)]
+/// Sessions, that weren't used for this many days, are expired. This matches the expiry of the session cookie.
+pub(crate) const SESSION_EXPIRY_DAYS: i32 = 7;
+/// Access to the Discord web sessions stored in the database.
+#[derive(Debug, Default)]
 #[non_exhaustive]
-pub struct DiscordAuthentications {
-    pub(in super::super) tokens: scc::HashMap<[u8;TOKEN_LENGTH], token::Token>
-}
-pub(crate) const DISCORD_AUTH_PATH: &str = "discord_authentications.json";
+pub struct DiscordAuthentications;
 impl DiscordAuthentications{
+    pub(crate) async fn insert_new_token(&self, token: &token::Token) -> ::anyhow::Result<[u8;TOKEN_LENGTH]> {
+        let access_token = crate::token_encryption::encrypt(&token.access_token)?;
+        let refresh_token = crate::token_encryption::encrypt(&token.refresh_token)?;
+        let db = crate::get_db().await;
+        loop {
+            let key:[u8;TOKEN_LENGTH] = rand::random();
+            let inserted = sqlx::query!(
+                "INSERT INTO discord_sessions (session_id, access_token, refresh_token, scope, token_created, expires_in) VALUES ($1, $2, $3, $4, $5, $6) ON CONFLICT DO NOTHING",
+                key.as_slice(), access_token, refresh_token, token.scope, token.created as _, i64::try_from(token.expires_in).unwrap_or(i64::MAX)
+            ).execute(&db).await?;
+            //Collision?, generate a new key
+            if inserted.rows_affected() != 0 {
+                return Ok(key);
+            }
+        }
+    }
 
-    async fn load() -> Self {
-        match tokio::fs::read_to_string(DISCORD_AUTH_PATH).await {
-            Ok(v) => serde_json::from_str(v.as_str()).unwrap_or_else(|err| {
-                tracing::info!("Failed to parse Discord Authentications: {err}");
-                Default::default()
-            }),
+    /// Gets the token of a session, that hasn't expired yet, and marks the session as used.
+    pub(crate) async fn get_token(&self, session: &[u8]) -> Option<token::Token> {
+        let db = crate::get_db().await;
+        match sqlx::query!(
+            r#"UPDATE discord_sessions SET last_used = now() WHERE session_id = $1 AND last_used > now() - make_interval(days => $2) RETURNING access_token, refresh_token, scope, token_created as "token_created: chrono::DateTime<chrono::Utc>", expires_in"#,
+            session, SESSION_EXPIRY_DAYS
+        ).fetch_optional(&db).await {
+            Ok(v) => v.and_then(|v| token::Token::from_row(v.access_token, v.refresh_token, v.scope, v.token_created, v.expires_in)
+                .inspect_err(|err| tracing::error!("Failed to decrypt Discord session: {err}"))
+                .ok()),
             Err(err) => {
-                tracing::info!("Failed to read Discord Authentications file: {err}");
-                Default::default()
+                tracing::error!("Failed to get Discord session: {err}");
+                None
             }
         }
     }
-    pub(crate) async fn save(&self) -> ::anyhow::Result<()> {
-        let auth = serde_json::to_string(&self)?;
-        match tokio::fs::write(DISCORD_AUTH_PATH, auth).await {
-            Ok(()) => {
-                tracing::info!("Saved Discord Authentications");
-            },
+
+    pub(crate) async fn update_token(&self, session: &[u8], token: &token::Token) {
+        let (access_token, refresh_token) = match (crate::token_encryption::encrypt(&token.access_token), crate::token_encryption::encrypt(&token.refresh_token)) {
+            (Ok(access_token), Ok(refresh_token)) => (access_token, refresh_token),
+            (Err(err), _) | (_, Err(err)) => {
+                tracing::error!("Failed to encrypt Discord session: {err}");
+                return;
+            }
+        };
+        let db = crate::get_db().await;
+        match sqlx::query!(
+            "UPDATE discord_sessions SET access_token = $2, refresh_token = $3, scope = $4, token_created = $5, expires_in = $6 WHERE session_id = $1",
+            session, access_token, refresh_token, token.scope, token.created as _, i64::try_from(token.expires_in).unwrap_or(i64::MAX)
+        ).execute(&db).await {
+            Ok(_) => {},
             Err(err) => {
-                tracing::error!("Failed to save Discord Authentications: {err}");
+                tracing::error!("Failed to update Discord session: {err}");
             }
         }
-        Ok(())
     }
 
-    pub(crate) async fn insert_new_token(&self, mut token: token::Token) -> [u8;TOKEN_LENGTH] {
-        let mut key:[u8;TOKEN_LENGTH] = rand::random();
-        loop {
-            match self.tokens.insert_async(key, token).await {
-                Ok(()) => return key,
-                Err((_, token1)) => {
-                    token = token1;
-                    key = rand::random();
-                }
+    /// Removes the session and returns its token.
+    pub(crate) async fn remove(&self, session: &[u8]) -> Option<token::Token> {
+        let db = crate::get_db().await;
+        match sqlx::query!(
+            r#"DELETE FROM discord_sessions WHERE session_id = $1 RETURNING access_token, refresh_token, scope, token_created as "token_created: chrono::DateTime<chrono::Utc>", expires_in"#,
+            session
+        ).fetch_optional(&db).await {
+            Ok(v) => v.and_then(|v| token::Token::from_row(v.access_token, v.refresh_token, v.scope, v.token_created, v.expires_in)
+                .inspect_err(|err| tracing::error!("Failed to decrypt removed Discord session: {err}"))
+                .ok()),
+            Err(err) => {
+                tracing::error!("Failed to remove Discord session: {err}");
+                None
             }
         }
     }
-}
 
+    pub(crate) async fn remove_expired(&self) {
+        let db = crate::get_db().await;
+        match sqlx::query!(
+            "DELETE FROM discord_sessions WHERE last_used <= now() - make_interval(days => $1)",
+            SESSION_EXPIRY_DAYS
+        ).execute(&db).await {
+            Ok(v) => {
+                tracing::info!("Removed {} expired Discord sessions", v.rows_affected());
+            },
+            Err(err) => {
+                tracing::error!("Failed to remove expired Discord sessions: {err}");
+            }
+        }
+    }
+}
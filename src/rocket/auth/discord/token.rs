@@ -29,6 +29,19 @@ pub struct Token {
     pub scope: String,
 }
 
+impl Token {
+    pub(crate) fn from_row(access_token: String, refresh_token: String, scope: String, created: chrono::DateTime<chrono::Utc>, expires_in: i64) -> ::anyhow::Result<Self> {
+        Ok(Self {
+            access_token: crate::token_encryption::decrypt(&access_token)?,
+            token_type: TokenType::Bearer,
+            created,
+            expires_in: u64::try_from(expires_in).unwrap_or(0),
+            refresh_token: crate::token_encryption::decrypt(&refresh_token)?,
+            scope,
+        })
+    }
+}
+
 impl<'a> TokenRequest<'a> {
     pub const fn authorization_code(code: &'a str, redirect_uri: &'a str) -> Self {
         Self::AuthorizationCode {
@@ -10,6 +10,68 @@ pub struct State;
 impl QueryParameter for State{
     const PARAMETER: &'static str = "state";
 }
+/// CSRF Token passed to form submissions of the dashboard.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Form;
+impl QueryParameter for Form{
+    const PARAMETER: &'static str = "csrf";
+}
+/// How long a CSRF Token stays valid after it was handed out.
+pub(in crate::rocket) const CSRF_TOKEN_TTL: std::time::Duration = std::time::Duration::from_secs(10*60);
+/// How many unused CSRF Tokens a single IP may hold at once.
+pub(in crate::rocket) const MAX_CSRF_TOKENS_PER_IP: usize = 16;
+/// How many unused CSRF Tokens may exist at once across all IPs.
+pub(in crate::rocket) const MAX_CSRF_TOKENS: usize = 4096;
+/// How often expired CSRF Tokens are removed.
+const CSRF_SWEEP_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+#[derive(Debug, Copy, Clone)]
+pub(in crate::rocket) struct CsrfTokenInfo {
+    pub created: std::time::Instant,
+    pub ip: Option<std::net::IpAddr>,
+}
+
+#[derive(rocket::response::Responder, Debug)]
+#[response(status = 429)]
+pub struct TooManyCsrfTokens(rocket::response::content::RawHtml<&'static str>);
+impl Default for TooManyCsrfTokens {
+    fn default() -> Self {
+        Self(rocket::response::content::RawHtml(r#"
+<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta charset="utf-8">
+        <meta name="color-scheme" content="light dark">
+        <title>Too Many Requests</title>
+    </head>
+    <body>
+        <h1>Too Many Requests</h1>
+        <p>Too many logins were started from your IP. Please finish one of them or try again later.</p>
+    </body>
+</html>
+"#))
+    }
+}
+
+/// Periodically removes expired CSRF Tokens, until the bot shuts down.
+pub(in crate::rocket) fn sweep_csrf_tokens(auth: Arc<crate::rocket::auth::Auth>) -> tokio::task::JoinHandle<()> {
+    let mut interval = tokio::time::interval(CSRF_SWEEP_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    tokio::spawn(async move {
+        let shutdown = crate::SHUTDOWN.notified();
+        tokio::pin!(shutdown);
+        loop {
+            tokio::select! {
+                biased;
+                _ = &mut shutdown => break,
+                _ = interval.tick() => {
+                    auth.remove_expired_csrf().await;
+                }
+            }
+        }
+    })
+}
+
 #[derive(rocket::form::FromForm, Debug)]
 pub(in crate::rocket) struct CsrfToken<P:QueryParameter>{
     pub token: Vec<u8>,
@@ -79,9 +141,24 @@ impl<'a, P:QueryParameter> rocket::request::FromRequest<'a> for CsrfToken<P> {
             "#.into())))),
         };
 
-        match auth.csrf_tokens.get_async(token.data.as_slice()).await {
-            Some(entry) => {
-                entry.remove_entry();
+        match auth.remove_csrf(token.data.as_slice()).await {
+            Some(info) if info.created.elapsed() > CSRF_TOKEN_TTL => {
+                Outcome::Error((rocket::http::Status::Unauthorized, CsrfTokenError::Err(rocket::response::content::RawHtml(r#"
+<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta charset="utf-8">
+        <meta name="color-scheme" content="light dark">
+        <title>Expired CSRF Token</title>
+    </head>
+    <body>
+        <h1>Expired CSRF Token</h1>
+        <p>The provided CSRF Token has expired. Please try again.</p>
+    </body>
+</html>
+            "#.into()))))
+            }
+            Some(_) => {
                 Outcome::Success(Self{
                     token: token.data,
                     _parameter: std::marker::PhantomData,
@@ -0,0 +1,107 @@
+pub mod guilds;
+pub mod forms;
+
+use std::sync::Arc;
+use rocket::Request;
+use rocket::request::Outcome;
+use crate::rocket::auth::NoAuth;
+
+/// A guild, that the logged-in Discord user may manage and that the bot is in.
+#[non_exhaustive]
+pub struct ManagedGuild {
+    pub(crate) name: String,
+    pub(crate) channels: Vec<serenity::model::channel::GuildChannel>,
+    pub(crate) roles: Vec<serenity::model::guild::Role>,
+}
+
+#[rocket::async_trait]
+impl<'a> rocket::request::FromRequest<'a> for ManagedGuild {
+    type Error = NoAuth;
+
+    async fn from_request(request: &'a Request<'_>) -> Outcome<Self, Self::Error> {
+        //All dashboard routes for a guild start with `/guilds/<guild_id>`
+        let guild_id = match request.param::<u64>(1) {
+            Some(Ok(v)) if v != 0 => serenity::model::id::GuildId::new(v),
+            _ => return Outcome::Forward(rocket::http::Status::NotFound),
+        };
+        let session = match request.guard::<super::discord::oauth::session::Session>().await {
+            Outcome::Success(v) => v,
+            Outcome::Forward(status) => return Outcome::Forward(status),
+            Outcome::Error((status, _)) => return Outcome::Forward(status),
+        };
+        let auth: &Arc<crate::rocket::auth::Auth> = match request.rocket().state() {
+            Some(v) => v,
+            None => return Outcome::Error((rocket::http::Status::InternalServerError, NoAuth)),
+        };
+        match user_guilds(&session).await {
+            Ok(guilds) if guilds.iter().any(|guild| guild.id == guild_id && can_manage(guild)) => {},
+            Ok(_) => {
+                tracing::info!("{} tried to manage guild {guild_id} without permission", session.current_user.id);
+                return Outcome::Forward(rocket::http::Status::Forbidden);
+            },
+            Err(err) => {
+                tracing::warn!("Failed to get the guilds of {}: {err}", session.current_user.id);
+                return Outcome::Forward(rocket::http::Status::BadGateway);
+            }
+        }
+        match auth.discord_client.cached_guild(guild_id) {
+            Some((name, channels, roles)) => Outcome::Success(Self {
+                name,
+                channels,
+                roles,
+            }),
+            None => Outcome::Forward(rocket::http::Status::NotFound),
+        }
+    }
+}
+
+/// Gets the guilds of the logged-in Discord user, including their permissions in each guild.
+async fn user_guilds(session: &super::discord::oauth::session::Session) -> Result<Vec<serenity::model::guild::GuildInfo>, serenity::Error> {
+    let http = serenity::http::Http::new(format!("Bearer {}", session.auth.access_token).as_str());
+    http.get_guilds(None, None).await
+}
+
+/// Mirrors the `MANAGE_GUILD` requirement of the `/settings` slash commands.
+fn can_manage(guild: &serenity::model::guild::GuildInfo) -> bool {
+    guild.owner || guild.permissions.administrator() || guild.permissions.manage_guild()
+}
+
+/// Names of guilds, channels and roles are user controlled, so they need escaping before ending up in html.
+pub(super) fn escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+pub(super) fn page(title: &str, body: &str) -> rocket::response::content::RawHtml<String> {
+    let title = escape(title);
+    rocket::response::content::RawHtml(format!(r#"
+<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta charset="utf-8">
+        <meta name="color-scheme" content="light dark">
+        <title>{title}</title>
+    </head>
+    <body>
+        <h1>{title}</h1>
+{body}
+    </body>
+</html>
+"#))
+}
+
+/// Converts an interval into seconds, assuming 30 days per month (like `client::migrate`).
+fn interval_seconds(interval: &sqlx::postgres::types::PgInterval) -> f64 {
+    const SECONDS_PER_DAY: f64 = 24.*60.*60.;
+    (f64::from(interval.months) * 30. + f64::from(interval.days)) * SECONDS_PER_DAY + interval.microseconds as f64 / 1_000_000.
+}
@@ -0,0 +1,328 @@
+use super::{page, ManagedGuild};
+use serenity::http::CacheHttp;
+use crate::rocket::csrf;
+
+#[derive(rocket::response::Responder)]
+pub enum Responder {
+    //Boxed, because a Redirect is much larger than the other variants
+    Ok(Box<rocket::response::Redirect>),
+    CsrfError(csrf::CsrfTokenError),
+    Err((rocket::http::Status, rocket::response::content::RawHtml<String>)),
+}
+impl Responder {
+    fn saved(guild_id: u64) -> Self {
+        Self::Ok(Box::new(rocket::response::Redirect::to(format!("/guilds/{guild_id}"))))
+    }
+    fn bad_request(message: &str) -> Self {
+        Self::Err((rocket::http::Status::BadRequest, page("Invalid Input", &format!("        <p>{}</p>", super::escape(message)))))
+    }
+    fn internal_error(err: impl std::fmt::Display) -> Self {
+        tracing::error!("Failed to save dashboard settings: {err}");
+        Self::Err((rocket::http::Status::InternalServerError, page("Error", "        <p>Failed to save the settings.</p>")))
+    }
+}
+
+impl ManagedGuild {
+    fn has_channel(&self, channel: u64, kinds: &[serenity::model::channel::ChannelType]) -> bool {
+        self.channels.iter().any(|v| v.id.get() == channel && (kinds.is_empty() || kinds.contains(&v.kind)))
+    }
+    fn has_role(&self, role: u64) -> bool {
+        self.roles.iter().any(|v| v.id.get() == role)
+    }
+}
+
+/// Converts seconds entered in a form into an interval. Rejects negative or absurdly large values.
+fn seconds_interval(seconds: f64) -> Option<sqlx::postgres::types::PgInterval> {
+    //About 31 years, which is way more than anyone should ever need here.
+    const MAX_SECONDS: f64 = 1e9;
+    if !seconds.is_finite() || !(0. ..=MAX_SECONDS).contains(&seconds) {
+        return None;
+    }
+    Some(sqlx::postgres::types::PgInterval {
+        months: 0,
+        days: 0,
+        microseconds: (seconds * 1_000_000.).round() as i64,
+    })
+}
+
+async fn ensure_guild(db: &sqlx::PgPool, guild_id: i64) -> Result<(), sqlx::Error> {
+    sqlx::query!("INSERT INTO guilds (guild_id) VALUES ($1) ON CONFLICT DO NOTHING", guild_id).execute(db).await?;
+    Ok(())
+}
+
+#[derive(rocket::form::FromFormField, Debug, Copy, Clone)]
+pub enum Action {
+    Add,
+    Remove,
+}
+
+#[derive(rocket::form::FromForm, Debug)]
+pub struct TempChannels {
+    creator_channel: u64,
+    create_category: Option<u64>,
+    delete_delay: f64,
+    delete_non_created_channels: bool,
+}
+
+#[rocket::post("/guilds/<guild_id>/temp_channels", data = "<form>")]
+pub async fn temp_channels(guild_id: u64, guild: ManagedGuild, csrf: Result<csrf::CsrfToken<csrf::Form>, csrf::CsrfTokenError>, form: rocket::form::Form<TempChannels>) -> Responder {
+    if let Err(err) = csrf {
+        return Responder::CsrfError(err);
+    }
+    use serenity::model::channel::ChannelType;
+    let db = crate::get_db().await;
+    let guild_db = crate::converti(guild_id);
+    if !guild.has_channel(form.creator_channel, &[ChannelType::Voice]) {
+        return Responder::bad_request("The creator channel is not a voice channel of this guild.");
+    }
+    let creator_channel = crate::converti(form.creator_channel);
+    let create_category = match form.create_category {
+        Some(v) if guild.has_channel(v, &[ChannelType::Category]) => Some(crate::converti(v)),
+        Some(_) => return Responder::bad_request("The category is not a category of this guild."),
+        None => None,
+    };
+    let delete_delay = match seconds_interval(form.delete_delay) {
+        Some(v) => v,
+        None => return Responder::bad_request("The delete delay must be a positive number of seconds."),
+    };
+    if let Err(err) = ensure_guild(&db, guild_db).await {
+        return Responder::internal_error(err);
+    }
+    match sqlx::query!(
+        "INSERT INTO temp_channels (guild_id, creator_channel, create_category, delete_delay, delete_non_created_channels) VALUES ($1, $2, $3, $4, $5)
+ON CONFLICT (guild_id, creator_channel) DO UPDATE SET create_category = $3, delete_delay = $4, delete_non_created_channels = $5",
+        guild_db, creator_channel, create_category, delete_delay, form.delete_non_created_channels
+    ).execute(&db).await {
+        Ok(_) => Responder::saved(guild_id),
+        Err(err) => Responder::internal_error(err),
+    }
+}
+
+#[derive(rocket::form::FromForm, Debug)]
+pub struct RemoveTempChannels {
+    creator_channel: u64,
+}
+
+#[rocket::post("/guilds/<guild_id>/temp_channels/remove", data = "<form>")]
+pub async fn remove_temp_channels(guild_id: u64, _guild: ManagedGuild, csrf: Result<csrf::CsrfToken<csrf::Form>, csrf::CsrfTokenError>, form: rocket::form::Form<RemoveTempChannels>) -> Responder {
+    if let Err(err) = csrf {
+        return Responder::CsrfError(err);
+    }
+    let db = crate::get_db().await;
+    match sqlx::query!("DELETE FROM temp_channels WHERE guild_id = $1 AND creator_channel = $2", crate::converti(guild_id), crate::converti(form.creator_channel)).execute(&db).await {
+        Ok(_) => Responder::saved(guild_id),
+        Err(err) => Responder::internal_error(err),
+    }
+}
+
+#[derive(rocket::form::FromForm, Debug)]
+pub struct Xp {
+    txt_apply_interval: f64,
+    txt_punish_interval: f64,
+}
+
+#[rocket::post("/guilds/<guild_id>/xp", data = "<form>")]
+pub async fn xp(guild_id: u64, _guild: ManagedGuild, csrf: Result<csrf::CsrfToken<csrf::Form>, csrf::CsrfTokenError>, form: rocket::form::Form<Xp>) -> Responder {
+    if let Err(err) = csrf {
+        return Responder::CsrfError(err);
+    }
+    let (Some(apply), Some(punish)) = (seconds_interval(form.txt_apply_interval), seconds_interval(form.txt_punish_interval)) else {
+        return Responder::bad_request("The intervals must be positive numbers of seconds.");
+    };
+    //Text XP gets divided by the apply interval, so it can't be zero.
+    if apply.microseconds <= 0 {
+        return Responder::bad_request("The text XP apply interval must be longer than zero seconds.");
+    }
+    let db = crate::get_db().await;
+    let guild_db = crate::converti(guild_id);
+    if let Err(err) = ensure_guild(&db, guild_db).await {
+        return Responder::internal_error(err);
+    }
+    match sqlx::query!(
+        "INSERT INTO xp (guild_id, txt_apply_interval, txt_punish_interval) VALUES ($1, $2, $3) ON CONFLICT (guild_id) DO UPDATE SET txt_apply_interval = $2, txt_punish_interval = $3",
+        guild_db, apply, punish
+    ).execute(&db).await {
+        Ok(_) => Responder::saved(guild_id),
+        Err(err) => Responder::internal_error(err),
+    }
+}
+
+#[derive(rocket::form::FromForm, Debug)]
+pub struct IgnoredChannel {
+    action: Action,
+    channel: u64,
+}
+
+#[rocket::post("/guilds/<guild_id>/xp/ignored_channels", data = "<form>")]
+pub async fn xp_ignored_channels(guild_id: u64, guild: ManagedGuild, csrf: Result<csrf::CsrfToken<csrf::Form>, csrf::CsrfTokenError>, form: rocket::form::Form<IgnoredChannel>) -> Responder {
+    if let Err(err) = csrf {
+        return Responder::CsrfError(err);
+    }
+    let db = crate::get_db().await;
+    let guild_db = crate::converti(guild_id);
+    let channel = crate::converti(form.channel);
+    let result = match form.action {
+        Action::Add => {
+            if !guild.has_channel(form.channel, &[]) {
+                return Responder::bad_request("The channel is not part of this guild.");
+            }
+            if let Err(err) = ensure_guild(&db, guild_db).await {
+                return Responder::internal_error(err);
+            }
+            sqlx::query!("INSERT INTO xp_channels_ignored (guild_id, channel_id) VALUES ($1, $2) ON CONFLICT DO NOTHING", guild_db, channel).execute(&db).await
+        },
+        //Deleted channels can still be removed, so no check for the channel here.
+        Action::Remove => sqlx::query!("DELETE FROM xp_channels_ignored WHERE guild_id = $1 AND channel_id = $2", guild_db, channel).execute(&db).await,
+    };
+    match result {
+        Ok(_) => Responder::saved(guild_id),
+        Err(err) => Responder::internal_error(err),
+    }
+}
+
+#[derive(rocket::form::FromForm, Debug)]
+pub struct AddReactionRole {
+    channel: u64,
+    message: u64,
+    role: u64,
+    emoji: String,
+}
+
+#[rocket::post("/guilds/<guild_id>/reaction_roles/add", data = "<form>")]
+pub async fn add_reaction_role(guild_id: u64, guild: ManagedGuild, auth: Result<&crate::rocket::auth::Auth, crate::rocket::auth::NoAuth>, csrf: Result<csrf::CsrfToken<csrf::Form>, csrf::CsrfTokenError>, form: rocket::form::Form<AddReactionRole>) -> Responder {
+    if let Err(err) = csrf {
+        return Responder::CsrfError(err);
+    }
+    let auth = match auth {
+        Ok(v) => v,
+        Err(err) => return Responder::internal_error(format!("{err:?}")),
+    };
+    if !guild.has_channel(form.channel, &[]) {
+        return Responder::bad_request("The channel is not part of this guild.");
+    }
+    if !guild.has_role(form.role) {
+        return Responder::bad_request("The role is not part of this guild.");
+    }
+    if form.message == 0 || form.emoji.trim().is_empty() {
+        return Responder::bad_request("A message id and an emoji are required.");
+    }
+    let emoji = form.emoji.trim();
+    let emoji = match serenity::utils::parse_emoji(emoji) {
+        Some(v) => v.into(),
+        None => serenity::model::channel::ReactionType::Unicode(emoji.to_string()),
+    };
+    let channel = serenity::model::id::ChannelId::new(form.channel);
+    let message = serenity::model::id::MessageId::new(form.message);
+    if let Err(err) = channel.create_reaction(auth.discord_client.http(), message, emoji.clone()).await {
+        tracing::info!("Failed to react to message {message} in {channel} of guild {guild_id}: {err}");
+        return Responder::bad_request("Failed to react to the message. Check the message id and the emoji.");
+    }
+    let emoji = match serde_json::to_value(&emoji) {
+        Ok(v) => v,
+        Err(err) => return Responder::internal_error(err),
+    };
+    let db = crate::get_db().await;
+    match sqlx::query!(
+        "INSERT INTO public.role_reactions (guild_id, message_id, emoji, give_role_id, channel_id) VALUES ($1, $2, $3, $4, $5) ON CONFLICT DO NOTHING",
+        crate::converti(guild_id), crate::converti(form.message), emoji, crate::converti(form.role), crate::converti(form.channel)
+    ).execute(&db).await {
+        Ok(_) => Responder::saved(guild_id),
+        Err(err) => Responder::internal_error(err),
+    }
+}
+
+#[derive(rocket::form::FromForm, Debug)]
+pub struct RemoveReactionRole {
+    message: u64,
+    role: u64,
+}
+
+#[rocket::post("/guilds/<guild_id>/reaction_roles/remove", data = "<form>")]
+pub async fn remove_reaction_role(guild_id: u64, _guild: ManagedGuild, auth: Result<&crate::rocket::auth::Auth, crate::rocket::auth::NoAuth>, csrf: Result<csrf::CsrfToken<csrf::Form>, csrf::CsrfTokenError>, form: rocket::form::Form<RemoveReactionRole>) -> Responder {
+    if let Err(err) = csrf {
+        return Responder::CsrfError(err);
+    }
+    let auth = match auth {
+        Ok(v) => v,
+        Err(err) => return Responder::internal_error(format!("{err:?}")),
+    };
+    if form.message == 0 {
+        return Responder::bad_request("A message id is required.");
+    }
+    let db = crate::get_db().await;
+    let removed = match sqlx::query!(
+        "DELETE FROM public.role_reactions WHERE guild_id = $1 AND message_id = $2 AND give_role_id = $3 RETURNING emoji, channel_id",
+        crate::converti(guild_id), crate::converti(form.message), crate::converti(form.role)
+    ).fetch_all(&db).await {
+        Ok(v) => v,
+        Err(err) => return Responder::internal_error(err),
+    };
+    let message = serenity::model::id::MessageId::new(form.message);
+    for removed in removed {
+        let channel = serenity::model::id::ChannelId::new(crate::convertu(removed.channel_id));
+        let emoji = match serde_json::from_value::<serenity::model::channel::ReactionType>(removed.emoji) {
+            Ok(v) => v,
+            Err(err) => {
+                tracing::warn!("Invalid emoji stored for a reaction role on message {message}: {err}");
+                continue;
+            }
+        };
+        if let Err(err) = channel.delete_reaction(auth.discord_client.http(), message, None, emoji).await {
+            tracing::info!("Failed to remove the reaction of the bot from message {message} in {channel}: {err}");
+        }
+    }
+    Responder::saved(guild_id)
+}
+
+#[derive(rocket::form::FromForm, Debug)]
+pub struct AddRoleLimit {
+    role: u64,
+    bind_roles: String,
+}
+
+#[rocket::post("/guilds/<guild_id>/role_limiter/add", data = "<form>")]
+pub async fn add_role_limit(guild_id: u64, guild: ManagedGuild, csrf: Result<csrf::CsrfToken<csrf::Form>, csrf::CsrfTokenError>, form: rocket::form::Form<AddRoleLimit>) -> Responder {
+    if let Err(err) = csrf {
+        return Responder::CsrfError(err);
+    }
+    if !guild.has_role(form.role) {
+        return Responder::bad_request("The role is not part of this guild.");
+    }
+    let bind_roles = match crate::client::role_limiter::BindRoles::parse(&form.bind_roles) {
+        Ok(v) => v,
+        Err(err) => return Responder::bad_request(&format!("Invalid formula: {err}")),
+    };
+    let bind_roles = match serde_json::to_value(&bind_roles) {
+        Ok(v) => v,
+        Err(err) => return Responder::internal_error(err),
+    };
+    let db = crate::get_db().await;
+    match sqlx::query!(
+        r#"INSERT INTO public.role_limiter (guild_id, role_id, bind_roles) VALUES ($1, $2, (SELECT bind from jsonb_to_record($3) as t(bind reaction_role_InnerBoolFormula[]) ))
+ON CONFLICT (guild_id, role_id) DO UPDATE SET bind_roles = EXCLUDED.bind_roles"#,
+        crate::converti(guild_id), crate::converti(form.role), bind_roles
+    ).execute(&db).await {
+        Ok(_) => Responder::saved(guild_id),
+        Err(err) => Responder::internal_error(err),
+    }
+}
+
+#[derive(rocket::form::FromForm, Debug)]
+pub struct RemoveRoleLimit {
+    role: u64,
+}
+
+#[rocket::post("/guilds/<guild_id>/role_limiter/remove", data = "<form>")]
+pub async fn remove_role_limit(guild_id: u64, _guild: ManagedGuild, csrf: Result<csrf::CsrfToken<csrf::Form>, csrf::CsrfTokenError>, form: rocket::form::Form<RemoveRoleLimit>) -> Responder {
+    if let Err(err) = csrf {
+        return Responder::CsrfError(err);
+    }
+    let db = crate::get_db().await;
+    match sqlx::query!(
+        "DELETE FROM public.role_limiter WHERE guild_id = $1 AND role_id = $2",
+        crate::converti(guild_id), crate::converti(form.role)
+    ).execute(&db).await {
+        Ok(_) => Responder::saved(guild_id),
+        Err(err) => Responder::internal_error(err),
+    }
+}
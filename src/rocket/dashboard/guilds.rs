@@ -0,0 +1,244 @@
+use std::fmt::Write;
+use super::{escape, interval_seconds, page, ManagedGuild};
+
+#[derive(rocket::response::Responder)]
+pub enum Responder {
+    Ok(rocket::response::content::RawHtml<String>),
+    //Boxed, because a Redirect is much larger than the other variants
+    Redirect(Box<rocket::response::Redirect>),
+    NoAuth(crate::rocket::auth::NoAuth),
+    TooManyCsrfTokens(crate::rocket::csrf::TooManyCsrfTokens),
+    Err((rocket::http::Status, rocket::response::content::RawHtml<String>)),
+}
+
+/// Lists the guilds, that the logged-in Discord user can change the settings of.
+#[rocket::get("/guilds")]
+pub async fn guilds(session: Option<crate::rocket::discord::oauth::session::Session>, auth: Result<&crate::rocket::auth::Auth, crate::rocket::auth::NoAuth>) -> Responder {
+    let auth = match auth {
+        Ok(v) => v,
+        Err(err) => return Responder::NoAuth(err),
+    };
+    let session = match session {
+        Some(v) => v,
+        None => return Responder::Redirect(Box::new(rocket::response::Redirect::to(crate::rocket::auth::discord::NEW_OAUTH_URL))),
+    };
+    let guilds = match super::user_guilds(&session).await {
+        Ok(v) => v,
+        Err(err) => {
+            tracing::warn!("Failed to get the guilds of {}: {err}", session.current_user.id);
+            return Responder::Err((rocket::http::Status::BadGateway, page("Error", "<p>Failed to get your guilds from Discord.</p>")));
+        }
+    };
+    let mut list = String::new();
+    for guild in guilds.iter().filter(|guild| super::can_manage(guild)) {
+        if auth.discord_client.cached_guild(guild.id).is_none() {
+            continue;
+        }
+        let guild_id = guild.id;
+        let name = escape(&guild.name);
+        let image = guild.icon_url().map_or_else(String::new, |url| format!(r#"<img src="{}" alt="" width="32" height="32"> "#, escape(&url)));
+        let _ = writeln!(list, r#"            <li><a href="/guilds/{guild_id}">{image}{name}</a></li>"#);
+    }
+    if list.is_empty() {
+        return Responder::Ok(page("Guilds", "        <p>There are no guilds, where you can manage the server and this bot is a member.</p>"));
+    }
+    Responder::Ok(page("Guilds", &format!("        <ul>\n{list}        </ul>")))
+}
+
+/// Shows and allows changing the settings of a single guild.
+#[rocket::get("/guilds/<guild_id>")]
+pub async fn guild(guild_id: u64, guild: ManagedGuild, auth: Result<&crate::rocket::auth::Auth, crate::rocket::auth::NoAuth>, ip: Option<std::net::IpAddr>) -> Responder {
+    let auth = match auth {
+        Ok(v) => v,
+        Err(err) => return Responder::NoAuth(err),
+    };
+    //Only one form can be submitted per page load, so all forms share one CSRF Token.
+    let csrf = match auth.get_new_csrf(ip).await {
+        Ok(v) => v,
+        Err(err) => return Responder::TooManyCsrfTokens(err),
+    };
+    match render_guild(guild_id, &guild, &csrf).await {
+        Ok(v) => Responder::Ok(v),
+        Err(err) => {
+            tracing::error!("Failed to render the settings of guild {guild_id}: {err}");
+            Responder::Err((rocket::http::Status::InternalServerError, page("Error", "<p>Failed to load the settings of this guild.</p>")))
+        }
+    }
+}
+
+async fn render_guild(guild_id: u64, guild: &ManagedGuild, csrf: &str) -> Result<rocket::response::content::RawHtml<String>, sqlx::Error> {
+    let db = crate::get_db().await;
+    let guild_db = crate::converti(guild_id);
+    let action = |path: &str| format!("/guilds/{guild_id}/{path}?csrf={csrf}");
+    let channel_name = |channel: i64| {
+        let channel = crate::convertu(channel);
+        guild.channels.iter()
+            .find(|v| v.id.get() == channel)
+            .map_or_else(|| format!("Unknown channel {channel}"), |v| format!("#{}", escape(&v.name)))
+    };
+    let role_name = |role: i64| {
+        let role = crate::convertu(role);
+        guild.roles.iter()
+            .find(|v| v.id.get() == role)
+            .map_or_else(|| format!("Unknown role {role}"), |v| format!("@{}", escape(&v.name)))
+    };
+    let channel_options = |kinds: &[serenity::model::channel::ChannelType], selected: Option<i64>| {
+        let mut out = String::new();
+        for channel in guild.channels.iter().filter(|v| kinds.contains(&v.kind)) {
+            let id = channel.id.get();
+            let selected = if selected.map(crate::convertu) == Some(id) { " selected" } else { "" };
+            let _ = write!(out, r#"<option value="{id}"{selected}>{}</option>"#, escape(&channel.name));
+        }
+        out
+    };
+    let role_options = || {
+        let mut out = String::new();
+        for role in &guild.roles {
+            let _ = write!(out, r#"<option value="{}">{}</option>"#, role.id.get(), escape(&role.name));
+        }
+        out
+    };
+    use serenity::model::channel::ChannelType;
+    let mut body = String::new();
+
+    //Temporary Channels
+    {
+        let creators = sqlx::query!("SELECT creator_channel, create_category, delete_delay, delete_non_created_channels FROM temp_channels WHERE guild_id = $1 ORDER BY creator_channel", guild_db)
+            .fetch_all(&db).await?;
+        let mut list = String::new();
+        for creator in creators {
+            let category_options = channel_options(&[ChannelType::Category], creator.create_category);
+            let checked = if creator.delete_non_created_channels { " checked" } else { "" };
+            let _ = write!(list, r#"
+            <li>{name}
+                <form method="post" action="{action}">
+                    <input type="hidden" name="creator_channel" value="{id}">
+                    <label>Category: <select name="create_category"><option value="">No category</option>{category_options}</select></label><br>
+                    <label>Delete delay (seconds): <input name="delete_delay" type="number" min="0" step="0.001" value="{delete_delay}"></label><br>
+                    <label>Delete non-created channels: <input name="delete_non_created_channels" type="checkbox"{checked}></label><br>
+                    <button type="submit">Save</button>
+                </form>
+                <form method="post" action="{remove_action}"><input type="hidden" name="creator_channel" value="{id}"><button type="submit">Remove</button></form>
+            </li>"#,
+                name = channel_name(creator.creator_channel), id = crate::convertu(creator.creator_channel), delete_delay = interval_seconds(&creator.delete_delay),
+                action = action("temp_channels"), remove_action = action("temp_channels/remove"));
+        }
+        let creator_options = channel_options(&[ChannelType::Voice], None);
+        let category_options = channel_options(&[ChannelType::Category], None);
+        let _ = write!(body, r#"
+        <h2>Temporary Channels</h2>
+        <ul>{list}
+        </ul>
+        <h3>Add a creator channel</h3>
+        <form method="post" action="{action}">
+            <label>Creator channel: <select name="creator_channel">{creator_options}</select></label><br>
+            <label>Category: <select name="create_category"><option value="">No category</option>{category_options}</select></label><br>
+            <label>Delete delay (seconds): <input name="delete_delay" type="number" min="0" step="0.001" value="15"></label><br>
+            <label>Delete non-created channels: <input name="delete_non_created_channels" type="checkbox"></label><br>
+            <button type="submit">Add</button>
+        </form>
+"#, action = action("temp_channels"));
+    }
+
+    //XP
+    {
+        let settings = sqlx::query!("SELECT txt_apply_interval, txt_punish_interval FROM xp WHERE guild_id = $1", guild_db)
+            .fetch_optional(&db).await?;
+        let (apply, punish) = match &settings {
+            Some(v) => (interval_seconds(&v.txt_apply_interval), interval_seconds(&v.txt_punish_interval)),
+            None => (0.05, 120.),
+        };
+        let ignored = sqlx::query!("SELECT channel_id FROM xp_channels_ignored WHERE guild_id = $1", guild_db)
+            .fetch_all(&db).await?;
+        let mut ignored_list = String::new();
+        for channel in ignored {
+            let _ = write!(ignored_list, r#"
+            <li>{name} <form method="post" action="{action}" style="display:inline"><input type="hidden" name="channel" value="{id}"><input type="hidden" name="action" value="remove"><button type="submit">Remove</button></form></li>"#,
+                name = channel_name(channel.channel_id), id = crate::convertu(channel.channel_id), action = action("xp/ignored_channels"));
+        }
+        let all_channels = channel_options(&[ChannelType::Text, ChannelType::Voice, ChannelType::Stage, ChannelType::News, ChannelType::Forum], None);
+        let _ = write!(body, r#"
+        <h2>XP</h2>
+        <form method="post" action="{action}">
+            <label>Text XP apply interval (seconds): <input name="txt_apply_interval" type="number" min="0.001" step="0.001" value="{apply}"></label><br>
+            <label>Text XP spam punish interval (seconds): <input name="txt_punish_interval" type="number" min="0" step="0.001" value="{punish}"></label><br>
+            <button type="submit">Save</button>
+        </form>
+        <h3>Channels without XP</h3>
+        <ul>{ignored_list}
+        </ul>
+        <form method="post" action="{ignore_action}">
+            <input type="hidden" name="action" value="add">
+            <select name="channel">{all_channels}</select>
+            <button type="submit">Add</button>
+        </form>
+"#, action = action("xp"), ignore_action = action("xp/ignored_channels"));
+    }
+
+    //Reaction Roles
+    {
+        let reactions = sqlx::query!("SELECT channel_id, message_id, give_role_id, emoji FROM role_reactions WHERE guild_id = $1 ORDER BY channel_id, message_id", guild_db)
+            .fetch_all(&db).await?;
+        let mut list = String::new();
+        for reaction in reactions {
+            let emoji = match serde_json::from_value::<serenity::model::channel::ReactionType>(reaction.emoji) {
+                Ok(serenity::model::channel::ReactionType::Unicode(v)) => escape(&v),
+                Ok(serenity::model::channel::ReactionType::Custom { name, id, .. }) => format!(":{}:", escape(name.as_deref().unwrap_or(&id.to_string()))),
+                _ => "Unknown emoji".to_string(),
+            };
+            let _ = write!(list, r#"
+            <li>{emoji} on message {message_id} in {channel} gives {role} <form method="post" action="{action}" style="display:inline"><input type="hidden" name="message" value="{message_id}"><input type="hidden" name="role" value="{role_id}"><button type="submit">Remove</button></form></li>"#,
+                channel = channel_name(reaction.channel_id), message_id = crate::convertu(reaction.message_id), role = role_name(reaction.give_role_id), role_id = crate::convertu(reaction.give_role_id), action = action("reaction_roles/remove"));
+        }
+        let text_channels = channel_options(&[ChannelType::Text, ChannelType::News, ChannelType::Voice], None);
+        let _ = write!(body, r#"
+        <h2>Reaction Roles</h2>
+        <ul>{list}
+        </ul>
+        <form method="post" action="{action}">
+            <label>Channel: <select name="channel">{text_channels}</select></label>
+            <label>Message id: <input name="message" inputmode="numeric" required></label>
+            <label>Role: <select name="role">{roles}</select></label>
+            <label>Emoji: <input name="emoji" required></label>
+            <button type="submit">Add</button>
+        </form>
+"#, action = action("reaction_roles/add"), roles = role_options());
+    }
+
+    //Role Limiter
+    {
+        let limits = sqlx::query!(r#"
+SELECT role_id, array_to_string(
+        array(SELECT
+                  '( ' || array_to_string(array_cat(
+                          array((SELECT v::text FROM unnest(t.normal) as v)),
+                          array((SELECT '!'||v FROM unnest(t.negated) as v))
+                  ), ' & ') || ' )'
+              FROM unnest(bind_roles) as t
+        ), ' | ') as "bind_roles!"
+FROM role_limiter
+WHERE guild_id = $1"#, guild_db)
+            .fetch_all(&db).await?;
+        let mut list = String::new();
+        for limit in limits {
+            let _ = write!(list, r#"
+            <li>{role} is bound to: <code>{bind_roles}</code> <form method="post" action="{action}" style="display:inline"><input type="hidden" name="role" value="{role_id}"><button type="submit">Remove</button></form></li>"#,
+                role = role_name(limit.role_id), role_id = crate::convertu(limit.role_id), bind_roles = escape(&limit.bind_roles), action = action("role_limiter/remove"));
+        }
+        let _ = write!(body, r#"
+        <h2>Role Limiter</h2>
+        <p>Members can only keep a role, if their roles satisfy the formula. Formulas use role ids: ! = NOT, &amp; = AND, | = OR.</p>
+        <ul>{list}
+        </ul>
+        <form method="post" action="{action}">
+            <label>Role: <select name="role">{roles}</select></label>
+            <label>Formula: <input name="bind_roles" required></label>
+            <button type="submit">Save</button>
+        </form>
+"#, action = action("role_limiter/add"), roles = role_options());
+    }
+
+    body.push_str(r#"
+        <p><a href="/guilds">Back to all guilds</a></p>"#);
+    Ok(page(&format!("Settings for {}", guild.name), &body))
+}
@@ -1,4 +1,5 @@
 pub mod new;
 pub mod err;
 pub mod ok;
-pub mod session;
\ No newline at end of file
+pub mod session;
+pub mod logout;
\ No newline at end of file
@@ -0,0 +1,47 @@
+use base64::Engine;
+
+#[derive(rocket::response::Responder)]
+pub enum Responder {
+    Ok(rocket::response::content::RawHtml<&'static str>),
+    NoAuth(crate::rocket::auth::NoAuth),
+    CsrfError(crate::rocket::csrf::CsrfTokenError),
+}
+
+#[rocket::post("/logout")]
+pub async fn logout(auth: Result<&crate::rocket::auth::Auth, crate::rocket::auth::NoAuth>, csrf: Result<crate::rocket::csrf::CsrfToken<crate::rocket::csrf::Form>, crate::rocket::csrf::CsrfTokenError>, cookie_jar: &rocket::http::CookieJar<'_>) -> Responder {
+    let auth = match auth {
+        Ok(v) => v,
+        Err(err) => return Responder::NoAuth(err),
+    };
+    if let Err(err) = csrf {
+        return Responder::CsrfError(err);
+    }
+    if let Some(cookie) = cookie_jar.get_private(super::session::SESSION_COOKIE) {
+        cookie_jar.remove_private(super::session::SESSION_COOKIE);
+        if let Ok(session) = base64::engine::general_purpose::URL_SAFE.decode(cookie.value().as_bytes()) {
+            if let Some(token) = auth.discord.auth.remove(session.as_slice()).await {
+                //Revoking the refresh token also revokes all access tokens issued with it.
+                match crate::rocket::auth::discord::token::RevokeTokenRequest::refresh_token(&token.refresh_token).revoke_token(&auth.discord).await {
+                    Ok(()) => {},
+                    Err(err) => {
+                        tracing::warn!("Failed to revoke Discord token on logout: {err}");
+                    }
+                }
+            }
+        }
+    }
+    Responder::Ok(rocket::response::content::RawHtml(r#"
+<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta charset="utf-8">
+        <meta name="color-scheme" content="light dark">
+        <title>Logged out</title>
+    </head>
+    <body>
+        <h1>Logged out</h1>
+        <p>You have been logged out.</p>
+    </body>
+</html>
+"#))
+}
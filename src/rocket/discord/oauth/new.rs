@@ -2,10 +2,16 @@ use rocket::http::uri::fmt::ValidRoutePrefix;
 
 const BASE_URI: rocket::http::uri::Absolute<'static> = rocket::uri!("https://discord.com/oauth2/authorize?");
 const SCOPES: &'static str = "openid identify connections guilds guilds.members.read guilds.channels.read";
+#[derive(rocket::response::Responder)]
+pub enum Responder {
+    NoAuth(crate::rocket::auth::NoAuth),
+    TooManyCsrfTokens(crate::rocket::csrf::TooManyCsrfTokens),
+}
+
 #[rocket::get("/discord/new_oauth")]
-pub async fn new_oauth<'r>(auth: Result<&crate::rocket::auth::Auth, crate::rocket::auth::NoAuth>) -> Result<rocket::response::Redirect, crate::rocket::auth::NoAuth> {
-    let auth = auth?;
-    let token = auth.get_new_csrf().await;
+pub async fn new_oauth<'r>(auth: Result<&crate::rocket::auth::Auth, crate::rocket::auth::NoAuth>, ip: Option<std::net::IpAddr>) -> Result<rocket::response::Redirect, Responder> {
+    let auth = auth.map_err(Responder::NoAuth)?;
+    let token = auth.get_new_csrf(ip).await.map_err(Responder::TooManyCsrfTokens)?;
     let uri = BASE_URI.append("/".into(), Some(format!("client_id={client_id}&response_type=code&redirect_uri={oauth_url}&scope={SCOPES}&state={token}", oauth_url=crate::rocket::auth::discord::OAUTH_URL, client_id=auth.discord.client_id).into()));
     Ok(rocket::response::Redirect::temporary(uri))
 }
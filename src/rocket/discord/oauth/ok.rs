@@ -40,13 +40,32 @@ pub async fn oauth_ok(code: &str, state: &str, csrf: Result<csrf::CsrfToken<csrf
 "#).into())))
         }
     };
-    let key = auth.discord.auth.insert_new_token(token).await;
+    let key = match auth.discord.auth.insert_new_token(&token).await {
+        Ok(v) => v,
+        Err(err) => {
+            tracing::error!("Failed to save Discord session: {err}");
+            return Responder::HtmlErr((rocket::http::Status::InternalServerError, rocket::response::content::RawHtml(r#"
+<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta charset="utf-8">
+        <meta name="color-scheme" content="light dark">
+        <title>Internal Server Error</title>
+    </head>
+    <body>
+        <h1>Internal Server Error</h1>
+        <p>Failed to save the session.</p>
+    </body>
+</html>
+"#.into())))
+        }
+    };
     let key = base64::engine::general_purpose::URL_SAFE.encode(&key);
     let mut cookie = rocket::http::Cookie::new(super::session::SESSION_COOKIE, key);
     cookie.set_secure(true);
-    if let Some(v) = rocket::time::OffsetDateTime::now_utc().checked_add(rocket::time::Duration::days(7)) {
+    if let Some(v) = rocket::time::OffsetDateTime::now_utc().checked_add(rocket::time::Duration::days(i64::from(crate::rocket::auth::discord::SESSION_EXPIRY_DAYS))) {
         cookie.set_expires(v);
     }
-    cookie_jar.add(cookie);
+    cookie_jar.add_private(cookie);
     Responder::Ok(rocket::response::Redirect::to("/"))
 }
\ No newline at end of file
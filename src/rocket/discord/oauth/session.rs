@@ -28,7 +28,7 @@ impl<'a> rocket::request::FromRequest<'a> for Session {
             None => return Outcome::Forward(rocket::http::Status::Unauthorized),
             Some(v) => v,
         };
-        if let Some(v) = time::OffsetDateTime::now_utc().checked_add(time::Duration::days(7)) {
+        if let Some(v) = time::OffsetDateTime::now_utc().checked_add(time::Duration::days(i64::from(crate::rocket::auth::discord::SESSION_EXPIRY_DAYS))) {
             cookie.set_expires(Expiration::DateTime(v));
         }
         cookie.set_secure(Some(true));
@@ -46,8 +46,11 @@ impl<'a> rocket::request::FromRequest<'a> for Session {
             Some(v) => v,
             None => return Outcome::Error((rocket::http::Status::InternalServerError, Responder::NoAuth(NoAuth))),
         };
-        let mut auth = match twitch.discord.auth.tokens.get_async(session.as_slice()).await {
-            None => return Outcome::Forward(rocket::http::Status::Unauthorized),
+        let mut auth = match twitch.discord.auth.get_token(session.as_slice()).await {
+            None => {
+                request.cookies().remove_private(SESSION_COOKIE);
+                return Outcome::Forward(rocket::http::Status::Unauthorized);
+            },
             Some(v) => v,
         };
         let mut http = serenity::http::Http::new(format!("Bearer {}", auth.access_token).as_str());
@@ -57,7 +60,8 @@ impl<'a> rocket::request::FromRequest<'a> for Session {
                 let mut err = ::anyhow::format_err!("Failed to get current user: {err}");
                 match crate::rocket::auth::discord::token::TokenRequest::refresh_token(&auth.refresh_token).request_token(&twitch.discord).await {
                     Ok(v) => {
-                        *auth = v;
+                        auth = v;
+                        twitch.discord.auth.update_token(session.as_slice(), &auth).await;
                         http = serenity::http::Http::new(format!("Bearer {}", auth.access_token).as_str());
                         match http.get_current_user().await {
                             Ok(v) => v,
@@ -76,7 +80,6 @@ impl<'a> rocket::request::FromRequest<'a> for Session {
                 }
             },
         };
-        let auth = auth.clone();
         Outcome::Success(Self{
             session,
             auth,
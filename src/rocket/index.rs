@@ -14,10 +14,16 @@ pub async fn index_none<'r>(
 
 #[rocket::get("/", rank = 0)]
 pub async fn index<'r>(
-    _auth: &rocket::State<Arc<crate::rocket::auth::Auth>>,
+    auth: &rocket::State<Arc<crate::rocket::auth::Auth>>,
     discord_session: super::discord::oauth::session::Session,
     twitch_session: super::twitch::oauth::session::Session,
+    ip: Option<std::net::IpAddr>,
 ) -> rocket::response::content::RawHtml<String> {
+    //Logging out changes state, so it needs a form with a CSRF Token.
+    let logout = match auth.get_new_csrf(ip).await {
+        Ok(csrf) => format!(r#"<form method="post" action="/logout?csrf={csrf}"><button type="submit">Log out</button></form>"#),
+        Err(_) => "<p>Too many open pages to log out right now. Please try again later.</p>".to_string(),
+    };
     let discord_name = &discord_session.current_user.name;
     let twitch_name = &twitch_session.auth.login;
     let twitch_id = &twitch_session.auth.user_id;
@@ -36,35 +42,42 @@ pub async fn index<'r>(
             <li>Twitch: {twitch_name} ({twitch_id})</li>
             <li>Discord: {discord_name}</li>
         </ul>
+        <p><a href="/twitch">Twitch live notifications</a></p>
+        <p><a href="/guilds">Guild settings</a></p>
+        {logout}
     </body>
 </html>
 "#))
 }
 
-async fn decorate_channel(channel: &serenity::model::id::ChannelId, value: &(bool, chrono::DateTime<chrono::Utc>), dc: &crate::discord_client::DiscordClient) -> Option<String> {
+/// Renders one channel, that asked for live notifications, as a checkbox for the `/twitch` page.
+/// Returns `None` for channels, that are not in a guild.
+pub(super) async fn decorate_channel(channel: &serenity::model::id::ChannelId, value: &(bool, chrono::DateTime<chrono::Utc>), dc: &crate::discord_client::DiscordClient) -> Option<String> {
+    use super::dashboard::escape;
     let input = {
         let checked = if value.0 {
             "checked"
         } else {
             ""
         };
-        format!(r#"<input name="{channel}" type="checkbox" {checked}></input>"#)
+        format!(r#"<input name="channels[{channel}]" type="checkbox" {checked}>"#)
     };
-    match dc.get_channel(channel.clone()).await {
+    let last_changed = value.1.format("%Y-%m-%d %H:%M UTC");
+    match dc.get_channel(*channel).await {
         Ok(serenity::model::channel::Channel::Guild(channel)) => {
-            match dc.get_guild(channel.guild_id.clone()).await {
+            match dc.get_guild(channel.guild_id).await {
                 Ok(guild) => {
-                    let guild_name = &guild.name;
-                    let channel_name = &channel.name;
-                    let image = guild.icon_url().map_or_else(String::new, |url|format!(r#"<img src="{url}" alt="Icon of the Guild: {channel_name}"></img>"#));
+                    let guild_name = escape(&guild.name);
+                    let channel_name = escape(&channel.name);
+                    let image = guild.icon_url().map_or_else(String::new, |url|format!(r#"<img src="{}" alt="Icon of the Guild: {guild_name}" width="32" height="32">"#, escape(&url)));
 
-                    Some(format!(r#"<div><label>{image}{guild_name} -> {channel_name}: {input}></label></div>"#))
+                    Some(format!(r#"<div><label>{image}{guild_name} -> #{channel_name}: {input}</label> (last changed {last_changed})</div>"#))
                 }
                 Err(_) => {
                     let guild_id = &channel.guild_id;
-                    let name = &channel.name;
+                    let name = escape(&channel.name);
 
-                    Some(format!(r#"<div><label>(Raw Guild Id: {guild_id}) -> {name}: {input}</label></div>"#))
+                    Some(format!(r#"<div><label>(Raw Guild Id: {guild_id}) -> #{name}: {input}</label> (last changed {last_changed})</div>"#))
                 }
             }
         }
@@ -72,7 +85,7 @@ async fn decorate_channel(channel: &serenity::model::id::ChannelId, value: &(boo
             None
         }
         Err(_) => {
-            Some(format!(r#"<div><label>Deleted, Unknown or Unaccessible Channel Id: {channel}</label></div>"#))
+            Some(format!(r#"<div><label>Deleted, Unknown or Unaccessible Channel Id: {channel}: {input}</label></div>"#))
         }
     }
-}
\ No newline at end of file
+}
@@ -1,2 +1,3 @@
 pub mod oauth;
-pub mod webhook;
\ No newline at end of file
+pub mod webhook;
+pub mod settings;
@@ -44,7 +44,8 @@ pub async fn oauth_ok(code: &str, scope: &str, state: &str, csrf: Result<csrf::C
                     return Responder::Err((rocket::http::Status::InternalServerError, "Cannot create cookie from UserToken".into()))
                 },
             }
-            auth.twitch.auth.authentications.upsert_async(token.user_id.clone(), From::from(token.clone())).await;
+            auth.twitch.auth.upsert(&From::from(token.clone())).await;
+            auth.twitch.subscribe_stream_events(&token.user_id).await;
             Responder::Ok(rocket::response::Redirect::to("/twitch"))
         },
         Err(err) => {
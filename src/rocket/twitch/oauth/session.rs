@@ -63,19 +63,14 @@ impl<'a> rocket::request::FromRequest<'a> for Session {
             Some(v) => v,
             None => return Outcome::Error((rocket::http::Status::InternalServerError, NoAuth)),
         };
-        let mut token = match &session {
+        let auth = match &session {
             SessionCookie::V1 { user_id } => {
-                match auth.twitch.auth.authentications.get_async(user_id).await {
+                match auth.twitch.get_valid_authentication(user_id).await {
                     None => return Outcome::Forward(rocket::http::Status::Unauthorized),
                     Some(v) => v,
                 }
             }
         };
-        if !token.check_valid(&auth.twitch).await {
-            auth.twitch.auth.authentications.remove_async(&token.user_id).await;
-            return Outcome::Forward(rocket::http::Status::Unauthorized);
-        }
-        let auth = token.clone();
         Outcome::Success(Self{
             session,
             auth,
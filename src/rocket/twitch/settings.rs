@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+use crate::rocket::csrf;
+use crate::rocket::dashboard::{escape, page};
+
+/// Discord rejects messages with more characters than this.
+const MAX_LIVE_MESSAGE_LENGTH: usize = 2000;
+
+#[derive(rocket::response::Responder)]
+pub enum Responder {
+    Ok(rocket::response::content::RawHtml<String>),
+    //Boxed, because a Redirect is much larger than the other variants
+    Redirect(Box<rocket::response::Redirect>),
+    NoAuth(crate::rocket::auth::NoAuth),
+    TooManyCsrfTokens(csrf::TooManyCsrfTokens),
+    CsrfError(csrf::CsrfTokenError),
+    Err((rocket::http::Status, rocket::response::content::RawHtml<String>)),
+}
+impl Responder {
+    fn saved() -> Self {
+        Self::Redirect(Box::new(rocket::response::Redirect::to("/twitch")))
+    }
+    fn login() -> Self {
+        Self::Redirect(Box::new(rocket::response::Redirect::to(crate::rocket::auth::twitch::NEW_OAUTH_URL)))
+    }
+    fn internal_error(err: impl std::fmt::Display) -> Self {
+        tracing::error!("Failed to save Twitch settings: {err}");
+        Self::Err((rocket::http::Status::InternalServerError, page("Error", "        <p>Failed to save the settings.</p>")))
+    }
+}
+
+/// Shows the Discord channels, that asked for live notifications of the logged-in streamer, and their live message.
+#[rocket::get("/twitch")]
+pub async fn settings(session: Option<super::oauth::session::Session>, auth: Result<&crate::rocket::auth::Auth, crate::rocket::auth::NoAuth>, ip: Option<std::net::IpAddr>) -> Responder {
+    let auth = match auth {
+        Ok(v) => v,
+        Err(err) => return Responder::NoAuth(err),
+    };
+    let session = match session {
+        Some(v) => v,
+        None => return Responder::login(),
+    };
+    //Only one form can be submitted per page load, so all forms share one CSRF Token.
+    let csrf = match auth.get_new_csrf(ip).await {
+        Ok(v) => v,
+        Err(err) => return Responder::TooManyCsrfTokens(err),
+    };
+    let user_id = &session.auth.user_id;
+
+    let mut channels = String::new();
+    for (channel, value) in auth.twitch.auth.enabled_channels(user_id).await {
+        if let Some(v) = crate::rocket::index::decorate_channel(&channel, &value, &auth.discord_client).await {
+            channels.push_str("            ");
+            channels.push_str(&v);
+            channels.push('\n');
+        }
+    }
+    if channels.is_empty() {
+        channels.push_str("            <p>No Discord channel asked for your live notifications yet.</p>\n");
+    }
+    let live_message = auth.twitch.auth.live_message(user_id).await
+        .map(|v| escape(&v.live_message))
+        .unwrap_or_default();
+
+    Responder::Ok(page(&format!("Twitch live notifications of {}", session.auth.login), &format!(r#"
+        <h2>Discord channels</h2>
+        <p>Only checked channels get a message, when you go live.</p>
+        <form method="post" action="/twitch/channels?csrf={csrf}">
+{channels}            <button type="submit">Save</button>
+        </form>
+        <h2>Live message</h2>
+        <p>Sent together with the announcement. Leave empty for the default message.</p>
+        <form method="post" action="/twitch/live_message?csrf={csrf}">
+            <textarea name="live_message" rows="4" cols="60" maxlength="{MAX_LIVE_MESSAGE_LENGTH}">{live_message}</textarea><br>
+            <button type="submit">Save</button>
+        </form>
+        <p><a href="/">Back</a></p>"#)))
+}
+
+#[derive(rocket::form::FromForm, Debug)]
+pub struct Channels {
+    //Unchecked checkboxes are not submitted at all.
+    channels: HashMap<u64, bool>,
+}
+
+/// Approves the checked channels and rejects all others.
+#[rocket::post("/twitch/channels", data = "<form>")]
+pub async fn channels(session: Option<super::oauth::session::Session>, auth: Result<&crate::rocket::auth::Auth, crate::rocket::auth::NoAuth>, csrf: Result<csrf::CsrfToken<csrf::Form>, csrf::CsrfTokenError>, form: rocket::form::Form<Channels>) -> Responder {
+    let auth = match auth {
+        Ok(v) => v,
+        Err(err) => return Responder::NoAuth(err),
+    };
+    if let Err(err) = csrf {
+        return Responder::CsrfError(err);
+    }
+    let session = match session {
+        Some(v) => v,
+        None => return Responder::login(),
+    };
+    let enabled = form.channels.iter()
+        .filter(|(channel, enabled)| **enabled && **channel != 0)
+        .map(|(channel, _)| serenity::model::id::ChannelId::new(*channel))
+        .collect::<Vec<_>>();
+    match auth.twitch.auth.set_enabled_channels(&session.auth.user_id, &enabled).await {
+        Ok(()) => Responder::saved(),
+        Err(err) => Responder::internal_error(err),
+    }
+}
+
+#[derive(rocket::form::FromForm, Debug)]
+pub struct LiveMessage {
+    live_message: String,
+}
+
+/// Changes the text, that gets sent with the live announcement.
+#[rocket::post("/twitch/live_message", data = "<form>")]
+pub async fn live_message(session: Option<super::oauth::session::Session>, auth: Result<&crate::rocket::auth::Auth, crate::rocket::auth::NoAuth>, csrf: Result<csrf::CsrfToken<csrf::Form>, csrf::CsrfTokenError>, form: rocket::form::Form<LiveMessage>) -> Responder {
+    let auth = match auth {
+        Ok(v) => v,
+        Err(err) => return Responder::NoAuth(err),
+    };
+    if let Err(err) = csrf {
+        return Responder::CsrfError(err);
+    }
+    let session = match session {
+        Some(v) => v,
+        None => return Responder::login(),
+    };
+    let live_message = form.live_message.trim();
+    if live_message.chars().count() > MAX_LIVE_MESSAGE_LENGTH {
+        return Responder::Err((rocket::http::Status::BadRequest, page("Invalid Input", &format!("        <p>The live message can be at most {MAX_LIVE_MESSAGE_LENGTH} characters long.</p>"))));
+    }
+    let result = if live_message.is_empty() {
+        auth.twitch.auth.remove_live_message(&session.auth.user_id).await;
+        Ok(())
+    } else {
+        auth.twitch.auth.set_live_message(&session.auth.user_id, live_message).await
+    };
+    match result {
+        Ok(()) => Responder::saved(),
+        Err(err) => Responder::internal_error(err),
+    }
+}
@@ -1,7 +1,36 @@
+use std::sync::Arc;
 use hmac::Mac;
 use rocket::{Data, Request};
 use crate::twitch_client::TWITCH_WS_SECRET;
 
+/// Twitch recommends rejecting messages that are older than 10 minutes.
+const MAX_MESSAGE_AGE: chrono::TimeDelta = chrono::TimeDelta::minutes(10);
+/// Upper bound of remembered message ids.
+/// Ids only need to be remembered for [MAX_MESSAGE_AGE], since older messages are rejected by their timestamp anyway.
+const MAX_SEEN_MESSAGES: usize = 10_000;
+static SEEN_MESSAGES: std::sync::LazyLock<scc::HashMap<String, chrono::DateTime<chrono::Utc>>> = std::sync::LazyLock::new(scc::HashMap::default);
+
+enum SeenMessage {
+    New,
+    Duplicate,
+    Full,
+}
+
+/// Remembers the message id until the message would be rejected because of its age.
+async fn remember_message(id: &str, timestamp: chrono::DateTime<chrono::FixedOffset>) -> SeenMessage {
+    if SEEN_MESSAGES.len() >= MAX_SEEN_MESSAGES {
+        let now = chrono::Utc::now();
+        SEEN_MESSAGES.retain_async(|_, expiry| *expiry > now).await;
+        if SEEN_MESSAGES.len() >= MAX_SEEN_MESSAGES {
+            return SeenMessage::Full;
+        }
+    }
+    match SEEN_MESSAGES.insert_async(id.to_string(), timestamp.to_utc() + MAX_MESSAGE_AGE).await {
+        Ok(()) => SeenMessage::New,
+        Err(_) => SeenMessage::Duplicate,
+    }
+}
+
 macro_rules! twitch_header {
     ($ident: ident, $request: ident,  $name: literal) => {
         twitch_header!($ident, $request, $name, {$ident});
@@ -67,6 +96,13 @@ impl<'r> rocket::data::FromData<'r> for TwitchEventsubMessage<'r> {
         if let Err(_) = hmac.verify_slice(signature.as_bytes()) {
             return rocket::data::Outcome::Error((rocket::http::Status::Unauthorized, "Invalid Signature"));
         }
+        //Only check the timestamp after the signature, because the signature proves that Twitch set the timestamp.
+        let age = chrono::Utc::now().signed_duration_since(timestamp);
+        if age > MAX_MESSAGE_AGE || -age > MAX_MESSAGE_AGE {
+            let ip = request.client_ip();
+            tracing::warn!(target: crate::FAIL2BAN_TARGET, "Rejected Twitch EventSub message {id} from {ip:?}: timestamp {timestamp} is outside of the accepted window");
+            return rocket::data::Outcome::Error((rocket::http::Status::BadRequest, "Twitch-Eventsub-Message-Timestamp is too old"));
+        }
 
         let body: twitch_api::eventsub::Event = match serde_json::from_str(&string) {
             Ok(body) => body,
@@ -82,11 +118,26 @@ impl<'r> rocket::data::FromData<'r> for TwitchEventsubMessage<'r> {
 }
 
 #[rocket::post("/twitch/eventsub", data="<twitch_event>")]
-pub async fn webhook<'r>(twitch_event: TwitchEventsubMessage<'r>) -> (rocket::http::Status, String) {
+pub async fn webhook<'r>(twitch_event: TwitchEventsubMessage<'r>, auth: &rocket::State<Arc<crate::rocket::auth::Auth>>, ip: Option<std::net::IpAddr>) -> (rocket::http::Status, String) {
     if let Some(verification) = twitch_event.body.get_verification_request() {
         return (rocket::http::Status::Ok, verification.challenge.clone());
     }
 
+    match remember_message(twitch_event.id, twitch_event.timestamp).await {
+        SeenMessage::New => {},
+        SeenMessage::Duplicate => {
+            let id = twitch_event.id;
+            tracing::info!("Dropped duplicate Twitch EventSub message {id} from {ip:?}");
+            //Twitch retries until it gets a 2xx response, so acknowledge the message without handling it again.
+            return (rocket::http::Status::NoContent, String::new());
+        },
+        SeenMessage::Full => {
+            let id = twitch_event.id;
+            tracing::warn!("Rejected Twitch EventSub message {id} from {ip:?}: too many messages in the last {} minutes", MAX_MESSAGE_AGE.num_minutes());
+            return (rocket::http::Status::ServiceUnavailable, String::new());
+        },
+    }
+
     match twitch_event.body {
         twitch_api::eventsub::Event::StreamOnlineV1(event) => {
             match event.message{
@@ -94,11 +145,45 @@ pub async fn webhook<'r>(twitch_event: TwitchEventsubMessage<'r>) -> (rocket::ht
                     (rocket::http::Status::Ok, event.challenge.clone())
                 },
                 twitch_api::eventsub::Message::Notification(event) => {
-                    //TODO: Handle
+                    //Twitch wants a response within a few seconds, so don't make it wait on discord.
+                    let auth = auth.inner().clone();
+                    tokio::spawn(async move {
+                        auth.twitch.announce_stream_online(&auth.discord_client, &event).await;
+                    });
+                    (rocket::http::Status::NoContent, String::new())
+                },
+                twitch_api::eventsub::Message::Revocation() => {
+                    let auth = auth.inner().clone();
+                    let subscription = event.subscription;
+                    tokio::spawn(async move {
+                        auth.twitch.handle_revocation(&auth.discord_client, &subscription.id, subscription.type_, &subscription.condition.broadcaster_user_id, &subscription.status).await;
+                    });
+                    (rocket::http::Status::NoContent, String::new())
+                },
+                other => {
+                    tracing::info!("Received unhandled twitch Webhook Message: {other:?}");
+                    (rocket::http::Status::NoContent, String::new())
+                }
+            }
+        }
+        twitch_api::eventsub::Event::StreamOfflineV1(event) => {
+            match event.message{
+                twitch_api::eventsub::Message::VerificationRequest(event) => {
+                    (rocket::http::Status::Ok, event.challenge.clone())
+                },
+                twitch_api::eventsub::Message::Notification(event) => {
+                    let auth = auth.inner().clone();
+                    tokio::spawn(async move {
+                        auth.twitch.announce_stream_offline(&auth.discord_client, &event).await;
+                    });
                     (rocket::http::Status::NoContent, String::new())
                 },
                 twitch_api::eventsub::Message::Revocation() => {
-                    //TODO: Handle
+                    let auth = auth.inner().clone();
+                    let subscription = event.subscription;
+                    tokio::spawn(async move {
+                        auth.twitch.handle_revocation(&auth.discord_client, &subscription.id, subscription.type_, &subscription.condition.broadcaster_user_id, &subscription.status).await;
+                    });
                     (rocket::http::Status::NoContent, String::new())
                 },
                 other => {
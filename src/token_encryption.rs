@@ -0,0 +1,214 @@
+//! Envelope encryption for OAuth tokens stored in the database.
+//!
+//! Every token gets encrypted with its own random data key.
+//! The data key is then encrypted ("wrapped") with the key from `TOKEN_ENCRYPTION_KEY`.
+//! Rotating the key therefore only needs to re-wrap the data keys, which happens on startup:
+//! Put the new key into `TOKEN_ENCRYPTION_KEY` and the old one into `TOKEN_ENCRYPTION_OLD_KEYS` (comma separated).
+//! Once the bot started once, the old keys can be removed.
+//!
+//! Stored values look like `enc:v1:<key id>:<wrapped data key>:<encrypted token>`.
+//! Values without the prefix are plaintext, from before encryption was enabled.
+
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use base64::Engine;
+use sha2::Digest;
+
+const PREFIX: &str = "enc:v1:";
+const KEY_LENGTH: usize = 32;
+const NONCE_LENGTH: usize = 12;
+
+struct Key {
+    id: String,
+    cipher: aes_gcm::Aes256Gcm,
+}
+impl Key {
+    fn new(key: &[u8; KEY_LENGTH]) -> Self {
+        //The first 8 bytes of the hash are enough to tell our keys apart, without leaking anything about them.
+        let hash = sha2::Sha256::digest(key);
+        let id = hash[..8].iter().map(|v| format!("{v:02x}")).collect();
+        Self {
+            id,
+            cipher: aes_gcm::Aes256Gcm::new(key.into()),
+        }
+    }
+    fn parse(key: &str) -> ::anyhow::Result<Self> {
+        let key = base64::engine::general_purpose::STANDARD.decode(key.trim())?;
+        let key = <[u8; KEY_LENGTH]>::try_from(key.as_slice())
+            .map_err(|_| ::anyhow::format_err!("Expected a key of {KEY_LENGTH} bytes, but got {} bytes", key.len()))?;
+        Ok(Self::new(&key))
+    }
+}
+
+struct Keys {
+    current: Option<Key>,
+    old: Vec<Key>,
+}
+impl Keys {
+    fn from_env() -> ::anyhow::Result<Self> {
+        let current = match std::env::var("TOKEN_ENCRYPTION_KEY") {
+            Ok(v) if !v.trim().is_empty() => Some(Key::parse(v.as_str()).map_err(|err| err.context("Invalid TOKEN_ENCRYPTION_KEY"))?),
+            _ => None,
+        };
+        let old = match std::env::var("TOKEN_ENCRYPTION_OLD_KEYS") {
+            Ok(v) => v.split(',')
+                .filter(|v| !v.trim().is_empty())
+                .map(|v| Key::parse(v).map_err(|err| err.context("Invalid key in TOKEN_ENCRYPTION_OLD_KEYS")))
+                .collect::<::anyhow::Result<Vec<_>>>()?,
+            Err(_) => Vec::new(),
+        };
+        Ok(Self { current, old })
+    }
+    fn get(&self, id: &str) -> Option<&Key> {
+        self.current.iter().chain(self.old.iter()).find(|key| key.id == id)
+    }
+}
+
+fn keys() -> &'static ::anyhow::Result<Keys> {
+    static KEYS: std::sync::LazyLock<::anyhow::Result<Keys>> = std::sync::LazyLock::new(Keys::from_env);
+    &KEYS
+}
+
+fn seal(cipher: &aes_gcm::Aes256Gcm, plaintext: &[u8], aad: &[u8]) -> ::anyhow::Result<Vec<u8>> {
+    let nonce: [u8; NONCE_LENGTH] = rand::random();
+    let mut out = nonce.to_vec();
+    out.extend(cipher.encrypt(&nonce.into(), Payload { msg: plaintext, aad })
+        .map_err(|err| ::anyhow::format_err!("Failed to encrypt: {err}"))?);
+    Ok(out)
+}
+fn open(cipher: &aes_gcm::Aes256Gcm, sealed: &[u8], aad: &[u8]) -> ::anyhow::Result<Vec<u8>> {
+    if sealed.len() < NONCE_LENGTH {
+        ::anyhow::bail!("Encrypted value is too short");
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LENGTH);
+    cipher.decrypt(nonce.into(), Payload { msg: ciphertext, aad })
+        .map_err(|err| ::anyhow::format_err!("Failed to decrypt: {err}"))
+}
+
+struct Envelope<'a> {
+    key_id: &'a str,
+    wrapped_key: Vec<u8>,
+    data: Vec<u8>,
+}
+impl<'a> Envelope<'a> {
+    fn parse(value: &'a str) -> ::anyhow::Result<Option<Self>> {
+        let Some(value) = value.strip_prefix(PREFIX) else {
+            return Ok(None);
+        };
+        let mut parts = value.split(':');
+        let (Some(key_id), Some(wrapped_key), Some(data), None) = (parts.next(), parts.next(), parts.next(), parts.next()) else {
+            ::anyhow::bail!("Malformed encrypted value");
+        };
+        Ok(Some(Self {
+            key_id,
+            wrapped_key: base64::engine::general_purpose::URL_SAFE.decode(wrapped_key)?,
+            data: base64::engine::general_purpose::URL_SAFE.decode(data)?,
+        }))
+    }
+    fn format(key_id: &str, wrapped_key: &[u8], data: &[u8]) -> String {
+        let wrapped_key = base64::engine::general_purpose::URL_SAFE.encode(wrapped_key);
+        let data = base64::engine::general_purpose::URL_SAFE.encode(data);
+        format!("{PREFIX}{key_id}:{wrapped_key}:{data}")
+    }
+    fn unwrap_data_key(&self, keys: &Keys) -> ::anyhow::Result<Vec<u8>> {
+        let key_id = self.key_id;
+        let key = keys.get(key_id)
+            .ok_or_else(|| ::anyhow::format_err!("Token was encrypted with key {key_id}, which is neither TOKEN_ENCRYPTION_KEY nor in TOKEN_ENCRYPTION_OLD_KEYS"))?;
+        open(&key.cipher, &self.wrapped_key, key_id.as_bytes())
+    }
+}
+
+/// Encrypts a token for storage.
+/// Without a configured `TOKEN_ENCRYPTION_KEY` the token is stored as is.
+pub(crate) fn encrypt(token: &str) -> ::anyhow::Result<String> {
+    let keys = keys().as_ref().map_err(|err| ::anyhow::format_err!("{err}"))?;
+    let Some(key) = &keys.current else {
+        return Ok(token.to_string());
+    };
+    let data_key: [u8; KEY_LENGTH] = rand::random();
+    let data = seal(&aes_gcm::Aes256Gcm::new(&data_key.into()), token.as_bytes(), &[])?;
+    let wrapped_key = seal(&key.cipher, &data_key, key.id.as_bytes())?;
+    Ok(Envelope::format(&key.id, &wrapped_key, &data))
+}
+
+/// Decrypts a stored token. Plaintext tokens are returned as is.
+pub(crate) fn decrypt(value: &str) -> ::anyhow::Result<String> {
+    let Some(envelope) = Envelope::parse(value)? else {
+        return Ok(value.to_string());
+    };
+    let keys = keys().as_ref().map_err(|err| ::anyhow::format_err!("{err}"))?;
+    let data_key = aes_gcm::Aes256Gcm::new_from_slice(&envelope.unwrap_data_key(keys)?)
+        .map_err(|err| ::anyhow::format_err!("Invalid data key: {err}"))?;
+    let token = open(&data_key, &envelope.data, &[])?;
+    Ok(String::from_utf8(token)?)
+}
+
+/// Returns the value re-wrapped with the current key, or `None` if nothing needs to change.
+fn rewrap(value: &str, keys: &Keys) -> ::anyhow::Result<Option<String>> {
+    let envelope = Envelope::parse(value)?;
+    let Some(key) = &keys.current else {
+        return match envelope {
+            Some(envelope) => Err(::anyhow::format_err!("Found a token encrypted with key {}, but TOKEN_ENCRYPTION_KEY is not set", envelope.key_id)),
+            None => Ok(None),
+        };
+    };
+    match envelope {
+        Some(envelope) if envelope.key_id == key.id => Ok(None),
+        Some(envelope) => {
+            let data_key = envelope.unwrap_data_key(keys)?;
+            let wrapped_key = seal(&key.cipher, &data_key, key.id.as_bytes())?;
+            Ok(Some(Envelope::format(&key.id, &wrapped_key, &envelope.data)))
+        },
+        None => Ok(Some(encrypt(value)?)),
+    }
+}
+fn rewrap_optional(value: Option<&str>, keys: &Keys) -> ::anyhow::Result<Option<Option<String>>> {
+    match value {
+        Some(value) => Ok(rewrap(value, keys)?.map(Some)),
+        None => Ok(None),
+    }
+}
+
+/// Checks the configured keys against the stored tokens.
+/// Fails if there are encrypted tokens, that cannot be decrypted with the configured keys.
+/// Tokens that are stored in plaintext or with an old key get (re-)encrypted with the current key.
+pub(crate) async fn init() -> ::anyhow::Result<()> {
+    let keys = keys().as_ref().map_err(|err| ::anyhow::format_err!("{err}"))?;
+    if keys.current.is_none() {
+        tracing::warn!("TOKEN_ENCRYPTION_KEY is not set. OAuth tokens will be stored unencrypted!");
+    }
+
+    let db = crate::get_db().await;
+    let mut transaction = db.begin().await?;
+    let mut changed = 0;
+    for row in sqlx::query!("SELECT user_id, access_token, refresh_token FROM twitch_authentications")
+        .fetch_all(&mut *transaction).await? {
+        let access_token = rewrap(&row.access_token, keys)?;
+        let refresh_token = rewrap_optional(row.refresh_token.as_deref(), keys)?;
+        if access_token.is_none() && refresh_token.is_none() {
+            continue;
+        }
+        sqlx::query!(
+            "UPDATE twitch_authentications SET access_token = $2, refresh_token = $3 WHERE user_id = $1",
+            row.user_id, access_token.unwrap_or(row.access_token), refresh_token.unwrap_or(row.refresh_token)
+        ).execute(&mut *transaction).await?;
+        changed += 1;
+    }
+    for row in sqlx::query!("SELECT session_id, access_token, refresh_token FROM discord_sessions")
+        .fetch_all(&mut *transaction).await? {
+        let access_token = rewrap(&row.access_token, keys)?;
+        let refresh_token = rewrap(&row.refresh_token, keys)?;
+        if access_token.is_none() && refresh_token.is_none() {
+            continue;
+        }
+        sqlx::query!(
+            "UPDATE discord_sessions SET access_token = $2, refresh_token = $3 WHERE session_id = $1",
+            row.session_id, access_token.unwrap_or(row.access_token), refresh_token.unwrap_or(row.refresh_token)
+        ).execute(&mut *transaction).await?;
+        changed += 1;
+    }
+    transaction.commit().await?;
+    if changed > 0 {
+        tracing::info!("Encrypted the OAuth tokens of {changed} rows with the current TOKEN_ENCRYPTION_KEY");
+    }
+    Ok(())
+}
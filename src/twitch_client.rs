@@ -1,4 +1,6 @@
 mod rocket_callback;
+mod live_notification;
+mod eventsub;
 
 use twitch_api::twitch_oauth2::{AccessToken, TwitchToken, UserToken};
 
@@ -20,14 +22,10 @@ pub(crate) struct Twitch {
     pub(super) auth: TwitchAuthentications,
 }
 
-#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
-#[serde(default)]
-pub(crate) struct TwitchAuthentications {
-    pub(crate) authentications: scc::HashMap<twitch_api::types::UserId, TwitchAuthentication>,
-    pub(crate) enabled_channels: scc::HashMap<twitch_api::types::UserId, scc::HashMap<serenity::model::id::ChannelId, (bool, chrono::DateTime<chrono::Utc>)>>,
-    pub(crate) live_message: scc::HashMap<twitch_api::types::UserId, TwitchLiveMessage>,
-    conduit: Option<twitch_api::eventsub::Conduit>,
-}
+/// Access to the Twitch state stored in the database.
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub(crate) struct TwitchAuthentications;
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 #[non_exhaustive]
@@ -44,30 +42,180 @@ pub(crate) struct TwitchAuthentication{
 }
 
 impl TwitchAuthentications {
-    async fn load() -> Self {
-        match tokio::fs::read_to_string(TWITCH_AUTH_PATH).await {
-            Ok(v) => serde_json::from_str(v.as_str()).unwrap_or_else(|err| {
-                tracing::info!("Failed to parse Twitch Authentications: {err}");
-                Default::default()
-            }),
+    /// Gets the stored authentication of the user, without checking if it is still valid.
+    pub(crate) async fn get(&self, user_id: &twitch_api::types::UserId) -> Option<TwitchAuthentication> {
+        let db = crate::get_db().await;
+        match sqlx::query!(
+            r#"SELECT user_id, login, client_id, access_token, refresh_token, expiry as "expiry: chrono::DateTime<chrono::Utc>" FROM twitch_authentications WHERE user_id = $1"#,
+            user_id.as_str()
+        ).fetch_optional(&db).await {
+            Ok(Some(v)) => match TwitchAuthentication::from_row(v.user_id, v.login, v.client_id, v.access_token, v.refresh_token, v.expiry) {
+                Ok(v) => Some(v),
+                Err(err) => {
+                    tracing::error!("Failed to decrypt Twitch Authentication for {user_id}: {err}");
+                    None
+                }
+            },
+            Ok(None) => None,
             Err(err) => {
-                tracing::info!("Failed to read Twitch Authentications file: {err}");
-                Default::default()
+                tracing::error!("Failed to get Twitch Authentication for {user_id}: {err}");
+                None
             }
         }
     }
-    pub(crate) async fn save(&self) -> ::anyhow::Result<()> {
-        let auth = serde_json::to_string(&self)?;
-        match tokio::fs::write(TWITCH_AUTH_PATH, auth).await {
-            Ok(()) => {
-                tracing::info!("Saved Twitch Authentications");
-            },
+    pub(crate) async fn all(&self) -> Vec<TwitchAuthentication> {
+        let db = crate::get_db().await;
+        match sqlx::query!(
+            r#"SELECT user_id, login, client_id, access_token, refresh_token, expiry as "expiry: chrono::DateTime<chrono::Utc>" FROM twitch_authentications"#
+        ).fetch_all(&db).await {
+            Ok(v) => v.into_iter().filter_map(|v| {
+                let user_id = v.user_id.clone();
+                TwitchAuthentication::from_row(v.user_id, v.login, v.client_id, v.access_token, v.refresh_token, v.expiry)
+                    .inspect_err(|err| tracing::error!("Failed to decrypt Twitch Authentication for {user_id}: {err}"))
+                    .ok()
+            }).collect(),
+            Err(err) => {
+                tracing::error!("Failed to get Twitch Authentications: {err}");
+                Vec::new()
+            }
+        }
+    }
+    pub(crate) async fn upsert(&self, token: &TwitchAuthentication) {
+        let db = crate::get_db().await;
+        match upsert_authentication(&db, token).await {
+            Ok(_) => {},
+            Err(err) => {
+                let user_id = &token.user_id;
+                let user_name = &token.login;
+                tracing::error!("Failed to save Twitch Authentication for {user_id}/{user_name}: {err}");
+            }
+        }
+    }
+    pub(crate) async fn remove(&self, user_id: &twitch_api::types::UserId) {
+        let db = crate::get_db().await;
+        match sqlx::query!("DELETE FROM twitch_authentications WHERE user_id = $1", user_id.as_str()).execute(&db).await {
+            Ok(_) => {},
+            Err(err) => {
+                tracing::error!("Failed to remove Twitch Authentication for {user_id}: {err}");
+            }
+        }
+    }
+
+    /// Gets all Discord channels, that asked for live notifications of the broadcaster, and whether the broadcaster approved them.
+    pub(crate) async fn enabled_channels(&self, user_id: &twitch_api::types::UserId) -> Vec<(serenity::model::id::ChannelId, (bool, chrono::DateTime<chrono::Utc>))> {
+        let db = crate::get_db().await;
+        match sqlx::query!(
+            r#"SELECT channel_id, enabled, last_changed as "last_changed: chrono::DateTime<chrono::Utc>" FROM twitch_enabled_channels WHERE user_id = $1"#,
+            user_id.as_str()
+        ).fetch_all(&db).await {
+            Ok(v) => v.into_iter()
+                .map(|v| (serenity::model::id::ChannelId::new(crate::convertu(v.channel_id)), (v.enabled, v.last_changed)))
+                .collect(),
             Err(err) => {
-                tracing::error!("Failed to save Twitch Authentications: {err}");
+                tracing::error!("Failed to get enabled channels for {user_id}: {err}");
+                Vec::new()
             }
         }
+    }
+    /// Removes all channels of the broadcaster and returns the ones, that were approved.
+    pub(crate) async fn remove_enabled_channels(&self, user_id: &twitch_api::types::UserId) -> Vec<serenity::model::id::ChannelId> {
+        let db = crate::get_db().await;
+        match sqlx::query!(
+            "DELETE FROM twitch_enabled_channels WHERE user_id = $1 RETURNING channel_id, enabled",
+            user_id.as_str()
+        ).fetch_all(&db).await {
+            Ok(v) => v.into_iter()
+                .filter(|v| v.enabled)
+                .map(|v| serenity::model::id::ChannelId::new(crate::convertu(v.channel_id)))
+                .collect(),
+            Err(err) => {
+                tracing::error!("Failed to remove enabled channels for {user_id}: {err}");
+                Vec::new()
+            }
+        }
+    }
+
+    pub(crate) async fn live_message(&self, user_id: &twitch_api::types::UserId) -> Option<TwitchLiveMessage> {
+        let db = crate::get_db().await;
+        match sqlx::query_as!(
+            TwitchLiveMessage,
+            r#"SELECT live_message, last_changed as "last_changed: chrono::DateTime<chrono::Utc>" FROM twitch_live_message WHERE user_id = $1"#,
+            user_id.as_str()
+        ).fetch_optional(&db).await {
+            Ok(v) => v,
+            Err(err) => {
+                tracing::error!("Failed to get live message for {user_id}: {err}");
+                None
+            }
+        }
+    }
+    /// Approves the given channels and rejects all other channels, that asked for live notifications of the broadcaster.
+    pub(crate) async fn set_enabled_channels(&self, user_id: &twitch_api::types::UserId, enabled: &[serenity::model::id::ChannelId]) -> Result<(), sqlx::Error> {
+        let db = crate::get_db().await;
+        let enabled = enabled.iter().map(|channel| crate::converti(channel.get())).collect::<Vec<_>>();
+        sqlx::query!(
+            "UPDATE twitch_enabled_channels SET enabled = (channel_id = ANY($2)), last_changed = now() WHERE user_id = $1 AND enabled <> (channel_id = ANY($2))",
+            user_id.as_str(), enabled.as_slice()
+        ).execute(&db).await?;
+        Ok(())
+    }
+    pub(crate) async fn set_live_message(&self, user_id: &twitch_api::types::UserId, live_message: &str) -> Result<(), sqlx::Error> {
+        let db = crate::get_db().await;
+        sqlx::query!(
+            "INSERT INTO twitch_live_message (user_id, live_message) VALUES ($1, $2) ON CONFLICT (user_id) DO UPDATE SET live_message = $2, last_changed = now()",
+            user_id.as_str(), live_message
+        ).execute(&db).await?;
         Ok(())
     }
+    pub(crate) async fn remove_live_message(&self, user_id: &twitch_api::types::UserId) {
+        let db = crate::get_db().await;
+        match sqlx::query!("DELETE FROM twitch_live_message WHERE user_id = $1", user_id.as_str()).execute(&db).await {
+            Ok(_) => {},
+            Err(err) => {
+                tracing::error!("Failed to remove live message for {user_id}: {err}");
+            }
+        }
+    }
+
+    /// Gets the most recently created conduit.
+    async fn conduit(&self) -> ::anyhow::Result<Option<twitch_api::eventsub::Conduit>> {
+        let db = crate::get_db().await;
+        let conduit = sqlx::query!(r#"SELECT conduit::text as "conduit!" FROM twitch_conduit ORDER BY created_at DESC LIMIT 1"#)
+            .fetch_optional(&db).await?;
+        match conduit {
+            Some(v) => Ok(Some(serde_json::from_str(v.conduit.as_str())?)),
+            None => Ok(None),
+        }
+    }
+    async fn save_conduit(&self, conduit: &twitch_api::eventsub::Conduit) -> ::anyhow::Result<()> {
+        let db = crate::get_db().await;
+        insert_conduit(&db, conduit).await?;
+        Ok(())
+    }
+}
+
+async fn upsert_authentication<'a, E: sqlx::PgExecutor<'a>>(executor: E, token: &TwitchAuthentication) -> ::anyhow::Result<()> {
+    let expiry = match token.expiry {
+        TwitchAuthenticationTime::NeverExpiring => None,
+        TwitchAuthenticationTime::Expiring(v) => Some(v),
+    };
+    let access_token = crate::token_encryption::encrypt(token.access_token.secret())?;
+    let refresh_token = match &token.refresh_token {
+        Some(v) => Some(crate::token_encryption::encrypt(v.secret())?),
+        None => None,
+    };
+    sqlx::query!(
+        "INSERT INTO twitch_authentications (user_id, login, client_id, access_token, refresh_token, expiry) VALUES ($1, $2, $3, $4, $5, $6) ON CONFLICT (user_id) DO UPDATE SET login = $2, client_id = $3, access_token = $4, refresh_token = $5, expiry = $6",
+        token.user_id.as_str(), token.login.as_str(), token.client_id.as_str(), access_token, refresh_token, expiry as _
+    ).execute(executor).await?;
+    Ok(())
+}
+async fn insert_conduit<'a, E: sqlx::PgExecutor<'a>>(executor: E, conduit: &twitch_api::eventsub::Conduit) -> ::anyhow::Result<()> {
+    sqlx::query!(
+        "INSERT INTO twitch_conduit (conduit_id, conduit) VALUES ($1, $2::text::jsonb) ON CONFLICT DO NOTHING",
+        conduit.id.as_str(), serde_json::to_string(conduit)?
+    ).execute(executor).await?;
+    Ok(())
 }
 
 #[derive(Default, Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -173,9 +321,98 @@ impl From<twitch_api::twitch_oauth2::UserToken> for TwitchAuthentication {
     }
 }
 
-pub(crate) const TWITCH_AUTH_PATH: &str = "twitch_authentications.json";
+impl TwitchAuthentication {
+    fn from_row(user_id: String, login: String, client_id: String, access_token: String, refresh_token: Option<String>, expiry: Option<chrono::DateTime<chrono::Utc>>) -> ::anyhow::Result<Self> {
+        let access_token = crate::token_encryption::decrypt(&access_token)?;
+        let refresh_token = match refresh_token {
+            Some(v) => Some(crate::token_encryption::decrypt(&v)?),
+            None => None,
+        };
+        Ok(Self {
+            access_token: AccessToken::from(access_token),
+            client_id: twitch_api::twitch_oauth2::ClientId::from(client_id),
+            login: twitch_api::types::UserName::from(login),
+            user_id: twitch_api::types::UserId::from(user_id),
+            refresh_token: refresh_token.map(twitch_api::twitch_oauth2::RefreshToken::from),
+            expiry: match expiry {
+                Some(v) => TwitchAuthenticationTime::Expiring(v),
+                None => TwitchAuthenticationTime::NeverExpiring,
+            },
+        })
+    }
+}
+
+impl Twitch {
+    /// Gets the stored authentication of the user and makes sure, that it is still valid.
+    /// Refreshed tokens get saved, invalid ones get removed.
+    pub(crate) async fn get_valid_authentication(&self, user_id: &twitch_api::types::UserId) -> Option<TwitchAuthentication> {
+        let mut token = self.auth.get(user_id).await?;
+        if token.check_valid(self).await {
+            self.auth.upsert(&token).await;
+            Some(token)
+        } else {
+            self.auth.remove(user_id).await;
+            None
+        }
+    }
+}
+
+pub async fn migrate() -> ::anyhow::Result<()> {
+    //<editor-fold desc="Old Struct definitions">
+    const TWITCH_AUTH_PATH: &str = "twitch_authentications.json";
+    #[derive(Debug, Default, serde::Deserialize)]
+    #[serde(default)]
+    struct OldTwitchAuthentications {
+        authentications: scc::HashMap<twitch_api::types::UserId, TwitchAuthentication>,
+        enabled_channels: scc::HashMap<twitch_api::types::UserId, scc::HashMap<serenity::model::id::ChannelId, (bool, chrono::DateTime<chrono::Utc>)>>,
+        live_message: scc::HashMap<twitch_api::types::UserId, TwitchLiveMessage>,
+        conduit: Option<twitch_api::eventsub::Conduit>,
+    }
+    //</editor-fold>
+    match tokio::fs::read_to_string(TWITCH_AUTH_PATH).await {
+        Ok(v) => {
+            let auth = serde_json::from_str::<OldTwitchAuthentications>(v.as_str())
+                .expect("Error deserializing Twitch Authentications.");
+
+            let db = crate::get_db().await;
+            let mut transaction = db.begin().await.expect("Failed to start transaction");
+            let mut next_entry = auth.authentications.first_entry_async().await;
+            while let Some(entry) = next_entry {
+                upsert_authentication(&mut *transaction, entry.get()).await?;
+                next_entry = entry.next_async().await;
+            }
+            let mut enabled_channels = Vec::new();
+            auth.enabled_channels.scan_async(|user_id, channels| {
+                channels.scan(|channel, (enabled, last_changed)| {
+                    enabled_channels.push((user_id.clone(), *channel, *enabled, *last_changed));
+                });
+            }).await;
+            for (user_id, channel, enabled, last_changed) in enabled_channels {
+                sqlx::query!("INSERT INTO twitch_enabled_channels (user_id, channel_id, enabled, last_changed) VALUES ($1, $2, $3, $4) ON CONFLICT DO NOTHING",
+                    user_id.as_str(), crate::converti(channel.get()), enabled, last_changed as _).execute(&mut *transaction).await?;
+            }
+            let mut next_entry = auth.live_message.first_entry_async().await;
+            while let Some(entry) = next_entry {
+                sqlx::query!("INSERT INTO twitch_live_message (user_id, live_message, last_changed) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING",
+                    entry.key().as_str(), entry.live_message, entry.last_changed as _).execute(&mut *transaction).await?;
+                next_entry = entry.next_async().await;
+            }
+            if let Some(conduit) = &auth.conduit {
+                insert_conduit(&mut *transaction, conduit).await?;
+            }
+            transaction.commit().await.expect("Failed to commit transaction");
+            tokio::fs::remove_file(TWITCH_AUTH_PATH).await.expect("Failed to remove Twitch Authentications file");
+        }
+        Err(err) => {
+            tracing::info!("Failed to read Twitch Authentications file: {err}");
+        }
+    }
+    Ok(())
+}
+
 pub(in super) async fn create_twitch_client(mut rocket: rocket::Rocket<rocket::Build>) -> ::anyhow::Result<(rocket::Rocket<rocket::Build>, Twitch)> {
-    let mut auth = TwitchAuthentications::load().await;
+    migrate().await.expect("Failed to migrate Twitch Authentications");
+    let auth = TwitchAuthentications;
     //Get Twitch Client ID and Secret from .env
     let client_id = ::twitch_api::twitch_oauth2::types::ClientId::from(::std::env::var("TWITCH_CLIENT_ID")?);
     let client_secret = ::twitch_api::twitch_oauth2::types::ClientSecret::from(::std::env::var("TWITCH_CLIENT_SECRET")?);
@@ -185,12 +422,13 @@ pub(in super) async fn create_twitch_client(mut rocket: rocket::Rocket<rocket::B
     //Utilize the Client credentials grant flow to get an app access token
     let access_token = twitch_api::twitch_oauth2::tokens::AppAccessToken::get_app_access_token(&client, client_id.clone(), client_secret.clone(), vec![]).await?;
     //Pre-Create a conduit for eventsub
-    let created_conduit = auth.conduit.is_none();
-    let conduit = match auth.conduit.clone() {
+    let stored_conduit = auth.conduit().await?;
+    let created_conduit = stored_conduit.is_none();
+    let conduit = match stored_conduit {
         Some(v) => v,
         None => {
             let conduit = client.create_conduit(1, &access_token).await?;
-            auth.conduit = Some(conduit.clone());
+            auth.save_conduit(&conduit).await?;
             conduit
         },
     };
@@ -202,15 +440,13 @@ pub(in super) async fn create_twitch_client(mut rocket: rocket::Rocket<rocket::B
         conduit,
         auth,
     };
-    twitch.auth.save().await?;
-    //Register the Twitch Rocket Callback to finish Conduit setup, once the Webserver is online
-    if created_conduit {
-        rocket = rocket.attach(rocket_callback::TwitchRocketCallback{
-            client: twitch.client.clone(),
-            access_token: twitch.access_token.clone(),
-            conduit: twitch.conduit.clone(),
-        });
-    }
+    //Register the Twitch Rocket Callback to finish Conduit setup and subscribe to stream events, once the Webserver is online
+    rocket = rocket.attach(rocket_callback::TwitchRocketCallback{
+        client: twitch.client.clone(),
+        access_token: twitch.access_token.clone(),
+        conduit: twitch.conduit.clone(),
+        created_conduit,
+    });
 
     Ok((rocket, twitch))
 }
\ No newline at end of file
@@ -0,0 +1,146 @@
+use twitch_api::eventsub::{EventSubscription, EventType, Status};
+
+impl super::Twitch {
+    /// Subscribes to `stream.online` and `stream.offline` events of the broadcaster on our conduit.
+    pub(crate) async fn subscribe_stream_events(&self, broadcaster: &twitch_api::types::UserId) {
+        self.subscribe(twitch_api::eventsub::stream::StreamOnlineV1::broadcaster_user_id(broadcaster.clone()), broadcaster).await;
+        self.subscribe(twitch_api::eventsub::stream::StreamOfflineV1::broadcaster_user_id(broadcaster.clone()), broadcaster).await;
+    }
+
+    /// Subscribes to the stream events of every linked broadcaster.
+    /// Unless `all` is set (e.g. for a new conduit), broadcasters with active subscriptions for both events are skipped.
+    pub(crate) async fn subscribe_all_stream_events(&self, all: bool) {
+        let subscribed = if all {
+            Vec::new()
+        } else {
+            let db = crate::get_db().await;
+            match sqlx::query!(
+                "SELECT broadcaster_id FROM twitch_eventsub_subscriptions WHERE revoked_at IS NULL GROUP BY broadcaster_id HAVING COUNT(DISTINCT event_type) >= 2"
+            ).fetch_all(&db).await {
+                Ok(v) => v.into_iter().map(|v| v.broadcaster_id).collect(),
+                Err(err) => {
+                    tracing::error!("Failed to get the active stream event subscriptions: {err}");
+                    Vec::new()
+                }
+            }
+        };
+        for auth in self.auth.all().await {
+            if subscribed.iter().any(|v| v.as_str() == auth.user_id.as_str()) {
+                continue;
+            }
+            self.subscribe_stream_events(&auth.user_id).await;
+        }
+    }
+
+    async fn subscribe<E: EventSubscription + Send>(&self, subscription: E, broadcaster: &twitch_api::types::UserId) -> bool {
+        let event_type = E::EVENT_TYPE.to_str();
+        let subscription = match self.client.create_eventsub_subscription(
+            subscription,
+            twitch_api::eventsub::Transport::conduit(&self.conduit.id),
+            &self.access_token,
+        ).await {
+            Ok(v) => v,
+            Err(err) => {
+                //Twitch responds with a conflict, if the subscription already exists, which is fine.
+                tracing::warn!("Failed to subscribe to {event_type} for {broadcaster}: {err}");
+                return false;
+            }
+        };
+        tracing::info!("Subscribed to {event_type} for {broadcaster}: {:?}", subscription.id);
+        let db = crate::get_db().await;
+        match sqlx::query!(
+            "INSERT INTO twitch_eventsub_subscriptions (subscription_id, broadcaster_id, event_type, status) VALUES ($1, $2, $3, $4) ON CONFLICT (subscription_id) DO UPDATE SET status = $4, revoked_at = NULL",
+            subscription.id.as_str(), broadcaster.as_str(), event_type, status_str(&subscription.status)
+        ).execute(&db).await {
+            Ok(_) => {},
+            Err(err) => {
+                tracing::error!("Failed to record {event_type} subscription for {broadcaster}: {err}");
+            }
+        }
+        true
+    }
+
+    /// Marks the revoked subscription as dead and tries to recreate it.
+    /// If that isn't possible, the guilds receiving live notifications of the broadcaster get notified and the broadcaster is removed from `enabled_channels`.
+    pub(crate) async fn handle_revocation(&self, discord: &crate::discord_client::DiscordClient, subscription_id: &twitch_api::types::EventSubIdRef, event_type: EventType, broadcaster: &twitch_api::types::UserId, status: &Status) {
+        tracing::warn!("Twitch revoked the {event_type} subscription {subscription_id} for {broadcaster}: {status:?}");
+        let db = crate::get_db().await;
+        match sqlx::query!(
+            "UPDATE twitch_eventsub_subscriptions SET status = $2, revoked_at = now() WHERE subscription_id = $1",
+            subscription_id.as_str(), status_str(status)
+        ).execute(&db).await {
+            Ok(_) => {},
+            Err(err) => {
+                tracing::error!("Failed to mark {event_type} subscription {subscription_id} for {broadcaster} as revoked: {err}");
+            }
+        }
+
+        let recoverable = match status {
+            //Our endpoint was unreachable for too long. Nothing about the broadcaster changed.
+            Status::NotificationFailuresExceeded => true,
+            //If the token can still be refreshed, the broadcaster probably just changed their password.
+            Status::AuthorizationRevoked => match self.auth.get(broadcaster).await {
+                Some(mut token) => {
+                    let valid = token.check_valid(self).await;
+                    if valid {
+                        self.auth.upsert(&token).await;
+                    }
+                    valid
+                },
+                None => false,
+            },
+            _ => false,
+        };
+        if recoverable {
+            let resubscribed = match event_type {
+                EventType::StreamOnline => self.subscribe(twitch_api::eventsub::stream::StreamOnlineV1::broadcaster_user_id(broadcaster.clone()), broadcaster).await,
+                EventType::StreamOffline => self.subscribe(twitch_api::eventsub::stream::StreamOfflineV1::broadcaster_user_id(broadcaster.clone()), broadcaster).await,
+                _ => false,
+            };
+            if resubscribed {
+                tracing::info!("Recreated the revoked {event_type} subscription for {broadcaster}");
+                return;
+            }
+        }
+
+        //Without stream.online there are no live notifications, so only tell the guilds about that one.
+        if event_type != EventType::StreamOnline {
+            return;
+        }
+        let channels = self.auth.remove_enabled_channels(broadcaster).await;
+        let login = match self.auth.get(broadcaster).await {
+            Some(v) => v.login.to_string(),
+            None => broadcaster.to_string(),
+        };
+        if matches!(status, Status::UserRemoved) {
+            self.auth.remove(broadcaster).await;
+            self.auth.remove_live_message(broadcaster).await;
+        }
+
+        let reason = match status {
+            Status::AuthorizationRevoked => "the streamer revoked the authorization of this bot",
+            Status::UserRemoved => "the Twitch account no longer exists",
+            Status::VersionRemoved => "Twitch no longer supports the notification type this bot uses",
+            Status::NotificationFailuresExceeded => "this bot could not receive notifications from Twitch",
+            _ => "Twitch revoked the subscription",
+        };
+        let message = serenity::builder::CreateMessage::new()
+            .content(format!("Live notifications for {login} have been disabled, because {reason}. The streamer needs to link their Twitch account again to re-enable them."))
+            .allowed_mentions(serenity::builder::CreateAllowedMentions::new().empty_roles().empty_users());
+        for channel in channels {
+            match channel.send_message(discord, message.clone()).await {
+                Ok(_) => {},
+                Err(err) => {
+                    tracing::error!("Failed to send revocation notice for {broadcaster} to channel {channel}: {err}");
+                }
+            }
+        }
+    }
+}
+
+fn status_str(status: &Status) -> String {
+    match serde_json::to_value(status) {
+        Ok(serde_json::Value::String(v)) => v,
+        _ => format!("{status:?}"),
+    }
+}
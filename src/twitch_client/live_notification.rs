@@ -0,0 +1,197 @@
+use futures::TryStreamExt;
+use serenity::http::CacheHttp;
+use twitch_api::eventsub::stream::{StreamOfflineV1Payload, StreamOnlineV1Payload};
+
+/// Size the `{width}x{height}` placeholder of Twitch thumbnail urls gets replaced with.
+const THUMBNAIL_SIZE: &str = "1280x720";
+/// Twitch purple
+const EMBED_COLOUR: u32 = 0x9146FF;
+
+impl super::Twitch {
+    /// Posts the go-live announcement of the broadcaster to every approved Discord channel.
+    pub(crate) async fn announce_stream_online(&self, discord: &crate::discord_client::DiscordClient, event: &StreamOnlineV1Payload) {
+        let broadcaster = &event.broadcaster_user_id;
+        let channels = self.auth.enabled_channels(broadcaster).await
+            .into_iter()
+            .filter_map(|(channel, (enabled, _))| enabled.then_some(channel))
+            .collect::<Vec<_>>();
+        if channels.is_empty() {
+            tracing::info!("{broadcaster} went live, but no Discord channel is subscribed to them");
+            return;
+        }
+
+        let message = self.build_live_message(event).await;
+        let db = crate::get_db().await;
+        for channel in channels {
+            let guild_id = match discord.get_channel(channel).await {
+                Ok(serenity::model::channel::Channel::Guild(v)) => v.guild_id,
+                Ok(_) => {
+                    tracing::warn!("Channel {channel} subscribed to {broadcaster} is not a guild channel");
+                    continue;
+                },
+                Err(err) => {
+                    tracing::error!("Failed to get channel {channel} subscribed to {broadcaster}: {err}");
+                    continue;
+                }
+            };
+            let message = match channel.send_message(discord, message.clone()).await {
+                Ok(v) => v,
+                Err(err) => {
+                    tracing::error!("Failed to send live notification for {broadcaster} to channel {channel}: {err}");
+                    continue;
+                }
+            };
+            match sqlx::query!(
+                "INSERT INTO twitch_live_messages (stream_id, broadcaster_id, guild_id, channel_id, message_id, started_at) VALUES ($1, $2, $3, $4, $5, $6::text::timestamptz) ON CONFLICT DO NOTHING",
+                event.id, broadcaster.as_str(), crate::converti(guild_id.get()), crate::converti(channel.get()), crate::converti(message.id.get()), event.started_at.as_str()
+            ).execute(&db).await {
+                Ok(_) => {},
+                Err(err) => {
+                    tracing::error!("Failed to record live notification for {broadcaster} in channel {channel}: {err}");
+                }
+            }
+        }
+    }
+
+    /// Edits (or deletes, depending on the guild setting) all go-live announcements of the broadcaster.
+    pub(crate) async fn announce_stream_offline(&self, discord: &crate::discord_client::DiscordClient, event: &StreamOfflineV1Payload) {
+        let broadcaster = &event.broadcaster_user_id;
+        let name = &event.broadcaster_user_name;
+        let db = crate::get_db().await;
+        let messages = match sqlx::query!(
+            r#"DELETE FROM twitch_live_messages WHERE broadcaster_id = $1 RETURNING
+    stream_id,
+    channel_id,
+    message_id,
+    now() - started_at as "duration!",
+    COALESCE((SELECT delete_offline_message FROM twitch_live_settings WHERE twitch_live_settings.guild_id = twitch_live_messages.guild_id), false) as "delete_offline_message!""#,
+            broadcaster.as_str()
+        ).fetch_all(&db).await {
+            Ok(v) => v,
+            Err(err) => {
+                tracing::error!("Failed to get live notifications for {broadcaster}: {err}");
+                return;
+            }
+        };
+        if messages.is_empty() {
+            tracing::info!("{broadcaster} went offline, but no live notification was posted");
+            return;
+        }
+
+        let vods = self.get_vods(broadcaster).await;
+        for message in messages {
+            let channel = serenity::model::id::ChannelId::new(crate::convertu(message.channel_id));
+            let message_id = serenity::model::id::MessageId::new(crate::convertu(message.message_id));
+            if message.delete_offline_message {
+                match channel.delete_message(discord.http(), message_id).await {
+                    Ok(()) => {},
+                    Err(err) => {
+                        tracing::error!("Failed to delete live notification {message_id} for {broadcaster} in channel {channel}: {err}");
+                    }
+                }
+                continue;
+            }
+
+            let old = match channel.message(discord, message_id).await {
+                Ok(v) => v,
+                Err(err) => {
+                    tracing::error!("Failed to get live notification {message_id} for {broadcaster} in channel {channel}: {err}");
+                    continue;
+                }
+            };
+            let mut embed = match old.embeds.into_iter().next() {
+                Some(embed) => serenity::builder::CreateEmbed::from(embed),
+                None => serenity::builder::CreateEmbed::new().title(format!("{name} was live on Twitch")),
+            };
+            embed = embed.field("Duration", format_duration(&message.duration), true);
+            if let Some((_, url)) = vods.iter().find(|(stream_id, _)| stream_id.as_deref() == Some(message.stream_id.as_str())) {
+                embed = embed.field("VOD", url.as_str(), true);
+            }
+            let edit = serenity::builder::EditMessage::new()
+                .content(format!("{name} was live"))
+                .embed(embed);
+            match channel.edit_message(discord, message_id, edit).await {
+                Ok(_) => {},
+                Err(err) => {
+                    tracing::error!("Failed to edit live notification {message_id} for {broadcaster} in channel {channel}: {err}");
+                }
+            }
+        }
+    }
+
+    /// Gets the stream ids and urls of the most recent archived streams of the broadcaster.
+    async fn get_vods(&self, broadcaster: &twitch_api::types::UserId) -> Vec<(Option<String>, String)> {
+        let mut request = twitch_api::helix::videos::GetVideosRequest::user_id(broadcaster);
+        request.type_ = Some(twitch_api::helix::videos::VideoTypeFilter::Archive);
+        request.first = Some(5);
+        match self.client.req_get(request, &self.access_token).await {
+            Ok(v) => v.data.into_iter().map(|video| (video.stream_id.map(|v| v.take()), video.url)).collect(),
+            Err(err) => {
+                tracing::warn!("Failed to get vods of {broadcaster}: {err}");
+                Vec::new()
+            }
+        }
+    }
+
+    async fn build_live_message(&self, event: &StreamOnlineV1Payload) -> serenity::builder::CreateMessage {
+        let name = &event.broadcaster_user_name;
+        let url = format!("https://twitch.tv/{}", event.broadcaster_user_login);
+
+        let ids = twitch_api::types::Collection::from(vec![event.broadcaster_user_id.clone()]);
+        //The stream might not be listed yet, directly after it went online.
+        let (title, category, thumbnail) = match self.client.get_streams_from_ids(&ids, &self.access_token).try_next().await {
+            Ok(Some(stream)) => (stream.title, stream.game_name, Some(stream.thumbnail_url.replace("{width}x{height}", THUMBNAIL_SIZE))),
+            Ok(None) => match self.client.get_channel_from_id(&event.broadcaster_user_id, &self.access_token).await {
+                Ok(Some(channel)) => (channel.title, channel.game_name.to_string(), None),
+                Ok(None) => (String::new(), String::new(), None),
+                Err(err) => {
+                    tracing::warn!("Failed to get channel information for {name}: {err}");
+                    (String::new(), String::new(), None)
+                }
+            },
+            Err(err) => {
+                tracing::warn!("Failed to get stream information for {name}: {err}");
+                (String::new(), String::new(), None)
+            }
+        };
+
+        let content = match self.auth.live_message(&event.broadcaster_user_id).await {
+            Some(v) if !v.live_message.is_empty() => v.live_message,
+            _ => format!("{name} is now live!"),
+        };
+
+        let mut embed = serenity::builder::CreateEmbed::new()
+            .author(serenity::builder::CreateEmbedAuthor::new(name.as_str()).url(url.as_str()))
+            .title(if title.is_empty() { format!("{name} is live on Twitch") } else { title })
+            .url(url.as_str())
+            .colour(EMBED_COLOUR);
+        if !category.is_empty() {
+            embed = embed.field("Category", category, true);
+        }
+        if let Some(thumbnail) = thumbnail {
+            //Discord caches images by url, so make sure we don't show the thumbnail of an older stream.
+            embed = embed.image(format!("{thumbnail}?t={}", chrono::Utc::now().timestamp()));
+        }
+        if let Ok(started_at) = serenity::model::Timestamp::parse(event.started_at.as_str()) {
+            embed = embed.timestamp(started_at);
+        }
+
+        serenity::builder::CreateMessage::new()
+            .content(content)
+            .embed(embed)
+            .allowed_mentions(serenity::builder::CreateAllowedMentions::new().empty_roles().empty_users())
+    }
+}
+
+fn format_duration(duration: &sqlx::postgres::types::PgInterval) -> String {
+    const SECONDS_PER_DAY: i64 = 24*60*60;
+    //Streams are at most 48 hours long, so months should never be set.
+    let seconds = i64::from(duration.days) * SECONDS_PER_DAY + duration.microseconds / 1_000_000;
+    let hours = seconds / 60 / 60;
+    let minutes = seconds / 60 % 60;
+    if hours > 0 {
+        format!("{hours}h {minutes}m")
+    } else {
+        format!("{minutes}m")
+    }
+}
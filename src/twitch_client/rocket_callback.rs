@@ -2,6 +2,8 @@ pub(super) struct TwitchRocketCallback{
     pub client: twitch_api::HelixClient<'static, reqwest::Client>,
     pub access_token: twitch_api::twitch_oauth2::AppAccessToken,
     pub conduit: twitch_api::eventsub::Conduit,
+    /// Whether the conduit was just created and still needs its shards.
+    pub created_conduit: bool,
 }
 
 #[rocket::async_trait]
@@ -14,6 +16,20 @@ impl rocket::fairing::Fairing for TwitchRocketCallback {
     }
 
     async fn on_liftoff(&self, rocket: &rocket::Rocket<rocket::Orbit>) {
+        if self.created_conduit && !self.update_shards(rocket).await {
+            return;
+        }
+        //Broadcasters linked before this start (or before a new conduit) need subscriptions on this conduit.
+        match rocket.state::<std::sync::Arc<crate::rocket::auth::Auth>>() {
+            Some(auth) => auth.twitch.subscribe_all_stream_events(self.created_conduit).await,
+            None => tracing::error!("Twitch client is missing in the Rocket state. Cannot subscribe to stream events."),
+        }
+    }
+}
+
+impl TwitchRocketCallback {
+    /// Points the conduit at our webhook. Shuts Rocket down and returns `false`, if that fails.
+    async fn update_shards(&self, rocket: &rocket::Rocket<rocket::Orbit>) -> bool {
         match self.client.update_conduit_shards(
             &self.conduit.id,
             vec![
@@ -30,14 +46,17 @@ impl rocket::fairing::Fairing for TwitchRocketCallback {
             Err(e) => {
                 tracing::error!("Failed to update conduit shards: {e:?}");
                 rocket.shutdown().await;
+                false
             }
             Ok(v) if !v.errors.is_empty() => {
                 tracing::error!("Failed to update some conduit shards: {v:?}");
                 rocket.shutdown().await;
+                false
             },
             Ok(v) => {
                 let shards = v.shards;
                 tracing::info!("Updated conduit shards: {shards:?}");
+                true
             }
         }
     }